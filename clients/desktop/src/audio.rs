@@ -1,11 +1,19 @@
-use ironboy_core::{SAMPLES_PER_FRAME, SAMPLING_FREQUENCY};
+use getset::CopyGetters;
+use ironboy_core::{SAMPLES_PER_FRAME, SampleRate};
 use sdl2::{
     Sdl,
     audio::{AudioCallback, AudioDevice, AudioSpecDesired},
 };
 use thiserror::Error;
 
-use std::collections::VecDeque;
+use std::{collections::VecDeque, time::Duration};
+
+/// Small device buffers keep the queue level, and so the rate control, from jumping around
+const DEVICE_BUFFER_SAMPLES: u16 = 512;
+/// Largest change to the output rate, far below what can be heard as pitch
+const MAX_RATE_ADJUSTMENT: f64 = 0.005;
+/// How quickly the averaged queue level follows the real one, per frame
+const QUEUE_SMOOTHING: f64 = 0.05;
 
 #[derive(Error, Debug)]
 pub enum AudioError {
@@ -15,22 +23,49 @@ pub enum AudioError {
     PlaybackError(String),
 }
 
+#[derive(CopyGetters)]
 pub struct GbAudio {
     left_buffer: VecDeque<f32>,
     right_buffer: VecDeque<f32>,
+    /// Playback waits for this many samples, at the start and after an underrun
+    target_samples: usize,
+    /// Samples past this are dropped from the front of the queue
+    max_samples: usize,
+    playing: bool,
+    /// Callbacks that ran out of samples
+    #[getset(get_copy = "pub")]
+    underruns: u32,
+    /// Times samples were dropped because the queue was full
+    #[getset(get_copy = "pub")]
+    overruns: u32,
 }
 
 impl GbAudio {
-    pub fn new() -> Self {
+    pub fn new(rate_control: &RateControl) -> Self {
         Self {
             left_buffer: VecDeque::new(),
             right_buffer: VecDeque::new(),
+            target_samples: rate_control.target_samples(),
+            max_samples: rate_control.max_samples(),
+            playing: false,
+            underruns: 0,
+            overruns: 0,
         }
     }
 
     pub fn queue_samples(&mut self, left: &[f32], right: &[f32]) {
         self.left_buffer.extend(left.iter());
         self.right_buffer.extend(right.iter());
+        if self.left_buffer.len() >= self.target_samples {
+            self.playing = true;
+        }
+
+        let excess = self.left_buffer.len().saturating_sub(self.max_samples);
+        if excess > 0 {
+            self.left_buffer.drain(..excess);
+            self.right_buffer.drain(..excess.min(self.right_buffer.len()));
+            self.overruns += 1;
+        }
     }
 
     pub fn sample_count(&self) -> usize {
@@ -42,6 +77,14 @@ impl AudioCallback for GbAudio {
     type Channel = f32;
 
     fn callback(&mut self, out: &mut [f32]) {
+        if !self.playing {
+            out.fill(0.0);
+            return;
+        }
+        if self.left_buffer.len() < out.len() / 2 {
+            self.underruns += 1;
+            self.playing = false;
+        }
         for (i, sample) in out.iter_mut().enumerate() {
             if i % 2 == 0 {
                 *sample = self.left_buffer.pop_front().unwrap_or(0.0);
@@ -52,14 +95,46 @@ impl AudioCallback for GbAudio {
     }
 }
 
-pub fn create_audio_device(sdl_context: &Sdl) -> Result<AudioDevice<GbAudio>, AudioError> {
+/// Steers the emulated output rate so the queue stays near a target latency, instead of pacing the emulator by audio
+pub struct RateControl {
+    target_samples: f64,
+    average_samples: f64,
+}
+
+impl RateControl {
+    pub fn new(sample_rate: SampleRate, latency: Duration) -> Self {
+        let target_samples = latency.as_secs_f64() * sample_rate.hz() as f64;
+        RateControl {
+            target_samples,
+            average_samples: target_samples,
+        }
+    }
+
+    pub fn target_samples(&self) -> usize {
+        self.target_samples as usize
+    }
+
+    /// The most samples worth queueing, twice the target with room for a frame on top
+    pub fn max_samples(&self) -> usize {
+        (2.0 * self.target_samples) as usize + SAMPLES_PER_FRAME
+    }
+
+    /// The rate adjustment for the next frame, above 1 when fewer samples than the target are queued
+    pub fn adjustment(&mut self, queued_samples: usize) -> f64 {
+        self.average_samples += (queued_samples as f64 - self.average_samples) * QUEUE_SMOOTHING;
+        let error = (self.target_samples - self.average_samples) / self.target_samples;
+        1.0 + (error * MAX_RATE_ADJUSTMENT).clamp(-MAX_RATE_ADJUSTMENT, MAX_RATE_ADJUSTMENT)
+    }
+}
+
+pub fn create_audio_device(sdl_context: &Sdl, sample_rate: SampleRate, rate_control: &RateControl) -> Result<AudioDevice<GbAudio>, AudioError> {
     let audio_spec_desired = AudioSpecDesired {
-        freq: Some(SAMPLING_FREQUENCY as i32),
-        samples: Some(SAMPLES_PER_FRAME as u16),
+        freq: Some(sample_rate.hz() as i32),
+        samples: Some(DEVICE_BUFFER_SAMPLES),
         channels: Some(2),
     };
 
-    let audio = GbAudio::new();
+    let audio = GbAudio::new(rate_control);
     let audio_subsystem = sdl_context.audio().map_err(AudioError::AudioSubsystemError)?;
     let device = audio_subsystem
         .open_playback(None, &audio_spec_desired, |_spec| audio)
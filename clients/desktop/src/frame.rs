@@ -2,7 +2,83 @@ use getset::CopyGetters;
 use ironboy_core::FPS;
 
 const FRAME_DURATION_NANOS: f32 = 1_000_000_000.0 / FPS;
-const FRAME_DURATION: std::time::Duration = std::time::Duration::from_nanos(FRAME_DURATION_NANOS as u64);
+pub const FRAME_DURATION: std::time::Duration = std::time::Duration::from_nanos(FRAME_DURATION_NANOS as u64);
+
+/// How fast emulation runs compared to the console
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub enum Speed {
+    Quarter,
+    Half,
+    #[default]
+    Normal,
+    Double,
+    Quadruple,
+    Octuple,
+    /// As fast as the host can go
+    Uncapped,
+}
+
+impl Speed {
+    const ALL: [Speed; 7] = [
+        Speed::Quarter,
+        Speed::Half,
+        Speed::Normal,
+        Speed::Double,
+        Speed::Quadruple,
+        Speed::Octuple,
+        Speed::Uncapped,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Speed::Quarter => "0.25x",
+            Speed::Half => "0.5x",
+            Speed::Normal => "1x",
+            Speed::Double => "2x",
+            Speed::Quadruple => "4x",
+            Speed::Octuple => "8x",
+            Speed::Uncapped => "uncapped",
+        }
+    }
+
+    /// None when uncapped
+    pub fn multiplier(self) -> Option<f64> {
+        match self {
+            Speed::Quarter => Some(0.25),
+            Speed::Half => Some(0.5),
+            Speed::Normal => Some(1.0),
+            Speed::Double => Some(2.0),
+            Speed::Quadruple => Some(4.0),
+            Speed::Octuple => Some(8.0),
+            Speed::Uncapped => None,
+        }
+    }
+
+    pub fn faster(self) -> Self {
+        let index = Self::ALL.iter().position(|speed| *speed == self).unwrap_or(0);
+        Self::ALL[(index + 1).min(Self::ALL.len() - 1)]
+    }
+
+    pub fn slower(self) -> Self {
+        let index = Self::ALL.iter().position(|speed| *speed == self).unwrap_or(0);
+        Self::ALL[index.saturating_sub(1)]
+    }
+
+    /// Frames run for every frame shown, the others are skipped so presenting does not hold emulation back.
+    /// None when uncapped, which runs frames until a frame's worth of real time has passed
+    pub fn frames_per_present(self) -> Option<usize> {
+        self.multiplier().map(|multiplier| multiplier.max(1.0) as usize)
+    }
+
+    /// Real time between two shown frames, slow motion shows every frame for longer
+    pub fn present_interval(self) -> std::time::Duration {
+        match self.multiplier() {
+            Some(multiplier) if multiplier < 1.0 => FRAME_DURATION.div_f64(multiplier),
+            Some(_) => FRAME_DURATION,
+            None => std::time::Duration::ZERO,
+        }
+    }
+}
 
 #[derive(CopyGetters)]
 pub struct FrameTimer {
@@ -23,11 +99,11 @@ impl FrameTimer {
         }
     }
 
-    pub fn slow_frame(&mut self) {
+    pub fn slow_frame(&mut self, frame_duration: std::time::Duration) {
         // slows frame rate down to the expected fps
         let time_elapsed = self.frame_clock.elapsed();
-        if time_elapsed < FRAME_DURATION {
-            std::thread::sleep(FRAME_DURATION - time_elapsed);
+        if time_elapsed < frame_duration {
+            std::thread::sleep(frame_duration - time_elapsed);
         }
         self.frame_clock = std::time::Instant::now();
     }
@@ -1,26 +1,43 @@
-use ironboy_core::{GameBoy, JoypadButton, SAMPLES_PER_FRAME, gb::GameBoyError};
-use std::{fs::File, io::Read};
+use ironboy_core::{
+    AudioChannel, AudioOutput, ColorCorrection, GameBoy, GameBoyConfig, GbMode, JoypadButton, LoadMode, PalettePreset, RenderOptions, SampleRate,
+    gb::GameBoyError,
+    gbs::{GbsError, GbsPlayer},
+    wav::{AudioRecorder, WavFormat},
+};
+use std::{
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 
 use sdl2::{
     EventPump,
     audio::AudioDevice,
     event::{Event, WindowEvent},
-    keyboard::Keycode,
+    keyboard::{Keycode, Mod, Scancode},
 };
 use thiserror::Error;
+use tracing::{info, warn};
 
 use crate::{
-    audio::{AudioError, GbAudio, create_audio_device},
-    frame::FrameTimer,
+    audio::{AudioError, GbAudio, RateControl, create_audio_device},
+    frame::{FRAME_DURATION, FrameTimer, Speed},
     logger::initilize_logger,
+    viewers::Viewers,
     window::{WindowError, WindowManager},
 };
 
 mod audio;
 mod frame;
 mod logger;
+mod viewers;
 mod window;
 
+/// GBS songs usually loop forever, the player moves on after this long
+const TRACK_SECONDS: f64 = 150.0;
+const FADE_SECONDS: f64 = 8.0;
+
 #[derive(Error, Debug)]
 pub enum ApplicationError {
     #[error("Failed to initialize SDL context: {0}")]
@@ -35,38 +52,90 @@ pub enum ApplicationError {
     RomReadError(#[from] std::io::Error),
     #[error("There was a game boy error: {0}")]
     GameBoyError(#[from] GameBoyError),
+    #[error("There was a GBS error: {0}")]
+    GbsError(#[from] GbsError),
+}
+
+/// How the R hotkey records audio
+#[derive(Debug, Default, Copy, Clone)]
+pub struct RecordingOptions {
+    pub format: WavFormat,
+    /// Also write every channel to its own file
+    pub stems: bool,
 }
 
 pub struct Application {
     game_boy: Option<GameBoy>,
+    /// Set instead of `game_boy` while a GBS file plays
+    player: Option<GbsPlayer>,
+    rom_path: Option<String>,
+    palette_preset: PalettePreset,
+    color_correction: ColorCorrection,
+    audio_output: AudioOutput,
+    render_options: RenderOptions,
+    sample_rate: SampleRate,
+    recording_options: RecordingOptions,
+    recorder: Option<AudioRecorder>,
+    viewers: Viewers,
     audio_device: AudioDevice<GbAudio>,
+    rate_control: RateControl,
+    speed: Speed,
+    /// Held down for uncapped speed
+    turbo: bool,
+    paused: bool,
+    /// Runs one frame while paused
+    advance_frame: bool,
+    /// How much faster than the console the last uncapped frames ran
+    uncapped_speed: f64,
     window_manager: WindowManager,
     event_pump: EventPump,
     frame_timer: FrameTimer,
 }
 
 impl Application {
-    pub fn new(rom_path: Option<String>) -> Result<Application, ApplicationError> {
+    /// `audio_latency` is how much audio the queue holds on average
+    pub fn new(
+        rom_path: Option<String>,
+        sample_rate: SampleRate,
+        audio_latency: Duration,
+        recording_options: RecordingOptions,
+    ) -> Result<Application, ApplicationError> {
         initilize_logger();
         let sdl_context = sdl2::init().map_err(ApplicationError::SdlInitError)?;
 
-        let audio_device = create_audio_device(&sdl_context)?;
+        let rate_control = RateControl::new(sample_rate, audio_latency);
+        let audio_device = create_audio_device(&sdl_context, sample_rate, &rate_control)?;
         let window_manager = WindowManager::new(&sdl_context)?;
         let event_pump = sdl_context.event_pump().map_err(ApplicationError::EventPumpError)?;
 
-        let game_boy = match rom_path {
-            Some(rom_path) => Some(GameBoy::new(&rom_path, read_rom(&rom_path)?)?),
-            None => None,
-        };
-
-        let desktop = Self {
-            game_boy,
+        let mut desktop = Self {
+            game_boy: None,
+            player: None,
+            rom_path: None,
+            palette_preset: PalettePreset::default(),
+            color_correction: ColorCorrection::default(),
+            audio_output: AudioOutput::default(),
+            render_options: RenderOptions::default(),
+            sample_rate,
+            recording_options,
+            recorder: None,
+            viewers: Viewers::default(),
             audio_device,
+            rate_control,
+            speed: Speed::default(),
+            turbo: false,
+            paused: false,
+            advance_frame: false,
+            uncapped_speed: 1.0,
             window_manager,
             event_pump,
             frame_timer: FrameTimer::new(),
         };
 
+        if let Some(rom_path) = rom_path {
+            desktop.load_rom(rom_path)?;
+        }
+
         Ok(desktop)
     }
 
@@ -75,7 +144,12 @@ impl Application {
         let main_window_id = self.window_manager.main_canvas().window().id();
 
         'game: loop {
-            for event in self.event_pump.poll_iter() {
+            let events: Vec<Event> = self.event_pump.poll_iter().collect();
+            for event in events {
+                if self.viewers.handle_event(&event, &mut self.window_manager, self.game_boy.as_ref())? {
+                    continue;
+                }
+
                 match event {
                     Event::Quit { .. } => break 'game,
                     Event::KeyDown {
@@ -98,9 +172,61 @@ impl Application {
                     }
                     Event::DropFile { window_id, filename, .. } => {
                         if window_id == main_window_id {
-                            self.game_boy = Some(GameBoy::new(&filename, read_rom(&filename)?)?);
+                            self.load_rom(filename)?;
                         }
                     }
+                    Event::KeyDown {
+                        keycode: Some(Keycode::P),
+                        keymod,
+                        repeat: false,
+                        ..
+                    } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => self.toggle_pause(),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::N), ..
+                    } if self.paused => self.advance_frame = true,
+                    Event::KeyDown {
+                        keycode: Some(Keycode::Tab), ..
+                    } => self.turbo = true,
+                    Event::KeyUp {
+                        keycode: Some(Keycode::Tab), ..
+                    } => self.turbo = false,
+                    Event::KeyDown {
+                        keycode: Some(keycode @ (Keycode::LeftBracket | Keycode::RightBracket)),
+                        ..
+                    } => self.change_speed(keycode),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::P), ..
+                    } => self.cycle_palette_preset(),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::C), ..
+                    } => self.cycle_color_correction(),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::A), ..
+                    } => self.cycle_audio_output(),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::R),
+                        repeat: false,
+                        ..
+                    } => self.toggle_recording(),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::V),
+                        repeat: false,
+                        ..
+                    } => self.toggle_vgm_log(),
+                    Event::KeyDown {
+                        keycode: Some(keycode @ (Keycode::Left | Keycode::Right | Keycode::F)),
+                        repeat: false,
+                        ..
+                    } if self.player.is_some() => self.control_player(keycode)?,
+                    Event::KeyDown {
+                        keycode: Some(keycode @ (Keycode::F1 | Keycode::F2 | Keycode::F3 | Keycode::F4 | Keycode::F5)),
+                        ..
+                    } => self.toggle_render_option(keycode),
+                    Event::KeyDown {
+                        keycode: Some(keycode @ (Keycode::Num1 | Keycode::Num2 | Keycode::Num3 | Keycode::Num4)),
+                        keymod,
+                        ..
+                    } => self.toggle_channel(keycode, keymod),
                     Event::KeyDown { keycode, .. } => {
                         if let Some(ref mut game_boy) = self.game_boy {
                             match keycode {
@@ -136,33 +262,409 @@ impl Application {
             }
 
             self.run_game_boy()?;
+            self.run_player()?;
         }
 
+        self.stop_recording();
+        self.save_vgm_log();
         Ok(())
     }
 
-    fn run_game_boy(&mut self) -> Result<(), ApplicationError> {
+    fn load_rom(&mut self, rom_path: String) -> Result<(), ApplicationError> {
+        self.stop_recording();
+        self.save_vgm_log();
+        if Path::new(&rom_path)
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("gbs"))
+        {
+            return self.load_gbs(rom_path);
+        }
+        let mut game_boy = load_game_boy(&rom_path, self.sample_rate, &self.event_pump)?;
+        self.palette_preset = saved_palette_preset(&rom_path);
+        game_boy.set_dmg_palette(self.palette_preset.palette());
+        game_boy.set_color_correction(self.color_correction);
+        game_boy.set_audio_output(self.audio_output);
+        game_boy.set_render_options(self.render_options);
+        self.game_boy = Some(game_boy);
+        self.player = None;
+        self.rom_path = Some(rom_path);
+        Ok(())
+    }
+
+    fn load_gbs(&mut self, gbs_path: String) -> Result<(), ApplicationError> {
+        let config = GameBoyConfig {
+            sample_rate: self.sample_rate,
+            audio_output: self.audio_output,
+            ..Default::default()
+        };
+        let player = GbsPlayer::new(&gbs_path, &read_rom(&gbs_path)?, config)?;
+        info!(
+            "Playing {} by {}, {} songs",
+            player.gbs().title(),
+            player.gbs().author(),
+            player.gbs().songs()
+        );
+        self.player = Some(player);
+        self.game_boy = None;
+        self.rom_path = Some(gbs_path);
+        Ok(())
+    }
+
+    /// Left and Right pick the track, F fades the current one out
+    fn control_player(&mut self, keycode: Keycode) -> Result<(), ApplicationError> {
+        let Some(ref mut player) = self.player else {
+            return Ok(());
+        };
+        match keycode {
+            Keycode::Left => player.previous_track()?,
+            Keycode::Right => player.next_track()?,
+            Keycode::F => player.fade_out(FADE_SECONDS),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// The Game Boy that is running, on its own or inside the GBS player
+    fn active_game_boy_mut(&mut self) -> Option<&mut GameBoy> {
+        match self.player {
+            Some(ref mut player) => Some(player.game_boy_mut()),
+            None => self.game_boy.as_mut(),
+        }
+    }
+
+    fn cycle_palette_preset(&mut self) {
+        let (Some(game_boy), Some(rom_path)) = (&mut self.game_boy, &self.rom_path) else {
+            return;
+        };
+        // CGB games and compatibility mode color with CGB palettes, so there is nothing to change or remember
+        if game_boy.mode() != GbMode::Monochrome {
+            return;
+        }
+
+        self.palette_preset = self.palette_preset.next();
+        game_boy.set_dmg_palette(self.palette_preset.palette());
+        if let Err(error) = fs::write(palette_file(rom_path), self.palette_preset.name()) {
+            warn!("Failed to save palette for {}: {}", rom_path, error);
+        }
+    }
+
+    fn cycle_color_correction(&mut self) {
+        self.color_correction = self.color_correction.next();
+        if let Some(ref mut game_boy) = self.game_boy {
+            game_boy.set_color_correction(self.color_correction);
+        }
+        info!("Color correction: {}", self.color_correction.name());
+    }
+
+    fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        // Audio stops with the emulator instead of running the queue dry
+        match self.paused {
+            true => self.audio_device.pause(),
+            false => self.audio_device.resume(),
+        }
+        info!("{}", if self.paused { "Paused" } else { "Resumed" });
+    }
+
+    /// ] speeds emulation up and [ slows it down
+    fn change_speed(&mut self, keycode: Keycode) {
+        self.speed = match keycode {
+            Keycode::RightBracket => self.speed.faster(),
+            _ => self.speed.slower(),
+        };
+        info!("Speed: {}", self.speed.name());
+    }
+
+    fn cycle_audio_output(&mut self) {
+        self.audio_output = self.audio_output.next();
+        let audio_output = self.audio_output;
+        if let Some(game_boy) = self.active_game_boy_mut() {
+            game_boy.set_audio_output(audio_output);
+        }
+        info!("Audio output: {}", self.audio_output.name());
+    }
+
+    fn toggle_render_option(&mut self, keycode: Keycode) {
+        let options = &mut self.render_options;
+        let (name, enabled) = match keycode {
+            Keycode::F1 => ("Background", &mut options.show_background),
+            Keycode::F2 => ("Window", &mut options.show_window),
+            Keycode::F3 => ("Objects", &mut options.show_objects),
+            Keycode::F4 => ("Object bounding boxes", &mut options.object_bounding_boxes),
+            Keycode::F5 => ("Unlimited objects per line", &mut options.unlimited_objects),
+            _ => return,
+        };
+        *enabled = !*enabled;
+        info!("{}: {}", name, if *enabled { "on" } else { "off" });
+
         if let Some(ref mut game_boy) = self.game_boy {
-            let audio_lock = self.audio_device.lock();
-            let sample_count = audio_lock.sample_count();
-            drop(audio_lock);
-
-            if sample_count < SAMPLES_PER_FRAME {
-                let (left_samples, right_samples) = game_boy.run_until_audio_buffer_full();
-                let mut audio_lock = self.audio_device.lock();
-                audio_lock.queue_samples(left_samples, right_samples);
-                drop(audio_lock)
+            game_boy.set_render_options(self.render_options);
+        }
+    }
+
+    fn toggle_recording(&mut self) {
+        if self.recorder.is_some() {
+            self.stop_recording();
+            return;
+        }
+        let (Some(game_boy), Some(rom_path)) = (&self.game_boy, &self.rom_path) else {
+            return;
+        };
+
+        let path = recording_file(rom_path, "wav");
+        let RecordingOptions { format, stems } = self.recording_options;
+        match AudioRecorder::start(&path, format, game_boy.sample_rate(), stems) {
+            Ok(recorder) => {
+                info!("Recording audio to {}", path.display());
+                self.recorder = Some(recorder);
             }
+            Err(error) => warn!("Failed to record audio to {}: {}", path.display(), error),
+        }
+    }
+
+    fn stop_recording(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            match recorder.finish() {
+                Ok(()) => info!("Stopped recording audio"),
+                Err(error) => warn!("Failed to finish audio recording: {}", error),
+            }
+        }
+    }
+
+    fn toggle_vgm_log(&mut self) {
+        let Some(ref mut game_boy) = self.game_boy else {
+            return;
+        };
+        if game_boy.vgm_logging() {
+            self.save_vgm_log();
+            return;
+        }
+        game_boy.start_vgm_log();
+        info!("Logging audio register writes");
+    }
+
+    /// Writes the running register log next to the ROM as a VGM file, ending at the loop point if one is found
+    fn save_vgm_log(&mut self) {
+        let (Some(game_boy), Some(rom_path)) = (&mut self.game_boy, &self.rom_path) else {
+            return;
+        };
+        let Some(vgm_log) = game_boy.stop_vgm_log() else {
+            return;
+        };
+        let path = recording_file(rom_path, "vgm");
+        match fs::write(&path, vgm_log.to_vgm(true)) {
+            Ok(()) => info!("Saved audio register log to {}", path.display()),
+            Err(error) => warn!("Failed to save audio register log to {}: {}", path.display(), error),
+        }
+    }
 
-            //TODO: make this toggleable
-            let fps = self.frame_timer.fps();
-            self.window_manager.render_screen(game_boy.current_frame(), Some(fps))?;
-            self.frame_timer.slow_frame();
-            self.frame_timer.count_frame();
+    /// 1-4 mute a channel, with Shift they solo it instead
+    fn toggle_channel(&mut self, keycode: Keycode, keymod: Mod) {
+        let Some(game_boy) = self.active_game_boy_mut() else {
+            return;
+        };
+        let channel = match keycode {
+            Keycode::Num1 => AudioChannel::Pulse1,
+            Keycode::Num2 => AudioChannel::Pulse2,
+            Keycode::Num3 => AudioChannel::Wave,
+            Keycode::Num4 => AudioChannel::Noise,
+            _ => return,
+        };
+
+        if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+            let soloed = !game_boy.channel_soloed(channel);
+            game_boy.set_channel_soloed(channel, soloed);
+            info!("{}: {}", channel.name(), if soloed { "soloed" } else { "not soloed" });
+        } else {
+            let muted = !game_boy.channel_muted(channel);
+            game_boy.set_channel_muted(channel, muted);
+            info!("{}: {}", channel.name(), if muted { "muted" } else { "on" });
         }
+    }
 
+    /// Video sets the pace and the audio rate is steered to keep the queue at its target. Faster speeds run several
+    /// frames per shown frame and make fewer samples per frame, slower ones show every frame for longer.
+    /// Recordings are made at the nominal rate, so they play back at normal speed whatever speed they were made at
+    fn run_game_boy(&mut self) -> Result<(), ApplicationError> {
+        let speed = if self.turbo { Speed::Uncapped } else { self.speed };
+        if !self.paused || self.advance_frame {
+            self.emulate_frames(speed);
+        }
+        self.advance_frame = false;
+        let Some(ref game_boy) = self.game_boy else {
+            return Ok(());
+        };
+
+        //TODO: make this toggleable
+        let audio_lock = self.audio_device.lock();
+        let latency = audio_lock.sample_count() as f64 * 1000.0 / self.sample_rate.hz() as f64;
+        let (underruns, overruns) = (audio_lock.underruns(), audio_lock.overruns());
+        drop(audio_lock);
+        let status = match (self.paused, speed) {
+            (true, _) => " paused".to_string(),
+            (false, Speed::Normal) => String::new(),
+            (false, speed) => format!(" {}", speed.name()),
+        };
+        let overlay = format!(
+            "{:.1} FPS {:.0} ms {} under {} over{}",
+            self.frame_timer.fps(),
+            latency,
+            underruns,
+            overruns,
+            status
+        );
+        self.window_manager.render_screen(game_boy.current_frame(), Some(&overlay))?;
+        self.viewers.render(game_boy)?;
+        let present_interval = if self.paused { FRAME_DURATION } else { speed.present_interval() };
+        self.frame_timer.slow_frame(present_interval);
+        self.frame_timer.count_frame();
         Ok(())
     }
+
+    /// Runs the frames behind one shown frame, only one while paused, and queues and records their audio
+    fn emulate_frames(&mut self, speed: Speed) {
+        let Some(ref mut game_boy) = self.game_boy else {
+            return;
+        };
+
+        let stems = self.recorder.as_ref().is_some_and(AudioRecorder::records_stems);
+        game_boy.set_channel_capture(stems || self.viewers.audio_open());
+        game_boy.set_recording(self.recorder.is_some());
+
+        let started = Instant::now();
+        let mut frames = 0;
+        loop {
+            let queued = self.audio_device.lock().sample_count();
+            let multiplier = speed.multiplier().unwrap_or(self.uncapped_speed);
+            game_boy.set_audio_rate_adjustment(self.rate_control.adjustment(queued) / multiplier);
+
+            let (left_samples, right_samples) = game_boy.run_frame_with_audio();
+            if !self.paused {
+                self.audio_device.lock().queue_samples(left_samples, right_samples);
+            }
+            if let Some(recorder) = &mut self.recorder {
+                let (left_samples, right_samples) = game_boy.recording_samples();
+                let channels = AudioChannel::ALL.map(|channel| game_boy.channel_samples(channel));
+                if let Err(error) = recorder.write(left_samples, right_samples, channels) {
+                    warn!("Stopped recording audio: {}", error);
+                    self.recorder = None;
+                }
+            }
+
+            frames += 1;
+            let presented = match speed.frames_per_present() {
+                Some(frames_per_present) => frames >= frames_per_present,
+                None => started.elapsed() >= FRAME_DURATION,
+            };
+            if presented || self.paused {
+                break;
+            }
+        }
+
+        if speed == Speed::Uncapped {
+            self.uncapped_speed = (frames as f64 * FRAME_DURATION.as_secs_f64() / started.elapsed().as_secs_f64()).max(1.0);
+        }
+    }
+
+    /// Tracks fade out after `TRACK_SECONDS` and the next one starts once they are silent
+    fn run_player(&mut self) -> Result<(), ApplicationError> {
+        let Some(ref mut player) = self.player else {
+            return Ok(());
+        };
+
+        let sample_count = self.audio_device.lock().sample_count();
+        if !self.paused && sample_count < self.rate_control.target_samples() {
+            let (left_samples, right_samples) = player.run_until_audio_buffer_full();
+            self.audio_device.lock().queue_samples(left_samples, right_samples);
+        }
+        if player.elapsed_seconds() >= TRACK_SECONDS {
+            player.fade_out(FADE_SECONDS);
+        }
+        if player.fade_finished() {
+            player.next_track()?;
+        }
+
+        let gbs = player.gbs();
+        let elapsed = player.elapsed_seconds() as u64;
+        let lines = [
+            gbs.title().clone(),
+            gbs.author().clone(),
+            gbs.copyright().clone(),
+            String::new(),
+            format!("Track {}/{}", player.track() + 1, gbs.songs()),
+            format!("{}:{:02}{}", elapsed / 60, elapsed % 60, if player.fading() { " fading" } else { "" }),
+            String::new(),
+            "Left/Right track".to_string(),
+            "F fade out".to_string(),
+        ];
+        self.window_manager.render_text(&lines)?;
+        self.frame_timer.slow_frame(FRAME_DURATION);
+        self.frame_timer.count_frame();
+        Ok(())
+    }
+}
+
+fn load_game_boy(rom_path: &str, sample_rate: SampleRate, event_pump: &EventPump) -> Result<GameBoy, ApplicationError> {
+    let config = GameBoyConfig {
+        load_mode: LoadMode::Lenient,
+        sample_rate,
+        ..Default::default()
+    };
+    let mut game_boy = GameBoy::with_config(rom_path, read_rom(rom_path)?, config)?;
+    for warning in game_boy.load_warnings() {
+        warn!("{}: {}", rom_path, warning);
+    }
+    select_compatibility_palette(&mut game_boy, event_pump);
+    Ok(game_boy)
+}
+
+// Same as the CGB boot ROM, a direction held while loading, optionally with A or B, picks the palette
+fn select_compatibility_palette(game_boy: &mut GameBoy, event_pump: &EventPump) {
+    let keyboard = event_pump.keyboard_state();
+    let held = |scancode| keyboard.is_scancode_pressed(scancode);
+
+    let direction = [
+        (Scancode::Right, JoypadButton::Right),
+        (Scancode::Left, JoypadButton::Left),
+        (Scancode::Up, JoypadButton::Up),
+        (Scancode::Down, JoypadButton::Down),
+    ]
+    .into_iter()
+    .find(|(scancode, _)| held(*scancode));
+
+    let modifier = if held(Scancode::X) {
+        Some(JoypadButton::A)
+    } else if held(Scancode::Z) {
+        Some(JoypadButton::B)
+    } else {
+        None
+    };
+
+    if let Some((_, direction)) = direction {
+        game_boy.select_compatibility_palette(direction, modifier);
+    }
+}
+
+// Remembered next to the ROM, the same way battery saves are
+fn palette_file(rom_path: &str) -> PathBuf {
+    Path::new(rom_path).with_extension("palette")
+}
+
+/// The first `<rom name>-<n>.<extension>` next to the ROM that does not exist yet
+fn recording_file(rom_path: &str, extension: &str) -> PathBuf {
+    let path = Path::new(rom_path);
+    let name = path.file_stem().unwrap_or_default().to_string_lossy();
+    (1..)
+        .map(|number| path.with_file_name(format!("{}-{}.{}", name, number, extension)))
+        .find(|file| !file.exists())
+        .unwrap_or_else(|| path.with_extension(extension))
+}
+
+fn saved_palette_preset(rom_path: &str) -> PalettePreset {
+    fs::read_to_string(palette_file(rom_path))
+        .ok()
+        .and_then(|name| PalettePreset::from_name(name.trim()))
+        .unwrap_or_default()
 }
 
 fn read_rom(rom_path: &str) -> Result<Vec<u8>, ApplicationError> {
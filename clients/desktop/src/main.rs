@@ -1,10 +1,37 @@
-use desktop::Application;
+use desktop::{Application, RecordingOptions};
+use ironboy_core::{SampleRate, wav::WavFormat};
 
-use std::env;
+use std::{env, time::Duration};
+
+const DEFAULT_AUDIO_LATENCY_MS: u64 = 60;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let rom_path = env::args().nth(1);
-    let mut application = Application::new(rom_path)?;
+    let mut rom_path = None;
+    let mut sample_rate = SampleRate::default();
+    let mut audio_latency = Duration::from_millis(DEFAULT_AUDIO_LATENCY_MS);
+    let mut recording_options = RecordingOptions::default();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--sample-rate" => {
+                let hz = args.next().and_then(|hz| hz.parse().ok()).and_then(SampleRate::from_hz);
+                sample_rate = hz.ok_or("--sample-rate expects 22050, 32000, 44100, 48000 or 96000")?;
+            }
+            "--audio-latency" => {
+                let ms = args.next().and_then(|ms| ms.parse().ok()).filter(|ms| *ms > 0);
+                audio_latency = Duration::from_millis(ms.ok_or("--audio-latency expects a number of milliseconds")?);
+            }
+            "--record-format" => {
+                let format = args.next().and_then(|name| WavFormat::from_name(&name));
+                recording_options.format = format.ok_or("--record-format expects pcm16 or float")?;
+            }
+            "--record-stems" => recording_options.stems = true,
+            _ => rom_path = Some(arg),
+        }
+    }
+
+    let mut application = Application::new(rom_path, sample_rate, audio_latency, recording_options)?;
     application.run()?;
     Ok(())
 }
@@ -0,0 +1,224 @@
+use ironboy_core::{GameBoy, PixelFormat};
+use sdl2::{
+    event::{Event, WindowEvent},
+    keyboard::Keycode,
+    pixels::{Color, PixelFormatEnum},
+    rect::Rect,
+    render::{Canvas, TextureCreator},
+    ttf::Font,
+    video::{Window, WindowContext},
+};
+
+use crate::{
+    viewers::{audio::AudioViewer, palettes::PaletteViewer, sprites::SpriteViewer, tile_map::TileMapViewer, tiles::TileViewer},
+    window::{StreamingTexture, WindowError, WindowManager},
+};
+
+pub mod audio;
+pub mod palettes;
+pub mod sprites;
+pub mod tile_map;
+pub mod tiles;
+
+const VIEWER_SCALE: u32 = 3;
+const VIEWER_SPACING: i32 = 24;
+
+pub trait Viewer {
+    fn id(&self) -> u32;
+    /// Returns true when the event was only meant for the viewer
+    fn handle_event(&mut self, event: &Event, game_boy: Option<&GameBoy>) -> bool;
+    fn render(&mut self, game_boy: &GameBoy) -> Result<(), WindowError>;
+}
+
+/// The debug windows that are open, each one opens and closes with its own key
+#[derive(Default)]
+pub struct Viewers {
+    tiles: Option<TileViewer>,
+    tile_map: Option<TileMapViewer>,
+    sprites: Option<SpriteViewer>,
+    palettes: Option<PaletteViewer>,
+    audio: Option<AudioViewer>,
+}
+
+impl Viewers {
+    /// Returns true when the event was only meant for the viewers
+    pub fn handle_event(&mut self, event: &Event, window_manager: &mut WindowManager, game_boy: Option<&GameBoy>) -> Result<bool, WindowError> {
+        if let Event::KeyDown {
+            keycode: Some(keycode),
+            repeat: false,
+            ..
+        } = event
+        {
+            match *keycode {
+                Keycode::F6 => return toggle(&mut self.tiles, || TileViewer::new(window_manager)),
+                Keycode::F7 => return toggle(&mut self.tile_map, || TileMapViewer::new(window_manager)),
+                Keycode::F8 => return toggle(&mut self.sprites, || SpriteViewer::new(window_manager)),
+                Keycode::F9 => return toggle(&mut self.palettes, || PaletteViewer::new(window_manager)),
+                Keycode::F10 => return toggle(&mut self.audio, || AudioViewer::new(window_manager)),
+                _ => {}
+            }
+        }
+
+        let handled = route_event(&mut self.tiles, event, game_boy)
+            .or_else(|| route_event(&mut self.tile_map, event, game_boy))
+            .or_else(|| route_event(&mut self.sprites, event, game_boy))
+            .or_else(|| route_event(&mut self.palettes, event, game_boy))
+            .or_else(|| route_event(&mut self.audio, event, game_boy));
+        Ok(handled.unwrap_or(false))
+    }
+
+    /// The audio viewer draws the channel streams, so they need capturing while it is open
+    pub fn audio_open(&self) -> bool {
+        self.audio.is_some()
+    }
+
+    pub fn render(&mut self, game_boy: &GameBoy) -> Result<(), WindowError> {
+        render(&mut self.tiles, game_boy)?;
+        render(&mut self.tile_map, game_boy)?;
+        render(&mut self.sprites, game_boy)?;
+        render(&mut self.palettes, game_boy)?;
+        render(&mut self.audio, game_boy)
+    }
+}
+
+fn toggle<T>(viewer: &mut Option<T>, open: impl FnOnce() -> Result<T, WindowError>) -> Result<bool, WindowError> {
+    *viewer = match viewer {
+        Some(_) => None,
+        None => Some(open()?),
+    };
+    Ok(true)
+}
+
+/// Gives the event to the viewer if it happened in its window, closing the window drops the viewer
+fn route_event<T: Viewer>(viewer: &mut Option<T>, event: &Event, game_boy: Option<&GameBoy>) -> Option<bool> {
+    let open = viewer.as_mut()?;
+    if event.get_window_id() != Some(open.id()) {
+        return None;
+    }
+
+    if let Event::Window {
+        win_event: WindowEvent::Close,
+        ..
+    } = event
+    {
+        *viewer = None;
+        return Some(true);
+    }
+    Some(open.handle_event(event, game_boy))
+}
+
+fn render<T: Viewer>(viewer: &mut Option<T>, game_boy: &GameBoy) -> Result<(), WindowError> {
+    match viewer {
+        Some(viewer) => viewer.render(game_boy),
+        None => Ok(()),
+    }
+}
+
+/// A debug window showing an RGBA8888 image that is redrawn every frame
+pub struct ViewerWindow {
+    image_texture: StreamingTexture,
+    canvas: Canvas<Window>,
+    texture_creator: TextureCreator<WindowContext>,
+    width: u32,
+    height: u32,
+}
+
+impl ViewerWindow {
+    /// Opens next to the main window, `slot` staggers viewers so they do not cover each other
+    pub fn new(window_manager: &mut WindowManager, title: &str, width: u32, height: u32, slot: i32) -> Result<Self, WindowError> {
+        let main_window = window_manager.main_canvas().window();
+        let (main_x, main_y) = main_window.position();
+        let (main_width, _) = main_window.size();
+        let x = main_x + main_width as i32 + VIEWER_SPACING * (slot + 1);
+        let y = main_y + VIEWER_SPACING * slot;
+
+        let mut canvas = window_manager.create_canvas(title, width * VIEWER_SCALE, height * VIEWER_SCALE, x, y)?;
+        // Mouse positions are reported in image pixels
+        canvas.set_logical_size(width, height)?;
+        let texture_creator = canvas.texture_creator();
+        let image_texture = StreamingTexture::new(canvas.texture_creator());
+
+        Ok(Self {
+            image_texture,
+            canvas,
+            texture_creator,
+            width,
+            height,
+        })
+    }
+
+    pub fn id(&self) -> u32 {
+        self.canvas.window().id()
+    }
+
+    pub fn set_title(&mut self, title: &str) -> Result<(), WindowError> {
+        self.canvas
+            .window_mut()
+            .set_title(title)
+            .map_err(|e| WindowError::CanvasError(e.to_string()))
+    }
+
+    pub fn present(&mut self, image: &Image) -> Result<(), WindowError> {
+        self.present_with_text(image, None, &[])
+    }
+
+    /// Draws each `(x, y, text)` over the image, positions are in image pixels
+    pub fn present_with_text(&mut self, image: &Image, font: Option<&Font>, lines: &[(i32, i32, String)]) -> Result<(), WindowError> {
+        let texture = self
+            .image_texture
+            .update(PixelFormatEnum::RGBA8888, self.width, self.height, &image.pixels, image.pitch())?;
+        self.canvas.copy(texture, None, None).map_err(WindowError::CanvasError)?;
+
+        if let Some(font) = font {
+            for (x, y, text) in lines.iter().filter(|(_, _, text)| !text.is_empty()) {
+                let surface = font
+                    .render(text)
+                    .solid(Color::RGB(230, 230, 230))
+                    .map_err(|e| WindowError::TextRenderError(e.to_string()))?;
+                let texture = self
+                    .texture_creator
+                    .create_texture_from_surface(&surface)
+                    .map_err(|e| WindowError::TextureError(e.to_string()))?;
+                let target = Rect::new(*x, *y, surface.width(), surface.height());
+                self.canvas.copy(&texture, None, target).map_err(WindowError::CanvasError)?;
+            }
+        }
+
+        self.canvas.present();
+        Ok(())
+    }
+}
+
+/// An RGBA8888 image in the same layout the core produces frames in
+pub struct Image {
+    width: usize,
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, background: (u8, u8, u8)) -> Self {
+        let mut image = Image {
+            width,
+            pixels: vec![0; width * height * PixelFormat::Rgba8888.bytes_per_pixel()],
+        };
+        image.fill_rect(0, 0, width, height, background);
+        image
+    }
+
+    pub fn put(&mut self, x: usize, y: usize, (r, g, b): (u8, u8, u8)) {
+        let start = (y * self.width + x) * PixelFormat::Rgba8888.bytes_per_pixel();
+        self.pixels[start..start + 4].copy_from_slice(&u32::from_be_bytes([r, g, b, 0xFF]).to_ne_bytes());
+    }
+
+    pub fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: (u8, u8, u8)) {
+        for row in y..y + height {
+            for column in x..x + width {
+                self.put(column, row, color);
+            }
+        }
+    }
+
+    fn pitch(&self) -> usize {
+        self.width * PixelFormat::Rgba8888.bytes_per_pixel()
+    }
+}
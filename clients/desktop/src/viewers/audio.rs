@@ -0,0 +1,150 @@
+use ironboy_core::{AudioChannel, ChannelView, GameBoy};
+use sdl2::{event::Event, ttf::Font};
+
+use crate::{
+    viewers::{Image, Viewer, ViewerWindow},
+    window::{WindowError, WindowManager},
+};
+
+const FONT_SIZE: u16 = 8;
+const LINE_HEIGHT: usize = 9;
+const MARGIN: usize = 4;
+const SCOPE_WIDTH: usize = 256;
+/// Leaves room for 4 lines of text above the wave RAM bars
+const SCOPE_HEIGHT: usize = 72;
+const ROW_HEIGHT: usize = SCOPE_HEIGHT + 2 * MARGIN;
+const INFO_X: usize = SCOPE_WIDTH + 3 * MARGIN;
+const WIDTH: usize = INFO_X + 200;
+const HEIGHT: usize = 4 * ROW_HEIGHT;
+const BAR_WIDTH: usize = 4;
+const BAR_STEP: usize = 2;
+/// Changes smaller than this are not a waveform to trigger on
+const MIN_TRIGGER_SWING: f32 = 0.01;
+const BACKGROUND: (u8, u8, u8) = (45, 45, 45);
+const SCOPE_BACKGROUND: (u8, u8, u8) = (20, 20, 20);
+const CENTER_LINE: (u8, u8, u8) = (60, 60, 60);
+const WAVEFORM: (u8, u8, u8) = (80, 220, 120);
+const SILENCED_WAVEFORM: (u8, u8, u8) = (110, 110, 110);
+const WAVE_RAM: (u8, u8, u8) = (90, 160, 230);
+
+/// An oscilloscope and the decoded registers of every APU channel
+pub struct AudioViewer {
+    window: ViewerWindow,
+    font: Font<'static, 'static>,
+}
+
+impl AudioViewer {
+    pub fn new(window_manager: &mut WindowManager) -> Result<Self, WindowError> {
+        Ok(AudioViewer {
+            window: ViewerWindow::new(window_manager, "Audio", WIDTH as u32, HEIGHT as u32, 4)?,
+            font: window_manager.load_font(FONT_SIZE)?,
+        })
+    }
+}
+
+impl Viewer for AudioViewer {
+    fn id(&self) -> u32 {
+        self.window.id()
+    }
+
+    fn handle_event(&mut self, _event: &Event, _game_boy: Option<&GameBoy>) -> bool {
+        false
+    }
+
+    fn render(&mut self, game_boy: &GameBoy) -> Result<(), WindowError> {
+        let mut image = Image::new(WIDTH, HEIGHT, BACKGROUND);
+        let mut lines = Vec::new();
+
+        for (row, view) in game_boy.channel_views().iter().enumerate() {
+            let top = row * ROW_HEIGHT;
+            let silenced = game_boy.channel_muted(view.channel)
+                || (!game_boy.channel_soloed(view.channel) && AudioChannel::ALL.iter().any(|channel| game_boy.channel_soloed(*channel)));
+            let color = if silenced { SILENCED_WAVEFORM } else { WAVEFORM };
+            draw_scope(&mut image, top + MARGIN, game_boy.channel_samples(view.channel), color);
+
+            if let Some(wave_ram) = view.wave_ram {
+                let bottom = top + MARGIN + SCOPE_HEIGHT;
+                for (i, sample) in wave_ram.iter().enumerate() {
+                    let height = (*sample as usize + 1) * BAR_STEP;
+                    image.fill_rect(INFO_X + i * BAR_WIDTH, bottom - height, BAR_WIDTH - 1, height, WAVE_RAM);
+                }
+            }
+            for (i, line) in describe(view, game_boy).into_iter().enumerate() {
+                lines.push((INFO_X as i32, (top + MARGIN + i * LINE_HEIGHT) as i32, line));
+            }
+        }
+
+        self.window.present_with_text(&image, Some(&self.font), &lines)
+    }
+}
+
+/// Draws the samples starting at the first rising edge through their middle, so a steady tone holds still
+fn draw_scope(image: &mut Image, top: usize, samples: &[f32], color: (u8, u8, u8)) {
+    image.fill_rect(MARGIN, top, SCOPE_WIDTH, SCOPE_HEIGHT, SCOPE_BACKGROUND);
+    let center = top + SCOPE_HEIGHT / 2;
+    image.fill_rect(MARGIN, center, SCOPE_WIDTH, 1, CENTER_LINE);
+
+    let shown = &samples[trigger(samples)..];
+    let mut previous = None;
+    for (x, sample) in shown.iter().take(SCOPE_WIDTH).enumerate() {
+        // Full scale fills the height, the DC blocker can push a channel a bit past it
+        let offset = (sample.clamp(-1.0, 1.0) * (SCOPE_HEIGHT / 2 - 1) as f32).round() as isize;
+        let y = (center as isize - offset) as usize;
+        let (from, to) = match previous {
+            Some(previous) => (y.min(previous), y.max(previous)),
+            None => (y, y),
+        };
+        image.fill_rect(MARGIN + x, from, 1, to - from + 1, color);
+        previous = Some(y);
+    }
+}
+
+fn trigger(samples: &[f32]) -> usize {
+    let (low, high) = samples
+        .iter()
+        .fold((f32::MAX, f32::MIN), |(low, high), sample| (low.min(*sample), high.max(*sample)));
+    if high - low < MIN_TRIGGER_SWING || samples.len() <= SCOPE_WIDTH {
+        return 0;
+    }
+
+    let middle = (low + high) / 2.0;
+    (1..samples.len() - SCOPE_WIDTH)
+        .find(|&i| samples[i - 1] < middle && samples[i] >= middle)
+        .unwrap_or(0)
+}
+
+fn describe(view: &ChannelView, game_boy: &GameBoy) -> Vec<String> {
+    let state = match (view.enabled, view.dac_enabled) {
+        (true, _) => "on",
+        (false, true) => "idle",
+        (false, false) => "DAC off",
+    };
+    let mut mixing = String::new();
+    if game_boy.channel_muted(view.channel) {
+        mixing += " muted";
+    }
+    if game_boy.channel_soloed(view.channel) {
+        mixing += " solo";
+    }
+
+    let mut lines = vec![format!("{} {}{}", view.channel.name(), state, mixing)];
+    lines.push(match (view.duty_percent(), view.lfsr_width) {
+        (Some(duty), _) => format!("Duty {}% Vol {}", duty, view.volume),
+        (_, Some(width)) => format!("LFSR {} bit Vol {}", width, view.volume),
+        _ => format!("Level {}", ["mute", "100%", "50%", "25%"][view.volume as usize]),
+    });
+    lines.push(match view.note() {
+        Some(note) => format!("{:.1} Hz {}", view.frequency, note),
+        None => format!("{:.0} Hz", view.frequency),
+    });
+    lines.push(format!("Length {} {}", view.length, if view.length_enabled { "on" } else { "off" }));
+    if let Some(envelope) = view.envelope {
+        let direction = if envelope.increase { "up" } else { "down" };
+        lines.push(format!("Env {} {} pace {}", envelope.initial_volume, direction, envelope.pace));
+    }
+    if let Some(sweep) = view.sweep {
+        let direction = if sweep.decrease { "down" } else { "up" };
+        lines.push(format!("Sweep pace {} {} {}", sweep.pace, direction, sweep.step));
+    }
+    lines
+}
@@ -0,0 +1,152 @@
+use ironboy_core::{GameBoy, PaletteView};
+use sdl2::{
+    event::{Event, WindowEvent},
+    keyboard::Keycode,
+};
+use tracing::info;
+
+use crate::{
+    viewers::{Image, Viewer, ViewerWindow},
+    window::{WindowError, WindowManager},
+};
+
+const SWATCH_SIZE: usize = 8;
+const GAP: usize = 1;
+const CELL: usize = SWATCH_SIZE + GAP;
+const PALETTE_WIDTH: usize = 4 * CELL;
+const SECTION_GAP: usize = 4;
+/// BGP sits above the CGB background palettes and OBP0/OBP1 above the object ones
+const DMG_ROWS: usize = 2;
+const CGB_TOP: usize = GAP + DMG_ROWS * CELL + SECTION_GAP;
+const WIDTH: usize = GAP + 2 * PALETTE_WIDTH + SECTION_GAP;
+const HEIGHT: usize = CGB_TOP + 8 * CELL;
+const BACKGROUND: (u8, u8, u8) = (45, 45, 45);
+const DMG_NAMES: [&str; 3] = ["BGP", "OBP0", "OBP1"];
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum Swatch {
+    /// BGP, OBP0 or OBP1 then the color index
+    Dmg(usize, usize),
+    /// Object palettes or not, the palette and the color index
+    Cgb(bool, usize, usize),
+}
+
+impl Swatch {
+    fn all() -> impl Iterator<Item = Swatch> {
+        let dmg = (0..3).flat_map(|register| (0..4).map(move |color| Swatch::Dmg(register, color)));
+        let cgb = [false, true]
+            .into_iter()
+            .flat_map(|object| (0..8).flat_map(move |palette| (0..4).map(move |color| Swatch::Cgb(object, palette, color))));
+        dmg.chain(cgb)
+    }
+
+    fn position(self) -> (usize, usize) {
+        let (column, top, row, color) = match self {
+            Swatch::Dmg(0, color) => (0, GAP, 0, color),
+            Swatch::Dmg(register, color) => (1, GAP, register - 1, color),
+            Swatch::Cgb(object, palette, color) => (object as usize, CGB_TOP, palette, color),
+        };
+        (GAP + column * (PALETTE_WIDTH + SECTION_GAP) + color * CELL, top + row * CELL)
+    }
+
+    fn describe(self, palettes: &PaletteView) -> String {
+        match self {
+            Swatch::Dmg(register, color) => format!(
+                "{} {:#04X} color {} shade {}",
+                DMG_NAMES[register],
+                palettes.dmg_registers[register],
+                color,
+                (palettes.dmg_registers[register] >> (2 * color)) & 0x03
+            ),
+            Swatch::Cgb(object, palette, color) => {
+                let (name, values) = match object {
+                    false => ("BG", &palettes.cgb_background),
+                    true => ("OBJ", &palettes.cgb_object),
+                };
+                format!("{}{} color {} RGB555 {:#06X}", name, palette, color, values[palette][color])
+            }
+        }
+    }
+
+    fn color(self, palettes: &PaletteView) -> (u8, u8, u8) {
+        match self {
+            Swatch::Dmg(register, color) => palettes.dmg_colors[register][color],
+            Swatch::Cgb(false, palette, color) => palettes.cgb_background_colors[palette][color],
+            Swatch::Cgb(true, palette, color) => palettes.cgb_object_colors[palette][color],
+        }
+    }
+}
+
+/// Shows BGP, OBP0 and OBP1 above the 8 CGB background and 8 CGB object palettes
+pub struct PaletteViewer {
+    window: ViewerWindow,
+    hovered: Option<Swatch>,
+    log_requested: bool,
+    title: String,
+}
+
+impl PaletteViewer {
+    pub fn new(window_manager: &mut WindowManager) -> Result<Self, WindowError> {
+        Ok(PaletteViewer {
+            window: ViewerWindow::new(window_manager, "Palettes", WIDTH as u32, HEIGHT as u32, 3)?,
+            hovered: None,
+            log_requested: false,
+            title: String::new(),
+        })
+    }
+}
+
+impl Viewer for PaletteViewer {
+    fn id(&self) -> u32 {
+        self.window.id()
+    }
+
+    fn handle_event(&mut self, event: &Event, _game_boy: Option<&GameBoy>) -> bool {
+        match event {
+            Event::MouseMotion { x, y, .. } => {
+                self.hovered = Swatch::all().find(|swatch| {
+                    let (swatch_x, swatch_y) = swatch.position();
+                    (swatch_x as i32..(swatch_x + SWATCH_SIZE) as i32).contains(x) && (swatch_y as i32..(swatch_y + SWATCH_SIZE) as i32).contains(y)
+                })
+            }
+            Event::Window {
+                win_event: WindowEvent::Leave,
+                ..
+            } => self.hovered = None,
+            Event::KeyDown {
+                keycode: Some(Keycode::Tab), ..
+            } => {
+                self.log_requested = true;
+                return true;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn render(&mut self, game_boy: &GameBoy) -> Result<(), WindowError> {
+        let palettes = game_boy.palette_view();
+        let mut image = Image::new(WIDTH, HEIGHT, BACKGROUND);
+        for swatch in Swatch::all() {
+            let (x, y) = swatch.position();
+            image.fill_rect(x, y, SWATCH_SIZE, SWATCH_SIZE, swatch.color(&palettes));
+        }
+
+        if std::mem::take(&mut self.log_requested) {
+            for swatch in Swatch::all() {
+                info!("{}", swatch.describe(&palettes));
+            }
+        }
+
+        let title = match self.hovered {
+            Some(swatch) => swatch.describe(&palettes),
+            None => "Palettes".to_string(),
+        };
+        if title != self.title {
+            self.window.set_title(&title)?;
+            self.title = title;
+        }
+
+        self.window.present(&image)
+    }
+}
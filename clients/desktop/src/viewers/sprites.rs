@@ -0,0 +1,184 @@
+use ironboy_core::{GameBoy, GbMode, ObjectView, PaletteView};
+use sdl2::{
+    event::{Event, WindowEvent},
+    keyboard::Keycode,
+    ttf::Font,
+};
+use tracing::info;
+
+use crate::{
+    viewers::{Image, Viewer, ViewerWindow},
+    window::{WindowError, WindowManager},
+};
+
+const OBJECTS: usize = 40;
+const COLUMNS: usize = 8;
+const BORDER: usize = 2;
+const CELL_WIDTH: usize = 8 + 2 * BORDER;
+const CELL_HEIGHT: usize = 16 + 2 * BORDER;
+const GRID_WIDTH: usize = COLUMNS * CELL_WIDTH;
+const GRID_HEIGHT: usize = OBJECTS / COLUMNS * CELL_HEIGHT;
+const FONT_SIZE: u16 = 8;
+const LINE_HEIGHT: usize = 9;
+const MARGIN: usize = 4;
+const SWATCH_SIZE: usize = 6;
+/// The entries are listed in two columns right of the grid, each line starts with the object's palette
+const LIST_ROWS: usize = OBJECTS / 2;
+const LIST_X: usize = GRID_WIDTH + MARGIN;
+const LIST_COLUMN_WIDTH: usize = 176;
+const TEXT_OFFSET: usize = 4 * SWATCH_SIZE + MARGIN;
+const WIDTH: usize = LIST_X + 2 * LIST_COLUMN_WIDTH;
+const HEIGHT: usize = 2 * MARGIN + LIST_ROWS * LINE_HEIGHT;
+const BACKGROUND: (u8, u8, u8) = (45, 45, 45);
+const TRANSPARENT: (u8, u8, u8) = (90, 90, 90);
+const DROPPED: (u8, u8, u8) = (255, 0, 0);
+
+/// Shows all 40 OAM entries in OAM order next to their decoded attributes and palettes,
+/// objects the 10 per line limit dropped get a red border
+pub struct SpriteViewer {
+    window: ViewerWindow,
+    font: Font<'static, 'static>,
+    hovered: Option<usize>,
+    log_requested: bool,
+    title: String,
+}
+
+impl SpriteViewer {
+    pub fn new(window_manager: &mut WindowManager) -> Result<Self, WindowError> {
+        Ok(SpriteViewer {
+            window: ViewerWindow::new(window_manager, "Sprites", WIDTH as u32, HEIGHT as u32, 2)?,
+            font: window_manager.load_font(FONT_SIZE)?,
+            hovered: None,
+            log_requested: false,
+            title: String::new(),
+        })
+    }
+}
+
+impl Viewer for SpriteViewer {
+    fn id(&self) -> u32 {
+        self.window.id()
+    }
+
+    fn handle_event(&mut self, event: &Event, _game_boy: Option<&GameBoy>) -> bool {
+        match event {
+            Event::MouseMotion { x, y, .. } => self.hovered = object_at(*x, *y),
+            Event::Window {
+                win_event: WindowEvent::Leave,
+                ..
+            } => self.hovered = None,
+            Event::KeyDown {
+                keycode: Some(Keycode::Tab), ..
+            } => {
+                self.log_requested = true;
+                return true;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn render(&mut self, game_boy: &GameBoy) -> Result<(), WindowError> {
+        let objects = game_boy.object_views();
+        let palettes = game_boy.palette_view();
+        let cgb_palettes = game_boy.mode() == GbMode::Color;
+        let mut image = Image::new(WIDTH, HEIGHT, BACKGROUND);
+        let mut lines = Vec::new();
+        for (oam_index, object) in objects.iter().enumerate() {
+            let cell_x = oam_index % COLUMNS * CELL_WIDTH;
+            let cell_y = oam_index / COLUMNS * CELL_HEIGHT;
+            if object.dropped {
+                image.fill_rect(cell_x, cell_y, CELL_WIDTH, CELL_HEIGHT, DROPPED);
+            }
+            image.fill_rect(cell_x + BORDER, cell_y + BORDER, 8, 16, BACKGROUND);
+            for (pixel, color) in object.pixels.iter().enumerate() {
+                image.put(cell_x + BORDER + pixel % 8, cell_y + BORDER + pixel / 8, color.unwrap_or(TRANSPARENT));
+            }
+
+            let (line_x, line_y) = list_position(oam_index);
+            for (index, swatch) in palette_colors(object, &palettes, cgb_palettes).iter().enumerate() {
+                image.fill_rect(line_x + index * SWATCH_SIZE, line_y + 1, SWATCH_SIZE, SWATCH_SIZE, *swatch);
+            }
+            lines.push(((line_x + TEXT_OFFSET) as i32, line_y as i32, summarize(oam_index, object, cgb_palettes)));
+        }
+
+        if std::mem::take(&mut self.log_requested) {
+            for (oam_index, object) in objects.iter().enumerate() {
+                info!("{}", describe(oam_index, object));
+            }
+        }
+
+        let title = match self.hovered {
+            Some(oam_index) => describe(oam_index, &objects[oam_index]),
+            None => "Sprites".to_string(),
+        };
+        if title != self.title {
+            self.window.set_title(&title)?;
+            self.title = title;
+        }
+
+        self.window.present_with_text(&image, Some(&self.font), &lines)
+    }
+}
+
+fn object_at(x: i32, y: i32) -> Option<usize> {
+    let (x, y) = (usize::try_from(x).ok()?, usize::try_from(y).ok()?);
+    if x < GRID_WIDTH && y < GRID_HEIGHT {
+        return Some(y / CELL_HEIGHT * COLUMNS + x / CELL_WIDTH);
+    }
+    if !(LIST_X..WIDTH).contains(&x) || !(MARGIN..MARGIN + LIST_ROWS * LINE_HEIGHT).contains(&y) {
+        return None;
+    }
+    Some((x - LIST_X) / LIST_COLUMN_WIDTH * LIST_ROWS + (y - MARGIN) / LINE_HEIGHT)
+}
+
+fn list_position(oam_index: usize) -> (usize, usize) {
+    (
+        LIST_X + oam_index / LIST_ROWS * LIST_COLUMN_WIDTH,
+        MARGIN + oam_index % LIST_ROWS * LINE_HEIGHT,
+    )
+}
+
+/// The four colors of the palette the object is drawn with, color 0 is transparent but still shown
+fn palette_colors(object: &ObjectView, palettes: &PaletteView, cgb_palettes: bool) -> [(u8, u8, u8); 4] {
+    match cgb_palettes {
+        true => palettes.cgb_object_colors[object.cgb_palette as usize],
+        false => palettes.dmg_colors[1 + object.dmg_palette as usize],
+    }
+}
+
+/// One list line, the flags are X flip, Y flip, behind BG and dropped
+fn summarize(oam_index: usize, object: &ObjectView, cgb_palettes: bool) -> String {
+    let palette = match cgb_palettes {
+        true => format!("P{} B{}", object.cgb_palette, object.bank),
+        false => format!("OBP{}", object.dmg_palette),
+    };
+    let flags: String = [(object.x_flip, 'X'), (object.y_flip, 'Y'), (object.priority, 'B'), (object.dropped, 'D')]
+        .iter()
+        .map(|(set, flag)| if *set { *flag } else { '-' })
+        .collect();
+    format!(
+        "{:02} X{:3} Y{:3} T{:02X} {} {}",
+        oam_index, object.x, object.y, object.tile, palette, flags
+    )
+}
+
+fn describe(oam_index: usize, object: &ObjectView) -> String {
+    let mut flags = String::new();
+    if object.x_flip {
+        flags += " X flip";
+    }
+    if object.y_flip {
+        flags += " Y flip";
+    }
+    if object.priority {
+        flags += " behind BG";
+    }
+    if object.dropped {
+        flags += " dropped";
+    }
+    format!(
+        "OBJ {:02} X {} Y {} tile {:#04X} OBP{} bank {} palette {}{}",
+        oam_index, object.x, object.y, object.tile, object.dmg_palette, object.bank, object.cgb_palette, flags
+    )
+}
@@ -0,0 +1,94 @@
+use ironboy_core::{GameBoy, TILE_MAP_SIZE, TileMapLayer, VIEWPORT_HEIGHT, VIEWPORT_WIDTH};
+use sdl2::{event::Event, keyboard::Keycode};
+
+use crate::{
+    viewers::{Image, Viewer, ViewerWindow},
+    window::{WindowError, WindowManager},
+};
+
+const VIEWPORT_COLOR: (u8, u8, u8) = (255, 0, 0);
+const WINDOW_X_OFFSET: usize = 7;
+
+/// Shows the whole background or window tile map with the part that is on screen outlined, Tab switches between them
+pub struct TileMapViewer {
+    window: ViewerWindow,
+    layer: TileMapLayer,
+    title: String,
+}
+
+impl TileMapViewer {
+    pub fn new(window_manager: &mut WindowManager) -> Result<Self, WindowError> {
+        Ok(TileMapViewer {
+            window: ViewerWindow::new(window_manager, "Tile map", TILE_MAP_SIZE as u32, TILE_MAP_SIZE as u32, 1)?,
+            layer: TileMapLayer::default(),
+            title: String::new(),
+        })
+    }
+}
+
+impl Viewer for TileMapViewer {
+    fn id(&self) -> u32 {
+        self.window.id()
+    }
+
+    fn handle_event(&mut self, event: &Event, _game_boy: Option<&GameBoy>) -> bool {
+        match event {
+            Event::KeyDown {
+                keycode: Some(Keycode::Tab), ..
+            } => {
+                self.layer = match self.layer {
+                    TileMapLayer::Background => TileMapLayer::Window,
+                    TileMapLayer::Window => TileMapLayer::Background,
+                };
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn render(&mut self, game_boy: &GameBoy) -> Result<(), WindowError> {
+        let view = game_boy.tile_map_view(self.layer);
+        let mut image = Image::new(TILE_MAP_SIZE, TILE_MAP_SIZE, (0, 0, 0));
+        for (offset, &color) in view.colors.iter().enumerate() {
+            image.put(offset % TILE_MAP_SIZE, offset / TILE_MAP_SIZE, color);
+        }
+
+        let (x, y) = (view.x as usize, view.y as usize);
+        let mut title = match self.layer {
+            TileMapLayer::Background => {
+                outline_wrapping(&mut image, x, y, VIEWPORT_WIDTH, VIEWPORT_HEIGHT);
+                format!("Background map {:#06X} - SCX {} SCY {}", view.map_address, x, y)
+            }
+            TileMapLayer::Window => {
+                // The window always starts drawing from the top left of its map
+                let width = (VIEWPORT_WIDTH + WINDOW_X_OFFSET).saturating_sub(x).min(VIEWPORT_WIDTH);
+                let height = VIEWPORT_HEIGHT.saturating_sub(y);
+                if view.enabled && width > 0 && height > 0 {
+                    outline_wrapping(&mut image, 0, 0, width, height);
+                }
+                format!("Window map {:#06X} - WX {} WY {}", view.map_address, x, y)
+            }
+        };
+        if !view.enabled {
+            title += " - disabled";
+        }
+        if title != self.title {
+            self.window.set_title(&title)?;
+            self.title = title;
+        }
+
+        self.window.present(&image)
+    }
+}
+
+/// Draws a rectangle that continues on the opposite edge, like scrolling does
+fn outline_wrapping(image: &mut Image, x: usize, y: usize, width: usize, height: usize) {
+    for column in x..x + width {
+        image.put(column % TILE_MAP_SIZE, y, VIEWPORT_COLOR);
+        image.put(column % TILE_MAP_SIZE, (y + height - 1) % TILE_MAP_SIZE, VIEWPORT_COLOR);
+    }
+    for row in y..y + height {
+        image.put(x, row % TILE_MAP_SIZE, VIEWPORT_COLOR);
+        image.put((x + width - 1) % TILE_MAP_SIZE, row % TILE_MAP_SIZE, VIEWPORT_COLOR);
+    }
+}
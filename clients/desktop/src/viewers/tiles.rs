@@ -0,0 +1,113 @@
+use ironboy_core::{GameBoy, GbMode, TILES_PER_BANK, ViewerPalette, tile_color_indices};
+use sdl2::{
+    event::{Event, WindowEvent},
+    keyboard::Keycode,
+};
+
+use crate::{
+    viewers::{Image, Viewer, ViewerWindow},
+    window::{WindowError, WindowManager},
+};
+
+const TILE_SIZE: usize = 8;
+const TILES_PER_ROW: usize = 16;
+const BANK_WIDTH: usize = TILES_PER_ROW * TILE_SIZE;
+const BANK_HEIGHT: usize = TILES_PER_BANK / TILES_PER_ROW * TILE_SIZE;
+const BANK_GAP: usize = 8;
+const WIDTH: usize = 2 * BANK_WIDTH + BANK_GAP;
+const BACKGROUND: (u8, u8, u8) = (45, 45, 45);
+
+/// Shows every tile in both VRAM banks, Tab cycles the palette they are drawn with
+pub struct TileViewer {
+    window: ViewerWindow,
+    palette: ViewerPalette,
+    hovered: Option<(usize, usize)>,
+    title: String,
+}
+
+impl TileViewer {
+    pub fn new(window_manager: &mut WindowManager) -> Result<Self, WindowError> {
+        Ok(TileViewer {
+            window: ViewerWindow::new(window_manager, "Tiles", WIDTH as u32, BANK_HEIGHT as u32, 0)?,
+            palette: ViewerPalette::default(),
+            hovered: None,
+            title: String::new(),
+        })
+    }
+}
+
+impl Viewer for TileViewer {
+    fn id(&self) -> u32 {
+        self.window.id()
+    }
+
+    fn handle_event(&mut self, event: &Event, game_boy: Option<&GameBoy>) -> bool {
+        match event {
+            Event::MouseMotion { x, y, .. } => self.hovered = tile_at(*x, *y),
+            Event::Window {
+                win_event: WindowEvent::Leave,
+                ..
+            } => self.hovered = None,
+            Event::KeyDown {
+                keycode: Some(Keycode::Tab), ..
+            } => {
+                let color = game_boy.is_some_and(|game_boy| game_boy.mode() == GbMode::Color);
+                self.palette = next_palette(self.palette, color);
+                return true;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn render(&mut self, game_boy: &GameBoy) -> Result<(), WindowError> {
+        let colors = game_boy.viewer_palette_colors(self.palette);
+        let mut image = Image::new(WIDTH, BANK_HEIGHT, BACKGROUND);
+
+        for bank in 0..2 {
+            let Some(vram_bank) = game_boy.vram_bank(bank) else {
+                continue;
+            };
+            for tile in 0..TILES_PER_BANK {
+                let tile_x = bank * (BANK_WIDTH + BANK_GAP) + tile % TILES_PER_ROW * TILE_SIZE;
+                let tile_y = tile / TILES_PER_ROW * TILE_SIZE;
+                for (pixel, color_index) in tile_color_indices(vram_bank, tile).into_iter().enumerate() {
+                    image.put(tile_x + pixel % TILE_SIZE, tile_y + pixel / TILE_SIZE, colors[color_index as usize]);
+                }
+            }
+        }
+
+        let mut title = format!("Tiles - {} palette", self.palette.name());
+        if let Some((bank, tile)) = self.hovered {
+            title += &format!(" - bank {} tile {:#05X} at {:#06X}", bank, tile, 0x8000 + tile * 16);
+        }
+        if title != self.title {
+            self.window.set_title(&title)?;
+            self.title = title;
+        }
+
+        self.window.present(&image)
+    }
+}
+
+fn tile_at(x: i32, y: i32) -> Option<(usize, usize)> {
+    let (x, y) = (usize::try_from(x).ok()?, usize::try_from(y).ok()?);
+    let bank = x / (BANK_WIDTH + BANK_GAP);
+    let bank_x = x % (BANK_WIDTH + BANK_GAP);
+    if bank > 1 || bank_x >= BANK_WIDTH || y >= BANK_HEIGHT {
+        return None;
+    }
+    Some((bank, y / TILE_SIZE * TILES_PER_ROW + bank_x / TILE_SIZE))
+}
+
+/// DMG only has BGP, OBP0 and OBP1 while CGB has 8 palettes of each kind
+fn next_palette(palette: ViewerPalette, color: bool) -> ViewerPalette {
+    let last = if color { 7 } else { 0 };
+    match palette {
+        ViewerPalette::Grayscale => ViewerPalette::Background(0),
+        ViewerPalette::Background(palette) if palette < last => ViewerPalette::Background(palette + 1),
+        ViewerPalette::Background(_) => ViewerPalette::Object(0),
+        ViewerPalette::Object(palette) if palette < last.max(1) => ViewerPalette::Object(palette + 1),
+        ViewerPalette::Object(_) => ViewerPalette::Grayscale,
+    }
+}
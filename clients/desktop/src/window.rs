@@ -1,21 +1,23 @@
 use getset::{Getters, MutGetters};
-use ironboy_core::{VIEWPORT_HEIGHT, VIEWPORT_WIDTH};
+use ironboy_core::{PixelFormat, VIEWPORT_HEIGHT, VIEWPORT_WIDTH};
 use sdl2::{
     Sdl, VideoSubsystem,
     image::{self, InitFlag, LoadTexture},
-    pixels::Color,
+    pixels::{Color, PixelFormatEnum},
     rect::Rect,
-    render::{Canvas, TextureCreator},
-    ttf::{self, Sdl2TtfContext},
+    render::{Canvas, Texture, TextureCreator},
+    ttf::{self, Font, Sdl2TtfContext},
     video::{Window, WindowContext},
 };
 use thiserror::Error;
 
 const SCALE: u32 = 6;
-const FPS_FONT_SIZE: u16 = 24;
-const FPS_PADDING: i32 = 10;
+const OVERLAY_FONT_SIZE: u16 = 24;
+const OVERLAY_PADDING: i32 = 10;
 const FONT_PATH: &str = "media/gbboot-alpm.ttf";
 const SPLASH_PADDING: u32 = 20;
+const TEXT_FONT_SIZE: u16 = 32;
+const TEXT_PADDING: i32 = 40;
 const SPLASH_PATH: &str = "media/ironboy_logo.png";
 
 #[derive(Error, Debug)]
@@ -40,19 +42,69 @@ pub enum WindowError {
     TextRenderError(String),
 }
 
+/// A streaming texture that is kept between frames and only recreated when its format or size changes
+pub struct StreamingTexture {
+    // Declared before the creator so it is destroyed while the renderer is still alive
+    texture: Option<(Texture<'static>, PixelFormatEnum, u32, u32)>,
+    texture_creator: TextureCreator<WindowContext>,
+}
+
+impl StreamingTexture {
+    pub fn new(texture_creator: TextureCreator<WindowContext>) -> Self {
+        StreamingTexture {
+            texture: None,
+            texture_creator,
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        format: PixelFormatEnum,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+        pitch: usize,
+    ) -> Result<&Texture<'static>, WindowError> {
+        if !matches!(self.texture, Some((_, f, w, h)) if (f, w, h) == (format, width, height)) {
+            // SDL only frees the old texture on drop
+            self.texture = None;
+            let texture = self
+                .texture_creator
+                .create_texture_streaming(format, width, height)
+                .map_err(|e| WindowError::TextureError(e.to_string()))?;
+            // SAFETY: the texture doesn't point into the creator, it only needs the renderer the creator keeps
+            // alive, and it never outlives the creator since it's only lent out borrowed from self
+            let texture = unsafe { std::mem::transmute::<Texture<'_>, Texture<'static>>(texture) };
+            self.texture = Some((texture, format, width, height));
+        }
+
+        let (texture, ..) = self.texture.as_mut().expect("Texture was just created");
+        texture
+            .update(None, pixels, pitch)
+            .map_err(|e| WindowError::TextureError(e.to_string()))?;
+        Ok(texture)
+    }
+}
+
 #[derive(Getters, MutGetters)]
 pub struct WindowManager {
+    screen: StreamingTexture,
     video_subsystem: VideoSubsystem,
     #[getset(get = "pub", get_mut = "pub")]
     main_canvas: Canvas<Window>,
     texture_creator: TextureCreator<WindowContext>,
-    ttf_context: Sdl2TtfContext,
+    ttf_context: &'static Sdl2TtfContext,
+    overlay_font: Font<'static, 'static>,
+    text_font: Font<'static, 'static>,
 }
 
 impl WindowManager {
     pub fn new(sdl_context: &Sdl) -> Result<WindowManager, WindowError> {
         image::init(InitFlag::PNG).map_err(WindowError::ImageInitError)?;
-        let ttf_context = ttf::init().map_err(WindowError::TtfInitError)?;
+        // Lives as long as the program so fonts can be kept instead of loaded for every frame
+        let ttf_context: &'static Sdl2TtfContext = Box::leak(Box::new(ttf::init().map_err(WindowError::TtfInitError)?));
+        let overlay_font = load_font(ttf_context, OVERLAY_FONT_SIZE)?;
+        let text_font = load_font(ttf_context, TEXT_FONT_SIZE)?;
 
         let video_subsystem = sdl_context.video().map_err(WindowError::VideoSubsystemError)?;
         let window = video_subsystem
@@ -64,15 +116,25 @@ impl WindowManager {
 
         let main_canvas = window.into_canvas().present_vsync().accelerated().build()?;
         let texture_creator = main_canvas.texture_creator();
+        let screen = StreamingTexture::new(main_canvas.texture_creator());
 
         Ok(Self {
+            screen,
             video_subsystem,
             main_canvas,
             texture_creator,
             ttf_context,
+            overlay_font,
+            text_font,
         })
     }
 
+    /// Viewers share the TTF context of the main window
+    pub fn load_font(&self, point_size: u16) -> Result<Font<'static, 'static>, WindowError> {
+        load_font(self.ttf_context, point_size)
+    }
+
+    /// Extra windows skip vsync, waiting on it for every window would slow the main one down
     pub fn create_canvas(&mut self, title: &str, width: u32, height: u32, x: i32, y: i32) -> Result<Canvas<Window>, WindowError> {
         let window = self
             .video_subsystem
@@ -81,43 +143,33 @@ impl WindowManager {
             .resizable()
             .opengl()
             .build()?;
-        let canvas = window.into_canvas().present_vsync().accelerated().build()?;
+        let canvas = window.into_canvas().accelerated().build()?;
         Ok(canvas)
     }
 
-    pub fn render_screen(&mut self, data: &[(u8, u8, u8)], fps: Option<f64>) -> Result<(), WindowError> {
+    /// Expects a frame in the core's default RGBA8888 format
+    pub fn render_screen(&mut self, frame: &[u8], overlay: Option<&str>) -> Result<(), WindowError> {
         self.main_canvas.set_draw_color(Color::RGB(0, 0, 0));
         self.main_canvas.clear();
 
-        for x in 0..VIEWPORT_WIDTH {
-            for y in 0..VIEWPORT_HEIGHT {
-                let i = y * VIEWPORT_WIDTH + x;
-                let color = data[i as usize];
-                self.main_canvas.set_draw_color(Color::RGB(color.0, color.1, color.2));
-                let rect = Rect::new(
-                    (x as u32 * SCALE) as i32,
-                    (y as u32 * SCALE) as i32,
-                    SCALE + 4, // change this if you want line speration
-                    SCALE + 4, // change this if you want line speration
-                );
-                self.main_canvas.fill_rect(rect).map_err(WindowError::CanvasError)?;
-            }
-        }
+        let pitch = VIEWPORT_WIDTH * PixelFormat::Rgba8888.bytes_per_pixel();
+        let texture = self
+            .screen
+            .update(PixelFormatEnum::RGBA8888, VIEWPORT_WIDTH as u32, VIEWPORT_HEIGHT as u32, frame, pitch)?;
+        self.main_canvas.copy(texture, None, None).map_err(WindowError::CanvasError)?;
 
-        if let Some(fps_value) = fps {
-            self.render_fps_overlay(fps_value)?;
+        if let Some(text) = overlay {
+            self.render_overlay(text)?;
         }
 
         self.main_canvas.present();
         Ok(())
     }
 
-    fn render_fps_overlay(&mut self, fps: f64) -> Result<(), WindowError> {
-        let font = self.ttf_context.load_font(FONT_PATH, FPS_FONT_SIZE).map_err(WindowError::FontLoadError)?;
-
-        let fps_text = format!("{:.1} FPS", fps);
-        let surface = font
-            .render(&fps_text)
+    fn render_overlay(&mut self, text: &str) -> Result<(), WindowError> {
+        let surface = self
+            .overlay_font
+            .render(text)
             .blended(Color::RGB(0, 255, 0))
             .map_err(|e| WindowError::TextRenderError(e.to_string()))?;
 
@@ -130,8 +182,8 @@ impl WindowManager {
         let text_width = surface.width();
         let text_height = surface.height();
 
-        let x = (window_width - text_width) as i32 - FPS_PADDING;
-        let y = (window_height - text_height) as i32 - FPS_PADDING;
+        let x = (window_width - text_width) as i32 - OVERLAY_PADDING;
+        let y = (window_height - text_height) as i32 - OVERLAY_PADDING;
 
         let dst_rect = Rect::new(x, y, text_width, text_height);
         self.main_canvas.copy(&texture, None, dst_rect).map_err(WindowError::CanvasError)?;
@@ -139,6 +191,35 @@ impl WindowManager {
         Ok(())
     }
 
+    /// Lines of text on a blank screen, empty lines leave a gap
+    pub fn render_text(&mut self, lines: &[String]) -> Result<(), WindowError> {
+        let font = &self.text_font;
+        self.main_canvas.set_draw_color(Color::RGB(45, 45, 45));
+        self.main_canvas.clear();
+
+        let mut y = TEXT_PADDING;
+        for line in lines {
+            if line.is_empty() {
+                y += font.recommended_line_spacing();
+                continue;
+            }
+            let surface = font
+                .render(line)
+                .blended(Color::RGB(255, 255, 255))
+                .map_err(|e| WindowError::TextRenderError(e.to_string()))?;
+            let texture = self
+                .texture_creator
+                .create_texture_from_surface(&surface)
+                .map_err(|e| WindowError::TextureError(e.to_string()))?;
+            let dst_rect = Rect::new(TEXT_PADDING, y, surface.width(), surface.height());
+            self.main_canvas.copy(&texture, None, dst_rect).map_err(WindowError::CanvasError)?;
+            y += font.recommended_line_spacing();
+        }
+
+        self.main_canvas.present();
+        Ok(())
+    }
+
     pub fn render_splash(&mut self) -> Result<(), WindowError> {
         let texture = self.texture_creator.load_texture(SPLASH_PATH).map_err(WindowError::TextureError)?;
 
@@ -169,3 +250,7 @@ impl WindowManager {
         Ok(())
     }
 }
+
+fn load_font(ttf_context: &'static Sdl2TtfContext, point_size: u16) -> Result<Font<'static, 'static>, WindowError> {
+    ttf_context.load_font(FONT_PATH, point_size).map_err(WindowError::FontLoadError)
+}
@@ -0,0 +1,80 @@
+use ironboy_core::{
+    AudioChannel, GameBoy, GameBoyConfig, LoadMode, SampleRate,
+    cpu::CPU_CLOCK_SPEED,
+    wav::{AudioRecorder, WavFormat},
+};
+
+use std::{env, fs, path::PathBuf};
+
+const DEFAULT_SECONDS: u64 = 10;
+const USAGE: &str = "Usage: headless <rom file path> [--seconds <n>] [--sample-rate <hz>] [--record <wav path>] [--record-format pcm16|float] [--record-stems] [--vgm <vgm path>]";
+
+/// Runs a ROM for a fixed amount of emulated time as fast as possible, without a window or audio device
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut rom_path = None;
+    let mut seconds = DEFAULT_SECONDS;
+    let mut sample_rate = SampleRate::default();
+    let mut record_path = None;
+    let mut record_format = WavFormat::default();
+    let mut record_stems = false;
+    let mut vgm_path = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seconds" => seconds = args.next().and_then(|seconds| seconds.parse().ok()).ok_or("--seconds expects a number")?,
+            "--sample-rate" => {
+                let hz = args.next().and_then(|hz| hz.parse().ok()).and_then(SampleRate::from_hz);
+                sample_rate = hz.ok_or("--sample-rate expects 22050, 32000, 44100, 48000 or 96000")?;
+            }
+            "--record" => record_path = Some(PathBuf::from(args.next().ok_or("--record expects a file path")?)),
+            "--record-format" => {
+                let format = args.next().and_then(|name| WavFormat::from_name(&name));
+                record_format = format.ok_or("--record-format expects pcm16 or float")?;
+            }
+            "--record-stems" => record_stems = true,
+            "--vgm" => vgm_path = Some(PathBuf::from(args.next().ok_or("--vgm expects a file path")?)),
+            option if option.starts_with("--") => return Err(format!("Unknown option {}. {}", option, USAGE).into()),
+            _ if rom_path.is_some() => return Err(format!("Unexpected argument {}. {}", arg, USAGE).into()),
+            _ => rom_path = Some(arg),
+        }
+    }
+    let rom_path = rom_path.ok_or(USAGE)?;
+    if record_stems && record_path.is_none() {
+        return Err(format!("--record-stems needs --record. {}", USAGE).into());
+    }
+
+    let config = GameBoyConfig {
+        load_mode: LoadMode::Lenient,
+        sample_rate,
+        ..Default::default()
+    };
+    let mut game_boy = GameBoy::with_config(&rom_path, fs::read(&rom_path)?, config)?;
+    let mut recorder = match &record_path {
+        Some(path) => Some(AudioRecorder::start(path, record_format, sample_rate, record_stems)?),
+        None => None,
+    };
+
+    game_boy.set_channel_capture(record_stems);
+    if vgm_path.is_some() {
+        game_boy.start_vgm_log();
+    }
+
+    let end = seconds * CPU_CLOCK_SPEED as u64;
+    while game_boy.t_cycles() < end {
+        game_boy.run_until_audio_buffer_full();
+        if let Some(recorder) = &mut recorder {
+            let (left_samples, right_samples) = game_boy.audio_samples();
+            let channels = AudioChannel::ALL.map(|channel| game_boy.channel_samples(channel));
+            recorder.write(left_samples, right_samples, channels)?;
+        }
+    }
+
+    if let Some(recorder) = recorder {
+        recorder.finish()?;
+    }
+    if let (Some(path), Some(vgm_log)) = (vgm_path, game_boy.stop_vgm_log()) {
+        fs::write(path, vgm_log.to_vgm(true))?;
+    }
+    Ok(())
+}
@@ -1,29 +1,66 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 
+use crate::apu::analog::{convert_digital_to_analog, dac_output};
 use crate::apu::noise::NoiseChannel;
 use crate::apu::pulse::PulseChannel;
+use crate::apu::stream::AudioStream;
 use crate::apu::wave::WaveChannel;
-use crate::cpu::CPU_CLOCK_SPEED;
 use crate::system_bus::SystemMemoryAccess;
-use crate::{GbMode, GbSpeed, T_CYCLES_PER_STEP};
-use getset::{Getters, Setters};
+use crate::vgm::{RegisterWrite, VgmLog};
+use crate::{GbSpeed, HardwareModel, t_cycles};
+use getset::{CopyGetters, Setters};
 
+mod analog;
+mod blip_buffer;
 mod length_timer;
 mod noise;
 mod period;
 mod pulse;
+mod stream;
 mod sweep;
+mod viewer;
 mod volume_envelope;
 mod wave;
 
-pub const SAMPLING_FREQUENCY: u32 = 44100;
+pub use analog::AudioOutput;
+pub use blip_buffer::SampleRate;
+pub use viewer::{ChannelView, EnvelopeView, SweepView};
+
 pub const SAMPLES_PER_FRAME: usize = 2048;
 const AUDIO_BUFFER_SIZE: usize = SAMPLES_PER_FRAME / 2;
 const CHANNEL_STEP_RATE: u8 = 4;
-const CYCLES_PER_SAMPLE: u32 = CPU_CLOCK_SPEED / SAMPLING_FREQUENCY;
+const FIRST_REGISTER: u16 = 0xFF10;
+/// NR10 to NR51, the registers that keep what was last written to them
+const REGISTER_COUNT: usize = 0x16;
+/// Wave RAM ends the range a VGM DMG chip can write to
+const LAST_LOGGED_REGISTER: u16 = 0xFF3F;
+const UNUSED_REGISTERS: [u16; 2] = [0xFF15, 0xFF1F];
+/// NR14, NR24, NR34 and NR44 in channel order
+const TRIGGER_REGISTERS: [u16; 4] = [0xFF14, 0xFF19, 0xFF1E, 0xFF23];
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum AudioChannel {
+    Pulse1,
+    Pulse2,
+    Wave,
+    Noise,
+}
+
+impl AudioChannel {
+    pub const ALL: [AudioChannel; 4] = [AudioChannel::Pulse1, AudioChannel::Pulse2, AudioChannel::Wave, AudioChannel::Noise];
 
-#[derive(Debug, Getters, Setters)]
+    pub fn name(self) -> &'static str {
+        match self {
+            AudioChannel::Pulse1 => "Pulse 1",
+            AudioChannel::Pulse2 => "Pulse 2",
+            AudioChannel::Wave => "Wave",
+            AudioChannel::Noise => "Noise",
+        }
+    }
+}
+
+#[derive(Debug, CopyGetters, Setters)]
 pub struct Apu {
     ch1: PulseChannel,
     ch2: PulseChannel,
@@ -34,19 +71,33 @@ pub struct Apu {
     sound_panning: u8,
     master_volume: u8,
     enabled: bool,
-    sample_cycles: u8,
     channel_clock: u8,
-    ch1_sample_sum: f32,
-    ch2_sample_sum: f32,
-    ch3_sample_sum: f32,
-    ch4_sample_sum: f32,
-    #[getset(get = "pub")]
-    left_audio_buffer: Vec<f32>,
-    #[getset(get = "pub")]
-    right_audio_buffer: Vec<f32>,
+    left: AudioStream,
+    right: AudioStream,
+    /// Every channel on its own, before panning and NR50
+    channel_streams: [AudioStream; 4],
+    /// The channel streams are only fed while something reads them
+    #[getset(get_copy = "pub")]
+    channel_capture: bool,
+    /// The mix again at the nominal rate, the rate adjustment only steers what is played
+    recording_streams: [AudioStream; 2],
+    #[getset(get_copy = "pub")]
+    recording: bool,
+    #[getset(get_copy = "pub", set = "pub")]
+    audio_output: AudioOutput,
+    /// Level a cartridge drives on the VIN pin
+    #[getset(set = "pub")]
+    vin_input: f32,
+    muted: [bool; 4],
+    soloed: [bool; 4],
+    /// T-cycles on the 4 MiHz clock since power on
+    clock: u64,
+    /// The last value written to each register while the APU was on
+    registers: [u8; REGISTER_COUNT],
+    vgm_log: Option<VgmLog>,
     #[getset(set = "pub")]
     speed: GbSpeed,
-    gb_mode: GbMode,
+    model: HardwareModel,
 }
 
 impl SystemMemoryAccess for Apu {
@@ -65,11 +116,29 @@ impl SystemMemoryAccess for Apu {
     }
 
     fn write_8(&mut self, address: u16, value: u8) {
+        if let Some(vgm_log) = &mut self.vgm_log
+            && address <= LAST_LOGGED_REGISTER
+        {
+            vgm_log.writes.push(RegisterWrite {
+                t_cycle: self.clock,
+                address,
+                value,
+            });
+        }
+        if self.enabled && address < FIRST_REGISTER + REGISTER_COUNT as u16 {
+            self.registers[(address - FIRST_REGISTER) as usize] = value;
+        }
+        self.write_register(address, value);
+    }
+}
+
+impl Apu {
+    fn write_register(&mut self, address: u16, value: u8) {
         match address {
             0xFF26 => return self.set_master_control(value),
             0xFF30..=0xFF3F => return self.ch3.write_8(address, value),
 
-            0xFF11 | 0xFF16 if self.enabled || self.gb_mode != GbMode::Color => {
+            0xFF11 | 0xFF16 if self.enabled || !self.model.is_color() => {
                 let masked_value = if self.enabled { value } else { value & 0x3F };
                 return match address {
                     0xFF11 => self.ch1.write_8(address, masked_value),
@@ -78,7 +147,7 @@ impl SystemMemoryAccess for Apu {
                 };
             }
 
-            0xFF1B | 0xFF20 if self.enabled || self.gb_mode != GbMode::Color => {
+            0xFF1B | 0xFF20 if self.enabled || !self.model.is_color() => {
                 return match address {
                     0xFF1B => self.ch3.write_8(address, value),
                     0xFF20 => self.ch4.write_8(address, value),
@@ -97,48 +166,147 @@ impl SystemMemoryAccess for Apu {
             _ => {}
         }
     }
-}
 
-impl Apu {
-    pub fn new(gb_mode: GbMode) -> Self {
+    pub fn new(model: HardwareModel) -> Self {
         let div_apu_step = Rc::new(RefCell::new(0));
         Apu {
-            ch1: PulseChannel::new(true, gb_mode, div_apu_step.clone()),
-            ch2: PulseChannel::new(false, gb_mode, div_apu_step.clone()),
-            ch3: WaveChannel::new(gb_mode, div_apu_step.clone()),
-            ch4: NoiseChannel::new(gb_mode, div_apu_step.clone()),
+            ch1: PulseChannel::new(true, model, div_apu_step.clone()),
+            ch2: PulseChannel::new(false, model, div_apu_step.clone()),
+            ch3: WaveChannel::new(model, div_apu_step.clone()),
+            ch4: NoiseChannel::new(model, div_apu_step.clone()),
             div_apu_step,
             previous_divider: 0,
             enabled: false,
             sound_panning: 0,
             master_volume: 0,
-            sample_cycles: 0,
             channel_clock: 0,
-            ch1_sample_sum: 0.0,
-            ch2_sample_sum: 0.0,
-            ch3_sample_sum: 0.0,
-            ch4_sample_sum: 0.0,
-            left_audio_buffer: Vec::new(),
-            right_audio_buffer: Vec::new(),
+            left: AudioStream::new(model, SampleRate::default()),
+            right: AudioStream::new(model, SampleRate::default()),
+            channel_streams: std::array::from_fn(|_| AudioStream::new(model, SampleRate::default())),
+            channel_capture: false,
+            recording_streams: std::array::from_fn(|_| AudioStream::new(model, SampleRate::default())),
+            recording: false,
+            audio_output: AudioOutput::default(),
+            vin_input: 0.0,
+            muted: [false; 4],
+            soloed: [false; 4],
+            clock: 0,
+            registers: [0; REGISTER_COUNT],
+            vgm_log: None,
             speed: GbSpeed::Normal,
-            gb_mode,
+            model,
+        }
+    }
+
+    pub fn left_audio_buffer(&self) -> &Vec<f32> {
+        self.left.samples()
+    }
+
+    pub fn right_audio_buffer(&self) -> &Vec<f32> {
+        self.right.samples()
+    }
+
+    pub fn channel_audio_buffer(&self, channel: AudioChannel) -> &Vec<f32> {
+        self.channel_streams[channel as usize].samples()
+    }
+
+    pub fn recording_audio_buffers(&self) -> (&Vec<f32>, &Vec<f32>) {
+        let [left, right] = &self.recording_streams;
+        (left.samples(), right.samples())
+    }
+
+    /// Starts or stops feeding the channel streams, they start out empty either way
+    pub fn set_channel_capture(&mut self, channel_capture: bool) {
+        if self.channel_capture != channel_capture {
+            self.channel_capture = channel_capture;
+            for stream in &mut self.channel_streams {
+                stream.clear();
+            }
+        }
+    }
+
+    /// Starts or stops feeding the recording streams, they start out empty either way
+    pub fn set_recording(&mut self, recording: bool) {
+        if self.recording != recording {
+            self.recording = recording;
+            for stream in &mut self.recording_streams {
+                stream.clear();
+            }
         }
     }
 
+    pub fn sample_rate(&self) -> SampleRate {
+        self.left.sample_rate()
+    }
+
+    /// Samples not read yet are dropped
+    pub fn set_sample_rate(&mut self, sample_rate: SampleRate) {
+        for stream in self.streams_mut() {
+            stream.set_sample_rate(sample_rate);
+        }
+    }
+
+    /// Only the played mix is adjusted, channel and recording streams keep the nominal rate
+    pub fn set_rate_adjustment(&mut self, adjustment: f64) {
+        self.left.set_rate_adjustment(adjustment);
+        self.right.set_rate_adjustment(adjustment);
+    }
+
     pub fn audio_buffers_full(&self) -> bool {
-        self.left_audio_buffer.len() >= AUDIO_BUFFER_SIZE && self.right_audio_buffer.len() >= AUDIO_BUFFER_SIZE
+        self.left.samples().len() >= AUDIO_BUFFER_SIZE && self.right.samples().len() >= AUDIO_BUFFER_SIZE
     }
 
     pub fn clear_audio_buffers(&mut self) {
-        self.left_audio_buffer.clear();
-        self.right_audio_buffer.clear();
+        for stream in self.streams_mut() {
+            stream.clear();
+        }
+    }
+
+    fn streams_mut(&mut self) -> impl Iterator<Item = &mut AudioStream> {
+        [&mut self.left, &mut self.right]
+            .into_iter()
+            .chain(self.channel_streams.iter_mut())
+            .chain(self.recording_streams.iter_mut())
     }
 
+    pub fn channel_muted(&self, channel: AudioChannel) -> bool {
+        self.muted[channel as usize]
+    }
+
+    pub fn set_channel_muted(&mut self, channel: AudioChannel, muted: bool) {
+        self.muted[channel as usize] = muted;
+    }
+
+    pub fn channel_soloed(&self, channel: AudioChannel) -> bool {
+        self.soloed[channel as usize]
+    }
+
+    pub fn set_channel_soloed(&mut self, channel: AudioChannel, soloed: bool) {
+        self.soloed[channel as usize] = soloed;
+    }
+
+    pub fn channel_views(&self) -> [ChannelView; 4] {
+        [
+            self.ch1.view(AudioChannel::Pulse1),
+            self.ch2.view(AudioChannel::Pulse2),
+            self.ch3.view(),
+            self.ch4.view(),
+        ]
+    }
+
+    /// Once any channel is soloed only soloed channels are heard, a muted channel never is
+    fn channel_audible(&self, index: usize) -> bool {
+        !self.muted[index] && (self.soloed[index] || !self.soloed.contains(&true))
+    }
+
+    /// Channels and sampling run on the 4 MiHz clock, so in double speed they only move every other M-cycle
     pub fn cycle(&mut self, divider: u8) {
-        self.channel_clock = self.channel_clock.wrapping_add(T_CYCLES_PER_STEP);
+        let t_cycles = t_cycles(self.speed);
+        self.clock += t_cycles as u64;
+        self.channel_clock = self.channel_clock.wrapping_add(t_cycles);
         if self.enabled {
             if self.channel_clock >= CHANNEL_STEP_RATE {
-                self.channel_clock = 0;
+                self.channel_clock -= CHANNEL_STEP_RATE;
 
                 self.ch1.cycle();
                 self.ch2.cycle();
@@ -148,7 +316,7 @@ impl Apu {
 
             self.cycle_div_apu(divider);
         }
-        self.sample_channels();
+        self.sample_output(t_cycles);
 
         self.previous_divider = divider;
     }
@@ -184,69 +352,60 @@ impl Apu {
         }
     }
 
-    fn mix_left(&mut self, ch1_sample: f32, ch2_sample: f32, ch3_sample: f32, ch4_sample: f32) {
-        let left_volume = (self.master_volume & 0x70) >> 4;
-        let mut left_sample = 0.0;
-        let channel_samples = [ch4_sample, ch3_sample, ch2_sample, ch1_sample];
-
-        for (i, sample) in channel_samples.iter().enumerate() {
-            if self.sound_panning & (1 << (7 - i)) != 0 {
-                left_sample += sample
+    /// NR51 bits 4-7 pick the channels sent left, bits 0-3 the ones sent right.
+    /// `master_volume` is one half of NR50, where bit 3 adds VIN and bits 0-2 set the volume
+    fn mix(&self, channel_samples: [f32; 4], panning_shift: u8, master_volume: u8) -> f32 {
+        let mut sample = 0.0;
+        for (i, channel_sample) in channel_samples.iter().enumerate() {
+            if self.sound_panning & (1 << (i as u8 + panning_shift)) != 0 && self.channel_audible(i) {
+                sample += channel_sample
             }
         }
+        if master_volume & 0x08 != 0 {
+            sample += self.vin_input;
+        }
 
-        let volume_reduction = (left_volume as f32 + 1.0) / 32.0;
-        left_sample *= volume_reduction;
-        self.left_audio_buffer.push(left_sample);
+        let volume_reduction = ((master_volume & 0x07) as f32 + 1.0) / 32.0;
+        sample * volume_reduction
     }
 
-    fn mix_right(&mut self, ch1_sample: f32, ch2_sample: f32, ch3_sample: f32, ch4_sample: f32) {
-        let right_volume = self.master_volume & 0x07;
-        let mut right_sample = 0.0;
-        let channel_samples = [ch4_sample, ch3_sample, ch2_sample, ch1_sample];
-
-        for (i, sample) in channel_samples.iter().enumerate() {
-            if self.sound_panning & (1 << (3 - i)) != 0 {
-                right_sample += sample
-            }
+    /// Feeds the current output level to the band-limited buffers, which only keep the changes
+    fn sample_output(&mut self, t_cycles: u8) {
+        let channel_samples = self.channel_samples();
+        let left_sample = self.mix(channel_samples, 4, self.master_volume >> 4);
+        let right_sample = self.mix(channel_samples, 0, self.master_volume & 0x0F);
+
+        let high_pass = self.audio_output == AudioOutput::Analog;
+        self.left.advance(left_sample, t_cycles, high_pass);
+        self.right.advance(right_sample, t_cycles, high_pass);
+        if self.recording {
+            let [left, right] = &mut self.recording_streams;
+            left.advance(left_sample, t_cycles, high_pass);
+            right.advance(right_sample, t_cycles, high_pass);
         }
-
-        let volume_reduction = (right_volume as f32 + 1.0) / 32.0;
-        right_sample *= volume_reduction;
-
-        self.right_audio_buffer.push(right_sample);
+        self.sample_channels(channel_samples, t_cycles, high_pass);
     }
 
-    fn sample_channels(&mut self) {
-        self.sample_cycles = self.sample_cycles.wrapping_add(T_CYCLES_PER_STEP);
-        let m_cycles = self.sample_cycles / T_CYCLES_PER_STEP;
-
-        let weight = (((CYCLES_PER_SAMPLE as u8 - m_cycles) as f32).ln() + 1.0) / ((CYCLES_PER_SAMPLE as f32).ln() + 1.0);
-        self.ch1_sample_sum += self.ch1.digital_output() * weight;
-        self.ch2_sample_sum += self.ch2.digital_output() * weight;
-        self.ch3_sample_sum += self.ch3.digital_output() * weight;
-        self.ch4_sample_sum += self.ch4.digital_output() * weight;
-
-        if self.sample_cycles as u32 >= CYCLES_PER_SAMPLE {
-            self.sample_cycles = 0;
-
-            let ch1_sample = convert_digital_to_analog(self.ch1_sample_sum, m_cycles);
-            let ch2_sample = convert_digital_to_analog(self.ch2_sample_sum, m_cycles);
-            let ch3_sample = convert_digital_to_analog(self.ch3_sample_sum, m_cycles);
-            let ch4_sample = convert_digital_to_analog(self.ch4_sample_sum, m_cycles);
-
-            self.mix_left(ch1_sample, ch2_sample, ch3_sample, ch4_sample);
-            self.mix_right(ch1_sample, ch2_sample, ch3_sample, ch4_sample);
-
-            self.clear_samples();
+    fn sample_channels(&mut self, channel_samples: [f32; 4], t_cycles: u8, high_pass: bool) {
+        if !self.channel_capture {
+            return;
+        }
+        for (stream, channel_sample) in self.channel_streams.iter_mut().zip(channel_samples) {
+            stream.advance(channel_sample, t_cycles, high_pass);
         }
     }
 
-    pub fn clear_samples(&mut self) {
-        self.ch1_sample_sum = 0.0;
-        self.ch2_sample_sum = 0.0;
-        self.ch3_sample_sum = 0.0;
-        self.ch4_sample_sum = 0.0;
+    fn channel_samples(&self) -> [f32; 4] {
+        let channels = [
+            (self.ch1.dac_enabled(), self.ch1.enabled(), self.ch1.digital_output()),
+            (self.ch2.dac_enabled(), self.ch2.enabled(), self.ch2.digital_output()),
+            (self.ch3.dac_enabled(), self.ch3.enabled(), self.ch3.digital_output()),
+            (self.ch4.dac_enabled(), self.ch4.enabled(), self.ch4.digital_output()),
+        ];
+        channels.map(|(dac_enabled, enabled, digital)| match self.audio_output {
+            AudioOutput::Analog => dac_output(self.model, dac_enabled, enabled, digital),
+            AudioOutput::Ideal => convert_digital_to_analog(digital),
+        })
     }
 
     pub fn master_control(&self) -> u8 {
@@ -265,8 +424,54 @@ impl Apu {
         }
     }
 
+    /// Logs every register write from now on, the log starts with writes that recreate the current state
+    pub fn start_vgm_log(&mut self) {
+        self.vgm_log = Some(VgmLog {
+            start: self.clock,
+            end: self.clock,
+            initial_state: self.initial_state(),
+            writes: Vec::new(),
+        });
+    }
+
+    pub fn stop_vgm_log(&mut self) -> Option<VgmLog> {
+        let mut vgm_log = self.vgm_log.take()?;
+        vgm_log.end = self.clock;
+        Some(vgm_log)
+    }
+
+    pub fn vgm_logging(&self) -> bool {
+        self.vgm_log.is_some()
+    }
+
+    /// Writes that bring a freshly powered APU to the current registers, retriggering the channels that are playing.
+    /// Envelopes, sweeps and length timers restart from their register values
+    fn initial_state(&self) -> Vec<(u16, u8)> {
+        // Wave RAM can be written freely while channel 3 is off
+        let mut writes = vec![(0xFF26, self.master_control() & 0x80), (0xFF1A, 0x00)];
+        writes.extend((0xFF30..).zip(self.ch3.wave_ram()));
+        if !self.enabled {
+            return writes;
+        }
+
+        for (address, value) in (FIRST_REGISTER..).zip(self.registers) {
+            match address {
+                _ if UNUSED_REGISTERS.contains(&address) => {}
+                _ if TRIGGER_REGISTERS.contains(&address) => writes.push((address, value & 0x7F)),
+                _ => writes.push((address, value)),
+            }
+        }
+        for (channel, address) in TRIGGER_REGISTERS.into_iter().enumerate() {
+            if self.master_control() & (1 << channel) != 0 {
+                writes.push((address, self.registers[(address - FIRST_REGISTER) as usize] | 0x80));
+            }
+        }
+        writes
+    }
+
     pub fn reset(&mut self) {
         self.enabled = false;
+        self.registers = [0; REGISTER_COUNT];
         self.sound_panning = 0;
         self.master_volume = 0;
         *self.div_apu_step.borrow_mut() = 0;
@@ -277,8 +482,3 @@ impl Apu {
         self.ch4.reset();
     }
 }
-
-fn convert_digital_to_analog(sample_sum: f32, cycles: u8) -> f32 {
-    let sample_average = sample_sum / cycles as f32;
-    (sample_average / 7.5) - 1.0
-}
@@ -0,0 +1,78 @@
+use crate::{HardwareModel, apu::SampleRate, cpu::CPU_CLOCK_SPEED};
+
+/// How channel levels become the output signal
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub enum AudioOutput {
+    /// Channels that are not playing are silent and nothing filters the mix
+    #[default]
+    Ideal,
+    /// DACs and the output capacitor of the model, with their offsets and pops
+    Analog,
+}
+
+impl AudioOutput {
+    pub fn name(self) -> &'static str {
+        match self {
+            AudioOutput::Analog => "analog",
+            AudioOutput::Ideal => "ideal",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            AudioOutput::Analog => AudioOutput::Ideal,
+            AudioOutput::Ideal => AudioOutput::Analog,
+        }
+    }
+}
+
+/// The level a channel DAC puts out, where 1.0 is full scale
+pub fn dac_output(model: HardwareModel, dac_enabled: bool, channel_enabled: bool, digital: f32) -> f32 {
+    match (dac_enabled, channel_enabled) {
+        (true, true) => convert_digital_to_analog(digital),
+        // An idle DAC still outputs the level of digital 0
+        (true, false) => convert_digital_to_analog(0.0),
+        // The AGB mixes digitally, so turning a DAC off does not move the output
+        (false, _) if model == HardwareModel::Agb => convert_digital_to_analog(0.0),
+        (false, _) => 0.0,
+    }
+}
+
+pub fn convert_digital_to_analog(sample: f32) -> f32 {
+    (sample / 7.5) - 1.0
+}
+
+/// The capacitor between the mixer and the amplifier, it charges toward the output and so removes its DC offset
+#[derive(Debug)]
+pub struct HighPassFilter {
+    clock_charge_factor: f64,
+    charge_factor: f32,
+    capacitor: f32,
+}
+
+impl HighPassFilter {
+    pub fn new(model: HardwareModel, sample_rate: SampleRate) -> Self {
+        let clock_charge_factor = match model {
+            HardwareModel::Dmg | HardwareModel::Sgb => 0.999958,
+            HardwareModel::Mgb | HardwareModel::Cgb | HardwareModel::Agb => 0.998943,
+        };
+        let mut filter = HighPassFilter {
+            clock_charge_factor,
+            charge_factor: 0.0,
+            capacitor: 0.0,
+        };
+        filter.set_sample_rate(sample_rate);
+        filter
+    }
+
+    /// The capacitor keeps its charge, only the amount it moves per sample changes
+    pub fn set_sample_rate(&mut self, sample_rate: SampleRate) {
+        self.charge_factor = self.clock_charge_factor.powf(CPU_CLOCK_SPEED as f64 / sample_rate.hz() as f64) as f32;
+    }
+
+    pub fn filter(&mut self, sample: f32) -> f32 {
+        let output = sample - self.capacitor;
+        self.capacitor = sample - output * self.charge_factor;
+        output
+    }
+}
@@ -0,0 +1,181 @@
+use std::{collections::VecDeque, f64::consts::PI};
+
+use crate::cpu::CPU_CLOCK_SPEED;
+
+/// Kernel positions between two output samples
+const PHASES: usize = 64;
+const HALF_WIDTH: usize = 16;
+const KERNEL_WIDTH: usize = 2 * HALF_WIDTH;
+/// Fraction of the output Nyquist frequency the kernel lets through
+const CUTOFF: f64 = 0.9;
+const KERNEL_BITS: u32 = 15;
+const KERNEL_UNIT: i32 = 1 << KERNEL_BITS;
+const AMPLITUDE_UNIT: f32 = (1 << 16) as f32;
+const INTEGRATION_STEPS: usize = 16;
+
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub enum SampleRate {
+    Hz22050,
+    Hz32000,
+    #[default]
+    Hz44100,
+    Hz48000,
+    Hz96000,
+}
+
+impl SampleRate {
+    pub const ALL: [SampleRate; 5] = [
+        SampleRate::Hz22050,
+        SampleRate::Hz32000,
+        SampleRate::Hz44100,
+        SampleRate::Hz48000,
+        SampleRate::Hz96000,
+    ];
+
+    pub fn hz(self) -> u32 {
+        match self {
+            SampleRate::Hz22050 => 22050,
+            SampleRate::Hz32000 => 32000,
+            SampleRate::Hz44100 => 44100,
+            SampleRate::Hz48000 => 48000,
+            SampleRate::Hz96000 => 96000,
+        }
+    }
+
+    pub fn from_hz(hz: u32) -> Option<Self> {
+        Self::ALL.into_iter().find(|sample_rate| sample_rate.hz() == hz)
+    }
+}
+
+/// Turns amplitude changes at T-cycle resolution into band-limited samples.
+/// Every change adds a windowed sinc step at its exact position between two samples,
+/// samples are read back by summing the steps once no later change can reach them
+#[derive(Debug)]
+pub struct BlipBuffer {
+    sample_rate: SampleRate,
+    kernel: Vec<[i32; KERNEL_WIDTH]>,
+    /// Step deltas of the samples that are not read yet, the first one is `read_sample`
+    deltas: VecDeque<i32>,
+    read_sample: u64,
+    /// The sample `clocks` counts from, moved along whenever the clock rate changes
+    origin_sample: u64,
+    clocks: u64,
+    /// Input clocks per second, off the real clock when the output is being sped up or slowed down
+    clock_rate: u64,
+    amplitude: i32,
+    level: i64,
+}
+
+impl BlipBuffer {
+    pub fn new(sample_rate: SampleRate) -> Self {
+        BlipBuffer {
+            sample_rate,
+            kernel: step_kernel(),
+            deltas: VecDeque::new(),
+            read_sample: 0,
+            origin_sample: 0,
+            clocks: 0,
+            clock_rate: CPU_CLOCK_SPEED as u64,
+            amplitude: 0,
+            level: 0,
+        }
+    }
+
+    pub fn sample_rate(&self) -> SampleRate {
+        self.sample_rate
+    }
+
+    /// Drops pending samples and starts over at the new rate, keeping the current amplitude
+    pub fn set_sample_rate(&mut self, sample_rate: SampleRate) {
+        self.sample_rate = sample_rate;
+        self.deltas.clear();
+        self.read_sample = 0;
+        self.origin_sample = 0;
+        self.clocks = 0;
+        self.level = self.amplitude as i64;
+    }
+
+    /// Resamples as if the input ran at `clock_rate`, without moving the current position
+    pub fn set_clock_rate(&mut self, clock_rate: u64) {
+        let scaled = self.clocks * self.sample_rate.hz() as u64;
+        self.origin_sample += scaled / self.clock_rate;
+        // Keep the position within the current sample
+        self.clocks = ((scaled % self.clock_rate) as u128 * clock_rate as u128 / (self.clock_rate as u128 * self.sample_rate.hz() as u128)) as u64;
+        self.clock_rate = clock_rate;
+    }
+
+    /// Sets the output from the current clock on, where 1.0 is full scale
+    pub fn set_amplitude(&mut self, amplitude: f32) {
+        let amplitude = (amplitude * AMPLITUDE_UNIT).round() as i32;
+        let delta = amplitude - self.amplitude;
+        if delta == 0 {
+            return;
+        }
+        self.amplitude = amplitude;
+
+        let scaled = self.clocks * self.sample_rate.hz() as u64;
+        let sample = self.origin_sample + scaled / self.clock_rate;
+        let phase = ((scaled % self.clock_rate) * PHASES as u64 / self.clock_rate) as usize;
+
+        let start = (sample + 1 - self.read_sample) as usize;
+        if self.deltas.len() < start + KERNEL_WIDTH {
+            self.deltas.resize(start + KERNEL_WIDTH, 0);
+        }
+
+        // Rounding errors go to the sample the step lands in so the steps always add up to the delta
+        let mut added = 0;
+        for (offset, tap) in self.kernel[phase].iter().enumerate() {
+            let part = ((delta as i64 * *tap as i64) >> KERNEL_BITS) as i32;
+            self.deltas[start + offset] += part;
+            added += part;
+        }
+        self.deltas[start + HALF_WIDTH - 1] += delta - added;
+    }
+
+    /// Moves time forward and appends every sample that can no longer change
+    pub fn advance(&mut self, clocks: u32, samples: &mut Vec<f32>) {
+        self.clocks += clocks as u64;
+        let ready = self.origin_sample + self.clocks * self.sample_rate.hz() as u64 / self.clock_rate + 1;
+        while self.read_sample < ready {
+            self.level += self.deltas.pop_front().unwrap_or(0) as i64;
+            samples.push(self.level as f32 / AMPLITUDE_UNIT);
+            self.read_sample += 1;
+        }
+    }
+}
+
+/// How much of a band-limited step lands in each sample around it, for every phase the step can start at
+fn step_kernel() -> Vec<[i32; KERNEL_WIDTH]> {
+    (0..PHASES)
+        .map(|phase| {
+            let fraction = phase as f64 / PHASES as f64;
+            let areas: [f64; KERNEL_WIDTH] = std::array::from_fn(|offset| {
+                let end = offset as f64 - HALF_WIDTH as f64 + 1.0 - fraction;
+                let step = 1.0 / INTEGRATION_STEPS as f64;
+                (0..INTEGRATION_STEPS)
+                    .map(|i| windowed_sinc(end - 1.0 + (i as f64 + 0.5) * step) * step)
+                    .sum()
+            });
+            let total: f64 = areas.iter().sum();
+
+            let mut taps = areas.map(|area| (area / total * KERNEL_UNIT as f64).round() as i32);
+            let rounding = KERNEL_UNIT - taps.iter().sum::<i32>();
+            taps[HALF_WIDTH - 1] += rounding;
+            taps
+        })
+        .collect()
+}
+
+fn windowed_sinc(x: f64) -> f64 {
+    if x.abs() >= HALF_WIDTH as f64 {
+        return 0.0;
+    }
+    let sinc = match x == 0.0 {
+        true => CUTOFF,
+        false => (PI * CUTOFF * x).sin() / (PI * x),
+    };
+    // Blackman window over the kernel width
+    let position = (x + HALF_WIDTH as f64) / KERNEL_WIDTH as f64;
+    let window = 0.42 - 0.5 * (2.0 * PI * position).cos() + 0.08 * (4.0 * PI * position).cos();
+    sinc * window
+}
@@ -1,10 +1,13 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 
+use crate::apu::AudioChannel;
 use crate::apu::length_timer::{DEFAULT_MAX_LENGTH, Length};
+use crate::apu::viewer::ChannelView;
 use crate::apu::volume_envelope::VolumeEnvelope;
+use crate::cpu::CPU_CLOCK_SPEED;
 use crate::system_bus::SystemMemoryAccess;
-use crate::{GbMode, T_CYCLES_PER_STEP};
+use crate::{HardwareModel, T_CYCLES_PER_STEP};
 use getset::{CopyGetters, Setters};
 
 #[derive(Debug, CopyGetters, Setters)]
@@ -20,7 +23,7 @@ pub struct NoiseChannel {
     control: u8,
     period_divider: u16,
     instruction_cycles: u16,
-    gb_mode: GbMode,
+    model: HardwareModel,
     div_apu_step: Rc<RefCell<u8>>,
 }
 
@@ -46,7 +49,7 @@ impl SystemMemoryAccess for NoiseChannel {
 }
 
 impl NoiseChannel {
-    pub fn new(gb_mode: GbMode, div_apu_step: Rc<RefCell<u8>>) -> Self {
+    pub fn new(model: HardwareModel, div_apu_step: Rc<RefCell<u8>>) -> Self {
         NoiseChannel {
             enabled: false,
             dac_enabled: false,
@@ -57,7 +60,7 @@ impl NoiseChannel {
             control: 0,
             period_divider: 0,
             instruction_cycles: 0,
-            gb_mode,
+            model,
             div_apu_step,
         }
     }
@@ -72,7 +75,7 @@ impl NoiseChannel {
         self.period_divider = 0;
         self.instruction_cycles = 0;
 
-        if self.gb_mode != GbMode::Color {
+        if !self.model.is_color() {
             self.length.reset();
         } else {
             self.length = Length::new(DEFAULT_MAX_LENGTH);
@@ -143,6 +146,24 @@ impl NoiseChannel {
         }
     }
 
+    pub fn view(&self) -> ChannelView {
+        let narrow_width = (self.polynomial & (1 << 3)) != 0;
+        ChannelView {
+            channel: AudioChannel::Noise,
+            enabled: self.enabled,
+            dac_enabled: self.dac_enabled,
+            frequency: CPU_CLOCK_SPEED as f64 / self.period_timer() as f64,
+            duty: None,
+            volume: self.envelope.volume(),
+            envelope: Some(self.envelope.view()),
+            length: self.length.time(),
+            length_enabled: (self.control & (1 << 6)) != 0,
+            sweep: None,
+            wave_ram: None,
+            lfsr_width: Some(if narrow_width { 7 } else { 15 }),
+        }
+    }
+
     pub fn trigger(&mut self) {
         if self.dac_enabled {
             self.enabled = true;
@@ -1,9 +1,11 @@
+use crate::apu::AudioChannel;
 use crate::apu::length_timer::{DEFAULT_MAX_LENGTH, Length};
 use crate::apu::period::Period;
 use crate::apu::sweep::Sweep;
+use crate::apu::viewer::ChannelView;
 use crate::apu::volume_envelope::VolumeEnvelope;
 use crate::system_bus::SystemMemoryAccess;
-use crate::{GbMode, T_CYCLES_PER_STEP};
+use crate::{HardwareModel, T_CYCLES_PER_STEP};
 
 use getset::{CopyGetters, Setters};
 use std::{cell::RefCell, rc::Rc};
@@ -12,13 +14,14 @@ use std::{cell::RefCell, rc::Rc};
 pub struct PulseChannel {
     #[getset(get_copy = "pub", set = "pub")]
     enabled: bool,
+    #[getset(get_copy = "pub")]
     dac_enabled: bool,
     wave_duty_position: u8,
     sweep: Option<Sweep>,
     length: Length,
     envelope: VolumeEnvelope,
     period: Period,
-    gb_mode: GbMode,
+    model: HardwareModel,
     div_apu_step: Rc<RefCell<u8>>,
 }
 
@@ -56,7 +59,7 @@ impl SystemMemoryAccess for PulseChannel {
 }
 
 impl PulseChannel {
-    pub fn new(with_sweep: bool, gb_mode: GbMode, div_apu_step: Rc<RefCell<u8>>) -> Self {
+    pub fn new(with_sweep: bool, model: HardwareModel, div_apu_step: Rc<RefCell<u8>>) -> Self {
         let sweep = match with_sweep {
             true => Some(Sweep::new()),
             false => None,
@@ -70,7 +73,7 @@ impl PulseChannel {
             length: Length::new(DEFAULT_MAX_LENGTH),
             envelope: VolumeEnvelope::new(),
             period: Period::new(),
-            gb_mode,
+            model,
             div_apu_step,
         }
     }
@@ -84,7 +87,7 @@ impl PulseChannel {
         }
         self.envelope = VolumeEnvelope::new();
         self.period = Period::new();
-        if self.gb_mode != GbMode::Color {
+        if !self.model.is_color() {
             self.length.reset();
         } else {
             self.length = Length::new(DEFAULT_MAX_LENGTH);
@@ -135,6 +138,23 @@ impl PulseChannel {
         }
     }
 
+    pub fn view(&self, channel: AudioChannel) -> ChannelView {
+        ChannelView {
+            channel,
+            enabled: self.enabled,
+            dac_enabled: self.dac_enabled,
+            frequency: 131072.0 / (2048 - self.period.value()) as f64,
+            duty: Some(self.length.initial_time() >> 6),
+            volume: self.envelope.volume(),
+            envelope: Some(self.envelope.view()),
+            length: self.length.time(),
+            length_enabled: (self.period.high() & (1 << 6)) != 0,
+            sweep: self.sweep.as_ref().map(Sweep::view),
+            wave_ram: None,
+            lfsr_width: None,
+        }
+    }
+
     pub fn cycle_sweep(&mut self) {
         if self.enabled {
             if let Some(sweep) = &mut self.sweep {
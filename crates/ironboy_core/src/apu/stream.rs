@@ -0,0 +1,65 @@
+use crate::{
+    HardwareModel,
+    apu::{SampleRate, analog::HighPassFilter, blip_buffer::BlipBuffer},
+    cpu::CPU_CLOCK_SPEED,
+};
+
+const MAX_RATE_ADJUSTMENT: f64 = 16.0;
+
+/// One output signal turned into samples, with the output capacitor applied when the analog stage is on
+#[derive(Debug)]
+pub struct AudioStream {
+    blip: BlipBuffer,
+    high_pass: HighPassFilter,
+    samples: Vec<f32>,
+}
+
+impl AudioStream {
+    pub fn new(model: HardwareModel, sample_rate: SampleRate) -> Self {
+        AudioStream {
+            blip: BlipBuffer::new(sample_rate),
+            high_pass: HighPassFilter::new(model, sample_rate),
+            samples: Vec::new(),
+        }
+    }
+
+    pub fn samples(&self) -> &Vec<f32> {
+        &self.samples
+    }
+
+    pub fn sample_rate(&self) -> SampleRate {
+        self.blip.sample_rate()
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: SampleRate) {
+        self.blip.set_sample_rate(sample_rate);
+        self.high_pass.set_sample_rate(sample_rate);
+        self.samples.clear();
+    }
+
+    /// Above 1 more samples are made for the same emulated time. Kept within 16 times either way, NaN counts as 1
+    pub fn set_rate_adjustment(&mut self, adjustment: f64) {
+        let adjustment = match adjustment.is_nan() {
+            true => 1.0,
+            false => adjustment.clamp(1.0 / MAX_RATE_ADJUSTMENT, MAX_RATE_ADJUSTMENT),
+        };
+        self.blip.set_clock_rate((CPU_CLOCK_SPEED as f64 / adjustment).round() as u64);
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Holds `level` for `t_cycles` and appends the samples that are complete
+    pub fn advance(&mut self, level: f32, t_cycles: u8, high_pass: bool) {
+        let start = self.samples.len();
+        self.blip.set_amplitude(level);
+        self.blip.advance(t_cycles as u32, &mut self.samples);
+
+        if high_pass {
+            for sample in &mut self.samples[start..] {
+                *sample = self.high_pass.filter(*sample);
+            }
+        }
+    }
+}
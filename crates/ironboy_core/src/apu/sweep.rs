@@ -1,4 +1,5 @@
 use crate::apu::period::Period;
+use crate::apu::viewer::SweepView;
 use getset::CopyGetters;
 
 #[derive(Debug, CopyGetters)]
@@ -72,6 +73,14 @@ impl Sweep {
         new_period
     }
 
+    pub fn view(&self) -> SweepView {
+        SweepView {
+            pace: self.pace,
+            decrease: self.direction,
+            step: self.step,
+        }
+    }
+
     pub fn read(&self) -> u8 {
         let pace = (self.pace & 0x07) << 4;
         let direction = (self.direction as u8) << 3;
@@ -0,0 +1,74 @@
+use crate::apu::AudioChannel;
+
+const NOTE_NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+const A4_FREQUENCY: f64 = 440.0;
+/// MIDI number of A4
+const A4_NOTE: i32 = 69;
+
+/// An APU channel decoded for debug views
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelView {
+    pub channel: AudioChannel,
+    pub enabled: bool,
+    pub dac_enabled: bool,
+    /// The tone in Hz, for noise the rate the LFSR shifts at
+    pub frequency: f64,
+    /// NRx1 bits 6-7 of the pulse channels
+    pub duty: Option<u8>,
+    /// Envelope volume for pulse and noise, NR32 output level for wave
+    pub volume: u8,
+    pub envelope: Option<EnvelopeView>,
+    /// Length steps left before the channel stops itself
+    pub length: u16,
+    pub length_enabled: bool,
+    pub sweep: Option<SweepView>,
+    /// The 32 samples of wave RAM
+    pub wave_ram: Option<[u8; 32]>,
+    /// 15 or 7 bits
+    pub lfsr_width: Option<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnvelopeView {
+    pub initial_volume: u8,
+    pub increase: bool,
+    /// 0 stops the envelope
+    pub pace: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SweepView {
+    /// 0 stops the sweep
+    pub pace: u8,
+    pub decrease: bool,
+    pub step: u8,
+}
+
+impl ChannelView {
+    /// The closest note and how many cents the tone is off from it, like "A4 +3". Noise has no note
+    pub fn note(&self) -> Option<String> {
+        if self.channel == AudioChannel::Noise || self.frequency <= 0.0 {
+            return None;
+        }
+        let semitones = 12.0 * (self.frequency / A4_FREQUENCY).log2();
+        let nearest = semitones.round();
+        let cents = ((semitones - nearest) * 100.0).round() as i32;
+        let note = A4_NOTE + nearest as i32;
+        Some(format!(
+            "{}{} {:+}",
+            NOTE_NAMES[note.rem_euclid(12) as usize],
+            note.div_euclid(12) - 1,
+            cents
+        ))
+    }
+
+    /// Duty cycle in percent of a pulse channel
+    pub fn duty_percent(&self) -> Option<f64> {
+        self.duty.map(|duty| match duty {
+            0 => 12.5,
+            1 => 25.0,
+            2 => 50.0,
+            _ => 75.0,
+        })
+    }
+}
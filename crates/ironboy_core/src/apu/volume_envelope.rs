@@ -1,3 +1,4 @@
+use crate::apu::viewer::EnvelopeView;
 use getset::{CopyGetters, Setters};
 
 #[derive(Debug, CopyGetters, Setters)]
@@ -55,6 +56,14 @@ impl VolumeEnvelope {
         self.pace = value & 0x07;
     }
 
+    pub fn view(&self) -> EnvelopeView {
+        EnvelopeView {
+            initial_volume: self.initial_volume,
+            increase: self.direction,
+            pace: self.pace,
+        }
+    }
+
     pub fn read(&self) -> u8 {
         let initial_volume = self.initial_volume << 4;
         let direction = (self.direction as u8) << 3;
@@ -1,23 +1,27 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 
+use crate::apu::AudioChannel;
 use crate::apu::length_timer::{Length, WAVE_MAX_LENGTH};
 use crate::apu::period::Period;
+use crate::apu::viewer::ChannelView;
 use crate::system_bus::SystemMemoryAccess;
-use crate::{GbMode, T_CYCLES_PER_STEP};
+use crate::{HardwareModel, T_CYCLES_PER_STEP};
 use getset::{CopyGetters, Setters};
 
 #[derive(Debug, CopyGetters, Setters)]
 pub struct WaveChannel {
     #[getset(get_copy = "pub", set = "pub")]
     enabled: bool,
+    #[getset(get_copy = "pub")]
     dac_enabled: bool,
     length: Length,
     volume: u8,
     period: Period,
     wave_position: u8,
+    #[getset(get_copy = "pub")]
     wave_ram: [u8; 0x10],
-    gb_mode: GbMode,
+    model: HardwareModel,
     div_apu_step: Rc<RefCell<u8>>,
 }
 
@@ -49,7 +53,7 @@ impl SystemMemoryAccess for WaveChannel {
 }
 
 impl WaveChannel {
-    pub fn new(gb_mode: GbMode, div_apu_step: Rc<RefCell<u8>>) -> Self {
+    pub fn new(model: HardwareModel, div_apu_step: Rc<RefCell<u8>>) -> Self {
         WaveChannel {
             enabled: false,
             dac_enabled: false,
@@ -58,7 +62,7 @@ impl WaveChannel {
             period: Period::new(),
             wave_position: 1,
             wave_ram: [0; 0x10],
-            gb_mode,
+            model,
             div_apu_step,
         }
     }
@@ -69,7 +73,7 @@ impl WaveChannel {
         self.volume = 0;
         self.period = Period::new();
 
-        if self.gb_mode != GbMode::Color {
+        if !self.model.is_color() {
             self.length.reset();
         } else {
             self.length = Length::new(WAVE_MAX_LENGTH);
@@ -122,9 +126,29 @@ impl WaveChannel {
         }
     }
 
+    pub fn view(&self) -> ChannelView {
+        ChannelView {
+            channel: AudioChannel::Wave,
+            enabled: self.enabled,
+            dac_enabled: self.dac_enabled,
+            frequency: 65536.0 / (2048 - self.period.value()) as f64,
+            duty: None,
+            volume: (self.volume & 0b01100000) >> 5,
+            envelope: None,
+            length: self.length.time(),
+            length_enabled: (self.period.high() & (1 << 6)) != 0,
+            sweep: None,
+            wave_ram: Some(std::array::from_fn(|i| match i % 2 {
+                0 => self.wave_ram[i / 2] >> 4,
+                _ => self.wave_ram[i / 2] & 0x0F,
+            })),
+            lfsr_width: None,
+        }
+    }
+
     pub fn trigger(&mut self) {
         let period_timer = self.period.timer();
-        if self.enabled && period_timer == 1 && self.gb_mode != GbMode::Color {
+        if self.enabled && period_timer == 1 && !self.model.is_color() {
             self.wave_ram_bug();
         }
 
@@ -185,7 +209,7 @@ impl WaveChannel {
         let mut wave_index = (address & 0xF) as u8;
         if self.enabled {
             wave_index = self.wave_position / 2;
-            match self.period.reloaded() || self.gb_mode == GbMode::Color {
+            match self.period.reloaded() || self.model.is_color() {
                 true => self.wave_ram[wave_index as usize],
                 false => 0xFF,
             }
@@ -198,7 +222,7 @@ impl WaveChannel {
         let mut wave_index = (address & 0xF) as u8;
         if self.enabled {
             wave_index = self.wave_position / 2;
-            if self.period.reloaded() || self.gb_mode == GbMode::Color {
+            if self.period.reloaded() || self.model.is_color() {
                 self.wave_ram[wave_index as usize] = value;
             }
         } else {
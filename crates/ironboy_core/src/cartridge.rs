@@ -5,7 +5,7 @@ use mbc5::Mbc5;
 use no_mbc::NoMbc;
 use thiserror::Error;
 
-use crate::{GbMode, system_bus::SystemMemoryAccess};
+use crate::{ppu::CompatibilityPalette, system_bus::SystemMemoryAccess};
 
 use self::header::Header;
 use std::{
@@ -22,10 +22,22 @@ mod mbc5;
 mod no_mbc;
 mod rtc;
 
+pub const ROM_BANK_SIZE: usize = 0x4000;
+const MIN_ROM_SIZE: usize = 2 * ROM_BANK_SIZE;
+
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub enum LoadMode {
+    /// Reject any ROM whose header or size doesn't match what it declares.
+    #[default]
+    Strict,
+    /// Boot whatever can be booted, recording the problems as warnings.
+    Lenient,
+}
+
 #[derive(Error, Debug)]
 pub enum CartridgeError {
-    #[error("Cartridge checksum not valid")]
-    CheckSumFailure,
+    #[error("Cartridge checksum not valid (header: {expected:#04X}, calculated: {actual:#04X})")]
+    CheckSumFailure { expected: u8, actual: u8 },
     #[error("Unsupported Cartridge type")]
     InvalidCatridgeType,
     #[error("Error reading save")]
@@ -36,6 +48,14 @@ pub enum CartridgeError {
     SaveFileFailure(#[from] std::io::Error),
     #[error("Invalid header data")]
     InvalidHeader,
+    #[error("Invalid ROM size code in header: {0:#04X}")]
+    InvalidRomSize(u8),
+    #[error("ROM is truncated, expected {expected} bytes but found {actual}")]
+    TruncatedRom { expected: usize, actual: usize },
+    #[error("ROM is oversized, expected {expected} bytes but found {actual}")]
+    OversizedRom { expected: usize, actual: usize },
+    #[error("ROM size of {0} bytes is not a multiple of the 16 KiB bank size")]
+    UnpaddedRom(usize),
 }
 
 pub trait MemoryBankController {
@@ -51,8 +71,10 @@ pub trait MemoryBankController {
 pub struct Cartridge {
     mbc: Box<dyn MemoryBankController>,
     title: String,
-    mode: GbMode,
+    supports_color: bool,
+    compatibility_palette: CompatibilityPalette,
     ram_file: PathBuf,
+    warnings: Vec<CartridgeError>,
 }
 
 impl SystemMemoryAccess for Cartridge {
@@ -60,7 +82,7 @@ impl SystemMemoryAccess for Cartridge {
         match address {
             0x0000..=0x7FFF => self.mbc.read_rom(address),
             0xA000..=0xBFFF => self.mbc.read_ram(address),
-            _ => panic!("Cartridge does not handle read from address {:#4X}", address),
+            _ => 0xFF,
         }
     }
 
@@ -68,40 +90,69 @@ impl SystemMemoryAccess for Cartridge {
         match address {
             0x0000..=0x7FFF => self.mbc.write_rom(address, value),
             0xA000..=0xBFFF => self.mbc.write_ram(address, value),
-            _ => panic!("Cartridge does not handle write to address {:#4X}", address),
+            _ => {}
         }
     }
 }
 
 impl Cartridge {
-    pub fn load(rom_file: PathBuf, buffer: Vec<u8>) -> Result<Cartridge, CartridgeError> {
-        let header = Header::load(&buffer[0x000..=0x014F])?;
+    pub fn load(rom_file: PathBuf, mut buffer: Vec<u8>, load_mode: LoadMode) -> Result<Cartridge, CartridgeError> {
+        let header = Header::load(&buffer)?;
+        let mut warnings = Vec::new();
+        let mut report = |error: CartridgeError| match load_mode {
+            LoadMode::Strict => Err(error),
+            LoadMode::Lenient => {
+                warnings.push(error);
+                Ok(())
+            }
+        };
 
-        let mut checksum: u8 = 0;
-        for address in 0x0134..=0x014C {
-            checksum = checksum.wrapping_sub(buffer[address]).wrapping_sub(1)
+        let checksum = Header::calculate_checksum(&buffer);
+        if checksum != header.checksum() {
+            report(CartridgeError::CheckSumFailure {
+                expected: header.checksum(),
+                actual: checksum,
+            })?;
         }
 
-        match checksum == header.checksum() {
-            true => Ok(()),
-            false => Err(CartridgeError::CheckSumFailure),
-        }?;
+        let actual = buffer.len();
+        match header.rom_size() {
+            Some(expected) if actual < expected => report(CartridgeError::TruncatedRom { expected, actual })?,
+            Some(expected) if actual > expected => report(CartridgeError::OversizedRom { expected, actual })?,
+            Some(_) => {}
+            None => report(CartridgeError::InvalidRomSize(header.rom_size_code()))?,
+        }
+        if !actual.is_multiple_of(ROM_BANK_SIZE) {
+            report(CartridgeError::UnpaddedRom(actual))?;
+        }
+
+        // Only reachable in lenient mode, pad with open bus so every bank is whole
+        let padded_size = header
+            .rom_size()
+            .unwrap_or(0)
+            .max(actual.next_multiple_of(ROM_BANK_SIZE))
+            .max(MIN_ROM_SIZE);
+        buffer.resize(padded_size, 0xFF);
+        let rom_banks = buffer.len() / ROM_BANK_SIZE;
 
         let mut mbc = match header.cartridge_type() {
             0x00 => NoMbc::new(buffer).map(|mbc| Box::new(mbc) as Box<dyn MemoryBankController>),
-            0x01..=0x03 => Mbc1::new(buffer, header.rom_banks(), header.ram_banks(), header.has_battery())
-                .map(|mbc| Box::new(mbc) as Box<dyn MemoryBankController>),
-            0x05..=0x06 => Mbc2::new(buffer, header.rom_banks(), header.has_battery()).map(|mbc| Box::new(mbc) as Box<dyn MemoryBankController>),
+            0x01..=0x03 => {
+                Mbc1::new(buffer, rom_banks, header.ram_banks(), header.has_battery()).map(|mbc| Box::new(mbc) as Box<dyn MemoryBankController>)
+            }
+            0x05..=0x06 => Mbc2::new(buffer, rom_banks, header.has_battery()).map(|mbc| Box::new(mbc) as Box<dyn MemoryBankController>),
             0x0F..=0x13 => Mbc3::new(
                 buffer,
+                rom_banks,
                 header.ram_banks(),
                 header.has_ram(),
                 header.has_battery(),
                 header.has_real_time_clock(),
             )
             .map(|mbc| Box::new(mbc) as Box<dyn MemoryBankController>),
-            0x19..=0x1E => Mbc5::new(buffer, header.rom_banks(), header.ram_banks(), header.has_battery())
-                .map(|mbc| Box::new(mbc) as Box<dyn MemoryBankController>),
+            0x19..=0x1E => {
+                Mbc5::new(buffer, rom_banks, header.ram_banks(), header.has_battery()).map(|mbc| Box::new(mbc) as Box<dyn MemoryBankController>)
+            }
             _ => Err(CartridgeError::InvalidCatridgeType),
         }?;
 
@@ -116,8 +167,10 @@ impl Cartridge {
         let cartridge = Cartridge {
             mbc,
             title: header.title().to_string(),
-            mode: header.mode(),
+            supports_color: header.supports_color(),
+            compatibility_palette: header.compatibility_palette(),
             ram_file,
+            warnings,
         };
         Ok(cartridge)
     }
@@ -126,9 +179,24 @@ impl Cartridge {
         self.title.as_str()
     }
 
-    pub fn mode(&self) -> GbMode {
-        self.mode
+    pub fn supports_color(&self) -> bool {
+        self.supports_color
+    }
+
+    pub fn compatibility_palette(&self) -> CompatibilityPalette {
+        self.compatibility_palette
     }
+
+    pub fn take_warnings(&mut self) -> Vec<CartridgeError> {
+        std::mem::take(&mut self.warnings)
+    }
+}
+
+/// Maps a selected bank onto the banks that physically exist. Bits above the
+/// cartridge's address lines are ignored, and ROMs that aren't a power of two
+/// in size wrap the remainder back to the start.
+pub fn mirror_bank(bank: usize, banks: usize) -> usize {
+    (bank & (banks.next_power_of_two() - 1)) % banks
 }
 
 impl Drop for Cartridge {
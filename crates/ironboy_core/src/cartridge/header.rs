@@ -2,8 +2,10 @@ use std::str::from_utf8;
 
 use getset::CopyGetters;
 
-use super::CartridgeError;
-use crate::GbMode;
+use super::{CartridgeError, ROM_BANK_SIZE};
+use crate::ppu::CompatibilityPalette;
+
+pub const HEADER_SIZE: usize = 0x0150;
 
 // May use these at some point
 #[allow(dead_code)]
@@ -12,6 +14,7 @@ pub struct Header {
     entry: [u8; 4],
     logo: [u8; 48],
     title: String,
+    raw_title: [u8; 16],
     cgb_flag: u8,
     new_licensee_code: [u8; 2],
     sgb_flag: u8,
@@ -29,10 +32,18 @@ pub struct Header {
 
 impl Header {
     pub fn load(bytes: &[u8]) -> Result<Header, CartridgeError> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(CartridgeError::TruncatedRom {
+                expected: HEADER_SIZE,
+                actual: bytes.len(),
+            });
+        }
+
         let header = Header {
             entry: bytes[0x0100..=0x0103].try_into().map_err(|_| CartridgeError::InvalidHeader)?,
             logo: bytes[0x0104..=0x0133].try_into().map_err(|_| CartridgeError::InvalidHeader)?,
             title: from_utf8(&bytes[0x0134..=0x0143]).unwrap_or("NO NAME").to_owned(),
+            raw_title: bytes[0x0134..=0x0143].try_into().map_err(|_| CartridgeError::InvalidHeader)?,
             cgb_flag: bytes[0x0143],
             new_licensee_code: bytes[0x0144..=0x0145].try_into().map_err(|_| CartridgeError::InvalidHeader)?,
             sgb_flag: bytes[0x0146],
@@ -52,15 +63,40 @@ impl Header {
         self.title.as_str()
     }
 
-    pub fn mode(&self) -> GbMode {
-        match self.cgb_flag {
-            0xC0 => GbMode::Color,
-            _ => GbMode::Monochrome,
+    pub fn nintendo_licensed(&self) -> bool {
+        self.old_licensee_code == 0x01 || (self.old_licensee_code == 0x33 && &self.new_licensee_code == b"01")
+    }
+
+    pub fn compatibility_palette(&self) -> CompatibilityPalette {
+        CompatibilityPalette::for_title(&self.raw_title, self.nintendo_licensed())
+    }
+
+    pub fn supports_color(&self) -> bool {
+        self.cgb_flag & 0x80 != 0
+    }
+
+    pub fn rom_banks(&self) -> Option<usize> {
+        match self.rom_size {
+            0x00..=0x08 => Some(2 << self.rom_size),
+            0x52 => Some(72),
+            0x53 => Some(80),
+            0x54 => Some(96),
+            _ => None,
         }
     }
 
-    pub fn rom_banks(&self) -> usize {
-        if self.rom_size <= 8 { 2 << self.rom_size } else { 0 }
+    pub fn rom_size_code(&self) -> u8 {
+        self.rom_size
+    }
+
+    pub fn rom_size(&self) -> Option<usize> {
+        self.rom_banks().map(|banks| banks * ROM_BANK_SIZE)
+    }
+
+    pub fn calculate_checksum(bytes: &[u8]) -> u8 {
+        bytes[0x0134..=0x014C]
+            .iter()
+            .fold(0u8, |checksum, byte| checksum.wrapping_sub(*byte).wrapping_sub(1))
     }
 
     pub fn ram_banks(&self) -> usize {
@@ -1,4 +1,4 @@
-use super::{CartridgeError, MemoryBankController};
+use super::{CartridgeError, MemoryBankController, ROM_BANK_SIZE, mirror_bank};
 
 pub struct Mbc1 {
     rom: Vec<u8>,
@@ -38,8 +38,8 @@ impl MemoryBankController for Mbc1 {
             },
             _ => self.current_rom_bank,
         };
-        let address = bank * 0x4000 | ((address as usize) & 0x3FFF);
-        *self.rom.get(address).unwrap_or(&0xFF)
+        let address = mirror_bank(bank, self.rom_banks) * ROM_BANK_SIZE | ((address as usize) & 0x3FFF);
+        self.rom[address]
     }
 
     fn write_rom(&mut self, address: u16, value: u8) {
@@ -52,13 +52,11 @@ impl MemoryBankController for Mbc1 {
                     0 => 1,
                     n => n,
                 };
-                self.current_rom_bank = ((self.current_rom_bank & 0xE0) | bank) % self.rom_banks;
+                self.current_rom_bank = (self.current_rom_bank & 0xE0) | bank;
             }
             0x4000..=0x5FFF => {
-                if self.rom_banks > 0x20 {
-                    let bits = (value as usize & 0x03) % (self.rom_banks >> 5);
-                    self.current_rom_bank = self.current_rom_bank & 0x1F | (bits << 5)
-                }
+                let bits = value as usize & 0x03;
+                self.current_rom_bank = self.current_rom_bank & 0x1F | (bits << 5);
                 if self.ram_banks > 1 {
                     self.current_ram_bank = (value as usize) & 0x03;
                 }
@@ -75,7 +73,7 @@ impl MemoryBankController for Mbc1 {
             return 0xFF;
         }
         let rambank = if self.banking_mode == 1 { self.current_ram_bank } else { 0 };
-        self.ram[(rambank * 0x2000) | ((address & 0x1FFF) as usize)]
+        self.ram.get((rambank * 0x2000) | ((address & 0x1FFF) as usize)).copied().unwrap_or(0xFF)
     }
 
     fn write_ram(&mut self, address: u16, value: u8) {
@@ -1,4 +1,4 @@
-use super::{CartridgeError, MemoryBankController};
+use super::{CartridgeError, MemoryBankController, ROM_BANK_SIZE, mirror_bank};
 
 pub struct Mbc2 {
     rom: Vec<u8>,
@@ -29,8 +29,8 @@ impl MemoryBankController for Mbc2 {
             0x0000..=0x3FFF => 0,
             _ => self.current_rom_bank,
         };
-        let address = bank * 0x4000 | ((address as usize) & 0x3FFF);
-        *self.rom.get(address).unwrap_or(&0xFF)
+        let address = mirror_bank(bank, self.rom_banks) * ROM_BANK_SIZE | ((address as usize) & 0x3FFF);
+        self.rom[address]
     }
 
     fn write_rom(&mut self, address: u16, value: u8) {
@@ -42,7 +42,7 @@ impl MemoryBankController for Mbc2 {
                     self.current_rom_bank = match (value as usize) & 0x0F {
                         0 => 1,
                         n => n,
-                    } % self.rom_banks;
+                    };
                 }
             }
             _ => {}
@@ -2,7 +2,7 @@ use std::convert::TryInto;
 use std::io::prelude::*;
 
 use super::rtc::RealTimeClock;
-use super::{CartridgeError, MemoryBankController};
+use super::{CartridgeError, MemoryBankController, ROM_BANK_SIZE, mirror_bank};
 
 pub struct Mbc3 {
     rom: Vec<u8>,
@@ -10,6 +10,7 @@ pub struct Mbc3 {
     ram_enabled: bool,
     current_rom_bank: usize,
     current_ram_bank: usize,
+    rom_banks: usize,
     ram_banks: usize,
     has_battery: bool,
     select_rtc_register: bool,
@@ -17,7 +18,14 @@ pub struct Mbc3 {
 }
 
 impl Mbc3 {
-    pub fn new(buffer: Vec<u8>, ram_banks: usize, has_ram: bool, has_battery: bool, has_real_time_clock: bool) -> Result<Mbc3, CartridgeError> {
+    pub fn new(
+        buffer: Vec<u8>,
+        rom_banks: usize,
+        ram_banks: usize,
+        has_ram: bool,
+        has_battery: bool,
+        has_real_time_clock: bool,
+    ) -> Result<Mbc3, CartridgeError> {
         let ram_banks = match has_ram {
             true => ram_banks,
             false => 0,
@@ -29,6 +37,7 @@ impl Mbc3 {
             ram_enabled: false,
             current_rom_bank: 1,
             current_ram_bank: 0,
+            rom_banks,
             ram_banks,
             has_battery,
             select_rtc_register: false,
@@ -44,8 +53,8 @@ impl MemoryBankController for Mbc3 {
             0x0000..=0x3FFF => 0,
             _ => self.current_rom_bank,
         };
-        let address = bank * 0x4000 | ((address as usize) & 0x3FFF);
-        *self.rom.get(address).unwrap_or(&0xFF)
+        let address = mirror_bank(bank, self.rom_banks) * ROM_BANK_SIZE | ((address as usize) & 0x3FFF);
+        self.rom[address]
     }
 
     fn write_rom(&mut self, address: u16, value: u8) {
@@ -62,7 +71,7 @@ impl MemoryBankController for Mbc3 {
                 self.current_ram_bank = (value & 0x7) as usize;
             }
             0x6000..=0x7FFF => self.rtc.set_latch_registers(),
-            _ => panic!("Could not write to {:04X} (MBC3)", address),
+            _ => {}
         }
     }
 
@@ -1,4 +1,4 @@
-use super::{CartridgeError, MemoryBankController};
+use super::{CartridgeError, MemoryBankController, ROM_BANK_SIZE, mirror_bank};
 
 pub struct Mbc5 {
     rom: Vec<u8>,
@@ -33,16 +33,16 @@ impl MemoryBankController for Mbc5 {
             0x0000..=0x3FFF => 0,
             _ => self.current_rom_bank,
         };
-        let address = bank * 0x4000 | ((address as usize) & 0x3FFF);
-        *self.rom.get(address).unwrap_or(&0xFF)
+        let address = mirror_bank(bank, self.rom_banks) * ROM_BANK_SIZE | ((address as usize) & 0x3FFF);
+        self.rom[address]
     }
 
     fn write_rom(&mut self, address: u16, value: u8) {
         match address {
             0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
-            0x2000..=0x2FFF => self.current_rom_bank = ((self.current_rom_bank & 0x100) | (value as usize)) % self.rom_banks,
-            0x3000..=0x3FFF => self.current_rom_bank = ((self.current_rom_bank & 0x0FF) | (((value & 0x1) as usize) << 8)) % self.rom_banks,
-            0x4000..=0x5FFF => self.current_ram_bank = ((value & 0x0F) as usize) % self.ram_banks,
+            0x2000..=0x2FFF => self.current_rom_bank = (self.current_rom_bank & 0x100) | (value as usize),
+            0x3000..=0x3FFF => self.current_rom_bank = (self.current_rom_bank & 0x0FF) | (((value & 0x1) as usize) << 8),
+            0x4000..=0x5FFF => self.current_ram_bank = ((value & 0x0F) as usize) % self.ram_banks.max(1),
             _ => {}
         }
     }
@@ -51,14 +51,19 @@ impl MemoryBankController for Mbc5 {
         if !self.ram_enabled {
             return 0;
         }
-        self.ram[self.current_ram_bank * 0x2000 | ((address as usize) & 0x1FFF)]
+        self.ram
+            .get(self.current_ram_bank * 0x2000 | ((address as usize) & 0x1FFF))
+            .copied()
+            .unwrap_or(0xFF)
     }
 
     fn write_ram(&mut self, address: u16, value: u8) {
         if self.ram_enabled == false {
             return;
         }
-        self.ram[self.current_ram_bank * 0x2000 | ((address as usize) & 0x1FFF)] = value;
+        if let Some(byte) = self.ram.get_mut(self.current_ram_bank * 0x2000 | ((address as usize) & 0x1FFF)) {
+            *byte = value;
+        }
     }
 
     fn load_ram(&mut self, data: &[u8]) -> Result<(), CartridgeError> {
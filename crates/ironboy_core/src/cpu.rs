@@ -3,7 +3,7 @@ use std::{cell::RefCell, rc::Rc};
 use getset::{CopyGetters, Getters, MutGetters, Setters};
 use tracing::debug;
 
-use crate::{GbMode, cpu::instructions::Instruction, interrupts::InterruptKind};
+use crate::{GbMode, HardwareModel, cpu::instructions::Instruction, interrupts::InterruptKind};
 
 use self::registers::Registers;
 
@@ -15,6 +15,14 @@ mod registers;
 
 pub const CPU_CLOCK_SPEED: u32 = 4194304;
 
+/// Raised when the CPU executes one of the illegal opcodes. Real hardware hangs
+/// until it's powered off, interrupts included, so nothing clears it.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Lockup {
+    pub opcode: u8,
+    pub address: u16,
+}
+
 #[derive(Getters, MutGetters, CopyGetters, Setters)]
 pub struct Cpu<I: MemoryInterface> {
     #[getset(get = "pub", get_mut = "pub")]
@@ -29,15 +37,17 @@ pub struct Cpu<I: MemoryInterface> {
     opcode: u8,
     #[getset(get = "pub")]
     instruction: Instruction,
+    #[getset(get_copy = "pub")]
+    lockup: Option<Lockup>,
     debugging: bool,
     disassemble: bool,
 }
 
 impl<I: MemoryInterface> Cpu<I> {
-    pub fn new(bus: I, mode: GbMode, halted: Rc<RefCell<bool>>) -> Self {
+    pub fn new(bus: I, model: HardwareModel, mode: GbMode, halted: Rc<RefCell<bool>>) -> Self {
         Cpu {
             bus,
-            registers: Registers::new(mode),
+            registers: Registers::new(model, mode),
             interrupt_master_enable: false,
             enable_interrupt_delay: 0,
             disable_interrupt_delay: 0,
@@ -45,6 +55,7 @@ impl<I: MemoryInterface> Cpu<I> {
             halt_bug: false,
             opcode: 0x00,
             instruction: Instruction::Nop,
+            lockup: None,
             //TODO: add flags for this
             debugging: false,
             disassemble: false,
@@ -52,10 +63,18 @@ impl<I: MemoryInterface> Cpu<I> {
     }
 
     pub fn cycle(&mut self) {
+        if self.lockup.is_some() {
+            self.bus.m_cycle();
+            return;
+        }
+
         if !*self.halted.borrow() {
             self.update_interrupt_master_enable();
             self.execute_instruction();
             self.log_cycle(self.registers.pc());
+            if self.lockup.is_some() {
+                return;
+            }
         } else {
             self.bus.m_cycle();
         }
@@ -90,12 +109,15 @@ impl<I: MemoryInterface> Cpu<I> {
     }
 
     fn pop_stack(&mut self) -> u16 {
-        let value = self.bus.load_16(self.registers.sp(), true);
-        self.registers.set_sp(self.registers.sp().wrapping_add(2));
-        value
+        let sp = self.registers.sp();
+        let lo = self.bus.load_8_with_increment(sp, true) as u16;
+        let hi = self.bus.load_8(sp.wrapping_add(1), true) as u16;
+        self.registers.set_sp(sp.wrapping_add(2));
+        hi << 8 | lo
     }
 
     fn push_stack(&mut self, value: u16) {
+        self.bus.idu_access(self.registers.sp());
         self.bus.m_cycle();
         self.registers.set_sp(self.registers.sp().wrapping_sub(2));
         self.bus.store_16(self.registers.sp(), value, true);
@@ -193,19 +215,27 @@ pub trait MemoryInterface {
 
     fn load_16(&mut self, address: u16, with_cycles: bool) -> u16 {
         let lo = self.load_8(address, with_cycles) as u16;
-        let hi = self.load_8(address + 1, with_cycles) as u16;
+        let hi = self.load_8(address.wrapping_add(1), with_cycles) as u16;
         hi << 8 | lo
     }
 
+    /// A read whose address register is incremented or decremented in the same M-cycle
+    fn load_8_with_increment(&mut self, address: u16, with_cycles: bool) -> u8 {
+        self.load_8(address, with_cycles)
+    }
+
     fn store_8(&mut self, address: u16, value: u8, with_cycles: bool);
 
     fn store_16(&mut self, address: u16, value: u16, with_cycles: bool) {
         self.store_8(address, (value & 0xFF) as u8, with_cycles);
-        self.store_8(address + 1, (value >> 8) as u8, with_cycles);
+        self.store_8(address.wrapping_add(1), (value >> 8) as u8, with_cycles);
     }
 
     fn m_cycle(&mut self);
 
+    /// An address put on the bus by the increment/decrement unit without a memory access
+    fn idu_access(&mut self, _address: u16) {}
+
     fn total_m_cycles(&self) -> u64;
 
     fn pending_interrupt(&self) -> u8;
@@ -358,6 +358,10 @@ pub fn halt() -> String {
     "HALT".to_string()
 }
 
+pub fn illegal(opcode: u8) -> String {
+    format!("ILLEGAL {:#04X}", opcode)
+}
+
 pub fn di() -> String {
     "DI".to_string()
 }
@@ -1,5 +1,7 @@
+use tracing::warn;
+
 use crate::cpu::{
-    Cpu, MemoryInterface,
+    Cpu, Lockup, MemoryInterface,
     operands::{Condition, R8, R16, R16Memory, R16Stack},
 };
 
@@ -16,9 +18,12 @@ pub fn ld_r16mem_a<I: MemoryInterface>(cpu: &mut Cpu<I>) {
 }
 
 pub fn ld_a_r16mem<I: MemoryInterface>(cpu: &mut Cpu<I>) {
-    let source = (cpu.opcode & 0b0011_0000) >> 4;
-    let address = R16Memory::from(source).load(cpu);
-    let byte = cpu.bus.load_8(address, true);
+    let source = R16Memory::from((cpu.opcode & 0b0011_0000) >> 4);
+    let address = source.load(cpu);
+    let byte = match source {
+        R16Memory::HLI | R16Memory::HLD => cpu.bus.load_8_with_increment(address, true),
+        _ => cpu.bus.load_8(address, true),
+    };
     cpu.registers.set_a(byte);
 }
 
@@ -341,8 +346,10 @@ pub fn cp_a_imm8<I: MemoryInterface>(cpu: &mut Cpu<I>) {
 pub fn inc_r16<I: MemoryInterface>(cpu: &mut Cpu<I>) {
     let operand = (cpu.opcode & 0b0011_0000) >> 4;
     let register = R16::from(operand);
-    let value = register.load(cpu).wrapping_add(1);
+    let previous = register.load(cpu);
+    let value = previous.wrapping_add(1);
     register.store(cpu, value);
+    cpu.bus.idu_access(previous);
     cpu.bus.m_cycle();
 }
 
@@ -361,8 +368,10 @@ pub fn inc_r8<I: MemoryInterface>(cpu: &mut Cpu<I>) {
 pub fn dec_r16<I: MemoryInterface>(cpu: &mut Cpu<I>) {
     let operand = (cpu.opcode & 0b0011_0000) >> 4;
     let register = R16::from(operand);
-    let value = register.load(cpu).wrapping_sub(1);
+    let previous = register.load(cpu);
+    let value = previous.wrapping_sub(1);
     register.store(cpu, value);
+    cpu.bus.idu_access(previous);
     cpu.bus.m_cycle();
 }
 
@@ -536,9 +545,9 @@ pub fn jr_cond_signed_imm8<I: MemoryInterface>(cpu: &mut Cpu<I>) {
 
     if jump {
         let signed_byte = cpu.fetch_byte() as i8;
-        cpu.registers.set_pc(((cpu.registers.pc() as i16) + (signed_byte as i16)) as u16);
+        cpu.registers.set_pc(cpu.registers.pc().wrapping_add_signed(signed_byte as i16));
     } else {
-        cpu.registers.set_pc(cpu.registers.pc() + 1);
+        cpu.registers.set_pc(cpu.registers.pc().wrapping_add(1));
     }
     cpu.bus.m_cycle();
 }
@@ -626,7 +635,7 @@ pub fn call_cond_imm16<I: MemoryInterface>(cpu: &mut Cpu<I>) {
 }
 
 pub fn call_imm16<I: MemoryInterface>(cpu: &mut Cpu<I>) {
-    cpu.push_stack(cpu.registers.pc() + 2);
+    cpu.push_stack(cpu.registers.pc().wrapping_add(2));
     let word = cpu.fetch_word();
     cpu.registers.set_pc(word);
 }
@@ -720,6 +729,15 @@ pub fn halt<I: MemoryInterface>(cpu: &mut Cpu<I>) {
     cpu.halt_bug = cpu.bus.pending_interrupt() != 0 && !cpu.interrupt_master_enable
 }
 
+pub fn illegal<I: MemoryInterface>(cpu: &mut Cpu<I>) {
+    let lockup = Lockup {
+        opcode: cpu.opcode,
+        address: cpu.registers.pc().wrapping_sub(1),
+    };
+    warn!("CPU locked up executing illegal opcode {:#04X} at {:#06X}", lockup.opcode, lockup.address);
+    cpu.lockup = Some(lockup);
+}
+
 pub fn di<I: MemoryInterface>(cpu: &mut Cpu<I>) {
     cpu.disable_interrupt_delay = 2;
 }
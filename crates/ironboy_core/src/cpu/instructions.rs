@@ -71,6 +71,7 @@ pub enum Instruction {
     LdSpHl,
     Di,
     Ei,
+    Illegal,
 }
 
 impl From<u8> for Instruction {
@@ -139,7 +140,7 @@ impl From<u8> for Instruction {
             0xDE => SbcAImm8,
             0xEE => XorAImm8,
             0xFE => CpAImm8,
-            code => panic!("Code {:#04X} not implemented", code),
+            0xD3 | 0xDB | 0xDD | 0xE3 | 0xE4 | 0xEB | 0xEC | 0xED | 0xF4 | 0xFC | 0xFD => Illegal,
         }
     }
 }
@@ -210,6 +211,7 @@ impl Instruction {
             Di => dissassemble::di(),
             Ei => dissassemble::ei(),
             Prefix => dissassemble::prefix(next_byte),
+            Illegal => dissassemble::illegal(opcode),
         }
     }
 }
@@ -279,6 +281,7 @@ impl<I: MemoryInterface> Cpu<I> {
             Prefix => execute::prefix(self),
             Di => execute::di(self),
             Ei => execute::ei(self),
+            Illegal => execute::illegal(self),
             Nop => {}
         }
     }
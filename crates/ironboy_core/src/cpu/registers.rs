@@ -1,6 +1,6 @@
 use getset::{CopyGetters, MutGetters, Setters};
 
-use crate::GbMode;
+use crate::{GbMode, HardwareModel};
 
 use bitfields::bitfield;
 
@@ -32,44 +32,29 @@ pub struct Registers {
 }
 
 impl Registers {
-    pub fn new(mode: GbMode) -> Self {
-        match mode {
-            GbMode::Monochrome => Registers {
-                a: 0x01,
-                f: Flags::from(0b1011_0000),
-                b: 0x00,
-                c: 0x13,
-                d: 0x00,
-                e: 0xD8,
-                h: 0x01,
-                l: 0x4D,
-                pc: 0x0100,
-                sp: 0xFFFE,
-            },
-            GbMode::Color => Registers {
-                a: 0x11,
-                f: Flags::from(0b1000_0000),
-                b: 0x00,
-                c: 0x00,
-                d: 0xFF,
-                e: 0x56,
-                h: 0x00,
-                l: 0x0D,
-                pc: 0x0100,
-                sp: 0xFFFE,
-            },
-            GbMode::ColorAsMonochrome => Registers {
-                a: 0x11,
-                f: Flags::from(0b1000_0000),
-                b: 0x00,
-                c: 0x00,
-                d: 0x00,
-                e: 0x08,
-                h: 0x00,
-                l: 0x7C,
-                pc: 0x0100,
-                sp: 0xFFFE,
-            },
+    pub fn new(model: HardwareModel, mode: GbMode) -> Self {
+        let registers = |a: u8, f: u8, bc: u16, de: u16, hl: u16| Registers {
+            a,
+            f: Flags::from(f),
+            b: (bc >> 8) as u8,
+            c: bc as u8,
+            d: (de >> 8) as u8,
+            e: de as u8,
+            h: (hl >> 8) as u8,
+            l: hl as u8,
+            pc: 0x0100,
+            sp: 0xFFFE,
+        };
+
+        match (model, mode) {
+            (HardwareModel::Dmg, _) => registers(0x01, 0b1011_0000, 0x0013, 0x00D8, 0x014D),
+            (HardwareModel::Mgb, _) => registers(0xFF, 0b1011_0000, 0x0013, 0x00D8, 0x014D),
+            (HardwareModel::Sgb, _) => registers(0x01, 0b0000_0000, 0x0014, 0x0000, 0xC060),
+            (HardwareModel::Cgb, GbMode::Color) => registers(0x11, 0b1000_0000, 0x0000, 0xFF56, 0x000D),
+            (HardwareModel::Cgb, _) => registers(0x11, 0b1000_0000, 0x0000, 0x0008, 0x007C),
+            // The AGB boot ROM finishes with an extra inc b
+            (HardwareModel::Agb, GbMode::Color) => registers(0x11, 0b0000_0000, 0x0100, 0xFF56, 0x000D),
+            (HardwareModel::Agb, _) => registers(0x11, 0b0000_0000, 0x0100, 0x0008, 0x007C),
         }
     }
 
@@ -111,13 +96,13 @@ impl Registers {
 
     pub fn decrement_hl(&mut self) -> u16 {
         let hl = self.hl();
-        self.set_hl(hl - 1);
+        self.set_hl(hl.wrapping_sub(1));
         hl
     }
 
     pub fn increment_hl(&mut self) -> u16 {
         let hl = self.hl();
-        self.set_hl(hl + 1);
+        self.set_hl(hl.wrapping_add(1));
         hl
     }
 }
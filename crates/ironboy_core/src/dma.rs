@@ -51,7 +51,7 @@ impl SystemMemoryAccess for Dma {
                 let mode = (self.vram_dma_mode == VramDmaMode::Stopped) as u8;
                 length | (mode << 7)
             }
-            _ => panic!("DMA does not handle read {:#04X}", address),
+            _ => 0xFF,
         }
     }
 
@@ -65,7 +65,7 @@ impl SystemMemoryAccess for Dma {
             0xFF52 => self.vram_dma_source_address = (self.vram_dma_source_address & 0xFF00) | (value & 0xF0) as u16,
             0xFF53 => self.vram_dma_destination_address = (self.vram_dma_destination_address & 0x00FF) | ((value & 0x1F) as u16) << 8,
             0xFF54 => self.vram_dma_destination_address = (self.vram_dma_destination_address & 0xFF00) | (value & 0xF0) as u16,
-            _ => panic!("DMA does not handle write {:#04X}", address),
+            _ => {}
         }
     }
 }
@@ -112,7 +112,7 @@ impl Dma {
         };
         ctx.ppu.write_8(0xFE00 | (self.oam_dma_source_address & 0x00FF), byte);
 
-        self.oam_dma_source_address += 1;
+        self.oam_dma_source_address = self.oam_dma_source_address.wrapping_add(1);
         self.oam_dma_cycles -= t_cycles(ctx.speed) as u16;
     }
 
@@ -1,13 +1,16 @@
 use std::{cell::RefCell, rc::Rc};
 
-use getset::Getters;
+use getset::{CopyGetters, Getters};
 use thiserror::Error;
 
 use crate::{
-    JoypadButton,
-    cartridge::{Cartridge, CartridgeError},
-    cpu::Cpu,
+    AudioChannel, AudioOutput, ChannelView, ColorCorrection, DmgPalette, GbMode, HardwareModel, JoypadButton, ObjectView, PaletteView, PixelFormat,
+    RenderOptions, SampleRate, TileMapLayer, TileMapView, ViewerPalette,
+    cartridge::{Cartridge, CartridgeError, LoadMode},
+    cpu::{Cpu, Lockup},
+    ppu::CompatibilityPalette,
     system_bus::SystemBus,
+    vgm::VgmLog,
 };
 
 #[derive(Error, Debug)]
@@ -18,27 +21,65 @@ pub enum GameBoyError {
     EmptyPath,
 }
 
-#[derive(Getters)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct GameBoyConfig {
+    pub load_mode: LoadMode,
+    /// Picked from the cartridge header when not set
+    pub model: Option<HardwareModel>,
+    pub pixel_format: PixelFormat,
+    pub sample_rate: SampleRate,
+    pub audio_output: AudioOutput,
+}
+
+#[derive(Getters, CopyGetters)]
 pub struct GameBoy {
     cpu: Cpu<SystemBus>,
+    #[getset(get_copy = "pub")]
+    model: HardwareModel,
+    #[getset(get_copy = "pub")]
+    mode: GbMode,
     #[getset(get = "pub")]
     game_title: String,
     #[getset(get = "pub")]
     rom_name: String,
+    #[getset(get = "pub")]
+    load_warnings: Vec<CartridgeError>,
 }
 
 impl GameBoy {
     pub fn new(rom_path: &str, buffer: Vec<u8>) -> Result<GameBoy, GameBoyError> {
-        let cartridge = Cartridge::load(rom_path.into(), buffer)?;
+        GameBoy::with_config(rom_path, buffer, GameBoyConfig::default())
+    }
+
+    pub fn with_config(rom_path: &str, buffer: Vec<u8>, config: GameBoyConfig) -> Result<GameBoy, GameBoyError> {
+        let mut cartridge = Cartridge::load(rom_path.into(), buffer, config.load_mode)?;
+        let load_warnings = cartridge.take_warnings();
         let game_title = cartridge.title().to_string();
         let rom_name = rom_path.split("/").last().ok_or(GameBoyError::EmptyPath)?.to_string();
-        let mode = cartridge.mode();
+        let model = config.model.unwrap_or(HardwareModel::for_cartridge(cartridge.supports_color()));
+        let mode = GbMode::new(model, cartridge.supports_color());
         let halted = Rc::new(RefCell::new(false));
-        Ok(GameBoy {
-            cpu: Cpu::new(SystemBus::new(cartridge, halted.clone()), mode, halted),
+        let mut game_boy = GameBoy {
+            cpu: Cpu::new(SystemBus::new(cartridge, model, mode, halted.clone()), model, mode, halted),
+            model,
+            mode,
             game_title,
             rom_name,
-        })
+            load_warnings,
+        };
+        game_boy.set_pixel_format(config.pixel_format);
+        game_boy.set_sample_rate(config.sample_rate);
+        game_boy.set_audio_output(config.audio_output);
+        Ok(game_boy)
+    }
+
+    /// T-cycles run since power on, counted on the 4 MiHz clock so double speed does not change the pace
+    pub fn t_cycles(&self) -> u64 {
+        *self.cpu.bus().total_t_cycles()
+    }
+
+    pub fn step(&mut self) {
+        self.cpu.cycle();
     }
 
     pub fn run_until_frame_complete(&mut self) -> bool {
@@ -54,6 +95,23 @@ impl GameBoy {
         }
     }
 
+    /// Like `run_until_frame_complete`, but gives up once `budget` T-cycles have run, finishing the instruction it is
+    /// on. Returns the T-cycles the frame took, or None when it did not complete in time
+    pub fn run_until_frame_complete_within(&mut self, budget: u64) -> Option<u64> {
+        let start = self.t_cycles();
+        while self.t_cycles() - start < budget {
+            self.cpu.cycle();
+
+            let frame_ready = self.cpu.bus().ppu().frame_ready();
+            self.cpu.bus_mut().ppu_mut().set_frame_ready(false);
+
+            if frame_ready {
+                return Some(self.t_cycles() - start);
+            }
+        }
+        None
+    }
+
     pub fn run_until_audio_buffer_full(&mut self) -> (&[f32], &[f32]) {
         self.cpu.bus_mut().apu_mut().clear_audio_buffers();
 
@@ -67,8 +125,206 @@ impl GameBoy {
         (left_samples, right_samples)
     }
 
-    pub fn current_frame(&self) -> &Vec<(u8, u8, u8)> {
-        self.cpu.bus().ppu().frame_buffer()
+    /// Runs one frame and returns the samples made during it, for callers that pace by video frames
+    pub fn run_frame_with_audio(&mut self) -> (&[f32], &[f32]) {
+        self.cpu.bus_mut().apu_mut().clear_audio_buffers();
+        self.run_until_frame_complete();
+        self.audio_samples()
+    }
+
+    /// Samples produced since the last `run_until_audio_buffer_full` or `run_frame_with_audio`, which start by clearing them
+    pub fn audio_samples(&self) -> (&[f32], &[f32]) {
+        let apu = self.cpu.bus().apu();
+        (apu.left_audio_buffer(), apu.right_audio_buffer())
+    }
+
+    /// Keeps a copy of the mix at the nominal sample rate for recordings, whatever rate adjustment is set
+    pub fn set_recording(&mut self, recording: bool) {
+        self.cpu.bus_mut().apu_mut().set_recording(recording);
+    }
+
+    pub fn recording(&self) -> bool {
+        self.cpu.bus().apu().recording()
+    }
+
+    /// Like `audio_samples` without the rate adjustment. Empty unless recording is on
+    pub fn recording_samples(&self) -> (&[f32], &[f32]) {
+        let (left, right) = self.cpu.bus().apu().recording_audio_buffers();
+        (left, right)
+    }
+
+    /// Logs APU register writes for VGM export until `stop_vgm_log`, a log that was running is dropped
+    pub fn start_vgm_log(&mut self) {
+        self.cpu.bus_mut().apu_mut().start_vgm_log();
+    }
+
+    pub fn stop_vgm_log(&mut self) -> Option<VgmLog> {
+        self.cpu.bus_mut().apu_mut().stop_vgm_log()
+    }
+
+    pub fn vgm_logging(&self) -> bool {
+        self.cpu.bus().apu().vgm_logging()
+    }
+
+    /// Channel samples are only made while capture is on, it is off by default because it slows emulation down
+    pub fn set_channel_capture(&mut self, channel_capture: bool) {
+        self.cpu.bus_mut().apu_mut().set_channel_capture(channel_capture);
+    }
+
+    pub fn channel_capture(&self) -> bool {
+        self.cpu.bus().apu().channel_capture()
+    }
+
+    /// Samples of one channel on its own since the last `run_until_audio_buffer_full`, whether it is muted or not.
+    /// Empty unless channel capture is on
+    pub fn channel_samples(&self, channel: AudioChannel) -> &[f32] {
+        self.cpu.bus().apu().channel_audio_buffer(channel)
+    }
+
+    /// The registers and internal state of every channel, in `AudioChannel` order
+    pub fn channel_views(&self) -> [ChannelView; 4] {
+        self.cpu.bus().apu().channel_views()
+    }
+
+    pub fn channel_muted(&self, channel: AudioChannel) -> bool {
+        self.cpu.bus().apu().channel_muted(channel)
+    }
+
+    /// Leaves the channel out of the mixed samples
+    pub fn set_channel_muted(&mut self, channel: AudioChannel, muted: bool) {
+        self.cpu.bus_mut().apu_mut().set_channel_muted(channel, muted);
+    }
+
+    pub fn channel_soloed(&self, channel: AudioChannel) -> bool {
+        self.cpu.bus().apu().channel_soloed(channel)
+    }
+
+    /// While any channel is soloed the mixed samples only have the soloed channels
+    pub fn set_channel_soloed(&mut self, channel: AudioChannel, soloed: bool) {
+        self.cpu.bus_mut().apu_mut().set_channel_soloed(channel, soloed);
+    }
+
+    pub fn sample_rate(&self) -> SampleRate {
+        self.cpu.bus().apu().sample_rate()
+    }
+
+    /// Samples that were not handed out yet are dropped
+    pub fn set_sample_rate(&mut self, sample_rate: SampleRate) {
+        self.cpu.bus_mut().apu_mut().set_sample_rate(sample_rate);
+    }
+
+    /// Makes slightly more (above 1) or fewer samples for the same emulated time, to keep an audio queue from
+    /// running dry or piling up when the host's audio clock drifts from the emulated one
+    pub fn set_audio_rate_adjustment(&mut self, adjustment: f64) {
+        self.cpu.bus_mut().apu_mut().set_rate_adjustment(adjustment);
+    }
+
+    pub fn audio_output(&self) -> AudioOutput {
+        self.cpu.bus().apu().audio_output()
+    }
+
+    pub fn set_audio_output(&mut self, audio_output: AudioOutput) {
+        self.cpu.bus_mut().apu_mut().set_audio_output(audio_output);
+    }
+
+    /// The level a cartridge drives on VIN, NR50 mixes it in like a fifth channel. No emulated cartridge drives it
+    pub fn set_vin_input(&mut self, level: f32) {
+        self.cpu.bus_mut().apu_mut().set_vin_input(level);
+    }
+
+    /// Recolors a DMG cartridge running on CGB hardware, as holding the buttons during boot would.
+    /// Returns false when not in compatibility mode or the buttons aren't a valid combination
+    pub fn select_compatibility_palette(&mut self, direction: JoypadButton, modifier: Option<JoypadButton>) -> bool {
+        if self.mode != GbMode::ColorAsMonochrome {
+            return false;
+        }
+
+        match CompatibilityPalette::for_buttons(direction, modifier) {
+            Some(palette) => {
+                self.cpu.bus_mut().ppu_mut().load_compatibility_palette(palette);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Colors used for the DMG shades, ignored by CGB games
+    pub fn dmg_palette(&self) -> DmgPalette {
+        self.cpu.bus().ppu().dmg_palette()
+    }
+
+    pub fn set_dmg_palette(&mut self, palette: DmgPalette) {
+        self.cpu.bus_mut().ppu_mut().set_dmg_palette(palette);
+    }
+
+    /// How CGB colors are adjusted before they reach the frame
+    pub fn color_correction(&self) -> ColorCorrection {
+        self.cpu.bus().ppu().color_correction()
+    }
+
+    pub fn set_color_correction(&mut self, color_correction: ColorCorrection) {
+        self.cpu.bus_mut().ppu_mut().set_color_correction(color_correction);
+    }
+
+    /// The bytes sent over the serial port, test ROMs report their results here. Only the last few KiB are kept
+    pub fn serial_output(&self) -> &[u8] {
+        self.cpu.bus().serial_transfer().output()
+    }
+
+    pub fn lockup(&self) -> Option<Lockup> {
+        self.cpu.lockup()
+    }
+
+    pub fn render_options(&self) -> RenderOptions {
+        self.cpu.bus().ppu().render_options()
+    }
+
+    pub fn set_render_options(&mut self, render_options: RenderOptions) {
+        self.cpu.bus_mut().ppu_mut().set_render_options(render_options);
+    }
+
+    /// Reads VRAM without going through the bus, so there are no side effects
+    pub fn vram_bank(&self, bank: usize) -> Option<&[u8]> {
+        self.cpu.bus().ppu().vram_bank(bank)
+    }
+
+    pub fn viewer_palette_colors(&self, palette: ViewerPalette) -> [(u8, u8, u8); 4] {
+        self.cpu.bus().ppu().viewer_palette_colors(palette)
+    }
+
+    /// Draws the whole background or window tile map as it currently is in VRAM
+    pub fn tile_map_view(&self, layer: TileMapLayer) -> TileMapView {
+        self.cpu.bus().ppu().tile_map_view(layer)
+    }
+
+    /// All 40 OAM entries, in OAM order
+    pub fn object_views(&self) -> Vec<ObjectView> {
+        self.cpu.bus().ppu().object_views()
+    }
+
+    pub fn palette_view(&self) -> PaletteView {
+        self.cpu.bus().ppu().palette_view()
+    }
+
+    /// The last finished frame in the configured pixel format, stays the same while the next one renders.
+    /// Empty after `swap_frame` until the next frame finishes
+    pub fn current_frame(&self) -> &[u8] {
+        self.cpu.bus().ppu().frame_buffer().front()
+    }
+
+    /// Moves the last finished frame into the buffer without copying, the buffer's old allocation is
+    /// reused to render a later frame. Returns false and leaves the buffer alone if no frame finished since the last swap
+    pub fn swap_frame(&mut self, buffer: &mut Vec<u8>) -> bool {
+        self.cpu.bus_mut().ppu_mut().frame_buffer_mut().swap_front(buffer)
+    }
+
+    pub fn pixel_format(&self) -> PixelFormat {
+        self.cpu.bus().ppu().frame_buffer().format()
+    }
+
+    /// Changing the format clears both frames
+    pub fn set_pixel_format(&mut self, pixel_format: PixelFormat) {
+        self.cpu.bus_mut().ppu_mut().frame_buffer_mut().set_format(pixel_format);
     }
 
     pub fn button_up(&mut self, button: JoypadButton) {
@@ -0,0 +1,312 @@
+use getset::{CopyGetters, Getters};
+use thiserror::Error;
+
+use crate::{
+    AudioChannel, GameBoyConfig, HardwareModel,
+    cartridge::ROM_BANK_SIZE,
+    cpu::CPU_CLOCK_SPEED,
+    gb::{GameBoy, GameBoyError},
+};
+
+const HEADER_SIZE: usize = 0x70;
+const TITLE_OFFSET: usize = 0x10;
+const AUTHOR_OFFSET: usize = 0x30;
+const COPYRIGHT_OFFSET: usize = 0x50;
+const TEXT_SIZE: usize = 0x20;
+/// The driver and the RST and interrupt vectors live below this
+const MIN_LOAD_ADDRESS: u16 = 0x0400;
+const DRIVER_ADDRESS: u16 = 0x0150;
+const VBLANK_VECTOR: usize = 0x40;
+const TIMER_VECTOR: usize = 0x50;
+/// MBC5 with RAM and no battery, GBS data switches banks through 0x2000 and expects RAM at 0xA000
+const CARTRIDGE_TYPE: u8 = 0x1A;
+const RAM_SIZE_8_KIB: u8 = 0x02;
+/// MBC5 addresses up to 512 banks
+const MAX_ROM_SIZE: usize = 512 * ROM_BANK_SIZE;
+const TAC_DOUBLE_SPEED: u8 = 0x80;
+const TAC_TIMER_ENABLED: u8 = 0x04;
+
+#[derive(Error, Debug)]
+pub enum GbsError {
+    #[error("Not a GBS file")]
+    InvalidSignature,
+    #[error("Unsupported GBS version {0}")]
+    UnsupportedVersion(u8),
+    #[error("GBS file is truncated")]
+    Truncated,
+    #[error("GBS load address {0:#06X} is not in the ROM above the driver")]
+    InvalidLoadAddress(u16),
+    #[error("GBS file has no songs")]
+    NoSongs,
+    #[error("GBS data does not fit in 8 MiB of ROM")]
+    TooLarge,
+    #[error("Failed to start the GBS driver: {0}")]
+    GameBoyError(#[from] GameBoyError),
+}
+
+/// A parsed GBS file, music code ripped from a game with the addresses of its init and play routines
+#[derive(Debug, Clone, Getters, CopyGetters)]
+pub struct Gbs {
+    #[getset(get_copy = "pub")]
+    songs: u8,
+    /// Zero based, the header counts from 1
+    #[getset(get_copy = "pub")]
+    first_song: u8,
+    #[getset(get_copy = "pub")]
+    load_address: u16,
+    #[getset(get_copy = "pub")]
+    init_address: u16,
+    #[getset(get_copy = "pub")]
+    play_address: u16,
+    #[getset(get_copy = "pub")]
+    stack_pointer: u16,
+    #[getset(get_copy = "pub")]
+    timer_modulo: u8,
+    #[getset(get_copy = "pub")]
+    timer_control: u8,
+    #[getset(get = "pub")]
+    title: String,
+    #[getset(get = "pub")]
+    author: String,
+    #[getset(get = "pub")]
+    copyright: String,
+    data: Vec<u8>,
+}
+
+impl Gbs {
+    pub fn parse(bytes: &[u8]) -> Result<Gbs, GbsError> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(match bytes.starts_with(b"GBS") {
+                true => GbsError::Truncated,
+                false => GbsError::InvalidSignature,
+            });
+        }
+        if &bytes[0x00..0x03] != b"GBS" {
+            return Err(GbsError::InvalidSignature);
+        }
+        if bytes[0x03] != 1 {
+            return Err(GbsError::UnsupportedVersion(bytes[0x03]));
+        }
+
+        let word = |offset: usize| u16::from_le_bytes([bytes[offset], bytes[offset + 1]]);
+        let songs = bytes[0x04];
+        let load_address = word(0x06);
+        let data = bytes[HEADER_SIZE..].to_vec();
+        if songs == 0 {
+            return Err(GbsError::NoSongs);
+        }
+        if !(MIN_LOAD_ADDRESS..0x8000).contains(&load_address) {
+            return Err(GbsError::InvalidLoadAddress(load_address));
+        }
+        if load_address as usize + data.len() > MAX_ROM_SIZE {
+            return Err(GbsError::TooLarge);
+        }
+
+        Ok(Gbs {
+            songs,
+            first_song: bytes[0x05].saturating_sub(1).min(songs - 1),
+            load_address,
+            init_address: word(0x08),
+            play_address: word(0x0A),
+            stack_pointer: word(0x0C),
+            timer_modulo: bytes[0x0E],
+            timer_control: bytes[0x0F],
+            title: text(&bytes[TITLE_OFFSET..TITLE_OFFSET + TEXT_SIZE]),
+            author: text(&bytes[AUTHOR_OFFSET..AUTHOR_OFFSET + TEXT_SIZE]),
+            copyright: text(&bytes[COPYRIGHT_OFFSET..COPYRIGHT_OFFSET + TEXT_SIZE]),
+            data,
+        })
+    }
+
+    /// The play routine runs on the timer interrupt instead of VBlank
+    pub fn uses_timer(&self) -> bool {
+        self.timer_control & TAC_TIMER_ENABLED != 0
+    }
+
+    /// Only a CGB can switch to double speed
+    pub fn double_speed(&self) -> bool {
+        self.timer_control & TAC_DOUBLE_SPEED != 0
+    }
+
+    /// A cartridge with the data at its load address and a driver that calls init with `song`, then play on every interrupt
+    pub fn rom(&self, song: u8) -> Vec<u8> {
+        let load_address = self.load_address as usize;
+        let size = (load_address + self.data.len()).next_power_of_two().max(2 * ROM_BANK_SIZE);
+        let mut rom = vec![0xFF; size];
+        rom[load_address..load_address + self.data.len()].copy_from_slice(&self.data);
+
+        // RST instructions jump into the data, the same offsets from the load address
+        for vector in (0x00..0x40).step_by(8) {
+            let [low, high] = (self.load_address + vector as u16).to_le_bytes();
+            rom[vector..vector + 3].copy_from_slice(&[0xC3, low, high]);
+        }
+        let [play_low, play_high] = self.play_address.to_le_bytes();
+        for vector in [VBLANK_VECTOR, TIMER_VECTOR] {
+            // CALL play, RETI
+            rom[vector..vector + 4].copy_from_slice(&[0xCD, play_low, play_high, 0xD9]);
+        }
+
+        let [driver_low, driver_high] = DRIVER_ADDRESS.to_le_bytes();
+        rom[0x0100..0x0104].copy_from_slice(&[0x00, 0xC3, driver_low, driver_high]);
+        rom[0x0134..0x0143].fill(0x00);
+        for (byte, character) in rom[0x0134..0x0143].iter_mut().zip(self.title.bytes().filter(u8::is_ascii)) {
+            *byte = character;
+        }
+        rom[0x0143] = if self.double_speed() { 0x80 } else { 0x00 };
+        rom[0x0144..0x0147].fill(0x00);
+        rom[0x0147] = CARTRIDGE_TYPE;
+        rom[0x0148] = (size / (2 * ROM_BANK_SIZE)).trailing_zeros() as u8;
+        rom[0x0149] = RAM_SIZE_8_KIB;
+        rom[0x014A..0x014D].fill(0x00);
+        rom[0x014D] = rom[0x0134..0x014D]
+            .iter()
+            .fold(0u8, |checksum, byte| checksum.wrapping_sub(*byte).wrapping_sub(1));
+
+        let driver = self.driver(song);
+        rom[DRIVER_ADDRESS as usize..DRIVER_ADDRESS as usize + driver.len()].copy_from_slice(&driver);
+        rom
+    }
+
+    fn driver(&self, song: u8) -> Vec<u8> {
+        let [sp_low, sp_high] = self.stack_pointer.to_le_bytes();
+        let [init_low, init_high] = self.init_address.to_le_bytes();
+        let interrupt = if self.uses_timer() { 0x04 } else { 0x01 };
+        let (modulo, control) = (self.timer_modulo, self.timer_control & 0x07);
+
+        // DI, LD SP, then enable cartridge RAM
+        let mut driver = vec![0xF3, 0x31, sp_low, sp_high, 0x3E, 0x0A, 0xEA, 0x00, 0x00];
+        if self.double_speed() {
+            // KEY1 armed and STOP
+            driver.extend([0x3E, 0x01, 0xE0, 0x4D, 0x10, 0x00]);
+        }
+        // TMA, TAC, IE and IF
+        driver.extend([
+            0x3E, modulo, 0xE0, 0x06, 0x3E, control, 0xE0, 0x07, 0x3E, interrupt, 0xE0, 0xFF, 0xAF, 0xE0, 0x0F,
+        ]);
+        // LD A, song, CALL init, EI, then HALT until the interrupts call play
+        driver.extend([0x3E, song, 0xCD, init_low, init_high, 0xFB, 0x76, 0x18, 0xFD]);
+        driver
+    }
+}
+
+/// NUL padded text, GBS files are not always ASCII so anything else is replaced
+fn text(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|byte| *byte == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).trim().to_string()
+}
+
+struct Fade {
+    samples: usize,
+    remaining: usize,
+}
+
+/// Plays the songs of a GBS file, every track starts from a freshly booted Game Boy
+#[derive(Getters, CopyGetters)]
+pub struct GbsPlayer {
+    #[getset(get = "pub")]
+    gbs: Gbs,
+    path: String,
+    config: GameBoyConfig,
+    #[getset(get = "pub")]
+    game_boy: GameBoy,
+    /// Zero based
+    #[getset(get_copy = "pub")]
+    track: u8,
+    left: Vec<f32>,
+    right: Vec<f32>,
+    fade: Option<Fade>,
+}
+
+impl GbsPlayer {
+    /// Starts the first song of the file
+    pub fn new(path: &str, bytes: &[u8], mut config: GameBoyConfig) -> Result<GbsPlayer, GbsError> {
+        let gbs = Gbs::parse(bytes)?;
+        if gbs.double_speed() {
+            config.model = Some(HardwareModel::Cgb);
+        }
+        let track = gbs.first_song();
+        let game_boy = GameBoy::with_config(path, gbs.rom(track), config)?;
+        Ok(GbsPlayer {
+            gbs,
+            path: path.to_string(),
+            config,
+            game_boy,
+            track,
+            left: Vec::new(),
+            right: Vec::new(),
+            fade: None,
+        })
+    }
+
+    pub fn game_boy_mut(&mut self) -> &mut GameBoy {
+        &mut self.game_boy
+    }
+
+    /// Restarts at `track`, wrapping past the last one. Output, mute and solo settings carry over
+    pub fn select_track(&mut self, track: u8) -> Result<(), GbsError> {
+        let track = track % self.gbs.songs();
+        self.config.sample_rate = self.game_boy.sample_rate();
+        self.config.audio_output = self.game_boy.audio_output();
+        let mut game_boy = GameBoy::with_config(&self.path, self.gbs.rom(track), self.config)?;
+        for channel in AudioChannel::ALL {
+            game_boy.set_channel_muted(channel, self.game_boy.channel_muted(channel));
+            game_boy.set_channel_soloed(channel, self.game_boy.channel_soloed(channel));
+        }
+        self.game_boy = game_boy;
+        self.track = track;
+        self.fade = None;
+        Ok(())
+    }
+
+    pub fn next_track(&mut self) -> Result<(), GbsError> {
+        self.select_track(self.track + 1)
+    }
+
+    pub fn previous_track(&mut self) -> Result<(), GbsError> {
+        self.select_track(self.track.checked_sub(1).unwrap_or(self.gbs.songs() - 1))
+    }
+
+    /// Lowers the volume to silence over `seconds`, a fade that is running keeps going
+    pub fn fade_out(&mut self, seconds: f64) {
+        if self.fade.is_none() {
+            let samples = ((seconds * self.game_boy.sample_rate().hz() as f64) as usize).max(1);
+            self.fade = Some(Fade { samples, remaining: samples });
+        }
+    }
+
+    pub fn fading(&self) -> bool {
+        self.fade.is_some()
+    }
+
+    pub fn fade_finished(&self) -> bool {
+        self.fade.as_ref().is_some_and(|fade| fade.remaining == 0)
+    }
+
+    /// Time the current track has played
+    pub fn elapsed_seconds(&self) -> f64 {
+        self.game_boy.t_cycles() as f64 / CPU_CLOCK_SPEED as f64
+    }
+
+    pub fn run_until_audio_buffer_full(&mut self) -> (&[f32], &[f32]) {
+        let (left, right) = self.game_boy.run_until_audio_buffer_full();
+        self.left.clear();
+        self.left.extend_from_slice(left);
+        self.right.clear();
+        self.right.extend_from_slice(right);
+
+        if let Some(fade) = &mut self.fade {
+            for (left, right) in self.left.iter_mut().zip(self.right.iter_mut()) {
+                let gain = fade.remaining as f32 / fade.samples as f32;
+                *left *= gain;
+                *right *= gain;
+                fade.remaining = fade.remaining.saturating_sub(1);
+            }
+        }
+        (&self.left, &self.right)
+    }
+
+    /// The faded samples of the last `run_until_audio_buffer_full`
+    pub fn audio_samples(&self) -> (&[f32], &[f32]) {
+        (&self.left, &self.right)
+    }
+}
@@ -2,6 +2,7 @@ use std::{cell::RefCell, rc::Rc};
 
 use crate::system_bus::SystemMemoryAccess;
 
+#[derive(Debug, PartialEq, Copy, Clone)]
 pub enum JoypadButton {
     Right,
     Left,
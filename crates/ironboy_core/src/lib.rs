@@ -3,6 +3,7 @@ mod cartridge;
 pub mod cpu;
 mod dma;
 pub mod gb;
+pub mod gbs;
 mod interrupts;
 mod joypad;
 pub mod memory;
@@ -11,11 +12,17 @@ mod serial_transfer;
 mod speed_switch;
 pub mod system_bus;
 mod timer;
+pub mod vgm;
+pub mod wav;
 
-pub use apu::{SAMPLES_PER_FRAME, SAMPLING_FREQUENCY};
-pub use gb::GameBoy;
+pub use apu::{AudioChannel, AudioOutput, ChannelView, EnvelopeView, SAMPLES_PER_FRAME, SampleRate, SweepView};
+pub use cartridge::{CartridgeError, LoadMode};
+pub use gb::{GameBoy, GameBoyConfig};
 pub use joypad::JoypadButton;
-pub use ppu::{FPS, VIEWPORT_HEIGHT, VIEWPORT_WIDTH};
+pub use ppu::{
+    ColorCorrection, DmgPalette, FPS, FRAME_T_CYCLES, ObjectView, PalettePreset, PaletteView, PixelFormat, RenderOptions, TILE_MAP_SIZE,
+    TILES_PER_BANK, TileMapLayer, TileMapView, VIEWPORT_HEIGHT, VIEWPORT_WIDTH, ViewerPalette, tile_color_indices,
+};
 
 pub const T_CYCLES_PER_STEP: u8 = 4;
 
@@ -26,6 +33,44 @@ pub enum GbMode {
     ColorAsMonochrome,
 }
 
+impl GbMode {
+    pub(crate) fn new(model: HardwareModel, supports_color: bool) -> Self {
+        match (model.is_color(), supports_color) {
+            (false, _) => GbMode::Monochrome,
+            (true, true) => GbMode::Color,
+            (true, false) => GbMode::ColorAsMonochrome,
+        }
+    }
+}
+
+/// The console being emulated, independent of what the cartridge asks for
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum HardwareModel {
+    Dmg,
+    Mgb,
+    Sgb,
+    Cgb,
+    Agb,
+}
+
+impl HardwareModel {
+    /// The model a cartridge would normally be played on
+    pub fn for_cartridge(supports_color: bool) -> Self {
+        match supports_color {
+            true => HardwareModel::Cgb,
+            false => HardwareModel::Dmg,
+        }
+    }
+
+    pub fn is_color(self) -> bool {
+        matches!(self, HardwareModel::Cgb | HardwareModel::Agb)
+    }
+
+    pub(crate) fn has_oam_bug(self) -> bool {
+        !self.is_color()
+    }
+}
+
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub(crate) enum GbSpeed {
     Normal,
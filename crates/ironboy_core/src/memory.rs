@@ -16,7 +16,7 @@ impl SystemMemoryAccess for Memory {
             0xD000..=0xDFFF | 0xF000..=0xFDFF => self.wram[(self.wram_bank * 0x1000) | address as usize & 0x0FFF],
             0xFF70 => self.wram_bank as u8,
             0xFF80..=0xFFFE => self.hram[address as usize & 0x007F],
-            _ => panic!("Memory does not handle read from address {:#4X}", address),
+            _ => 0xFF,
         }
     }
 
@@ -31,7 +31,7 @@ impl SystemMemoryAccess for Memory {
                 };
             }
             0xFF80..=0xFFFE => self.hram[address as usize & 0x007F] = value,
-            _ => panic!("Memory does not handle read to address {:#4X}", address),
+            _ => {}
         }
     }
 }
@@ -1,8 +1,8 @@
 use std::{cell::RefCell, rc::Rc};
 
 use background::Background;
-use getset::{CopyGetters, Getters, Setters};
-use palette::{CgbPalette, Palette, color_index};
+use getset::{CopyGetters, Getters, MutGetters, Setters};
+use palette::{CgbPalette, Palette, Rgb, color_index};
 use registers::{LcdControl, LcdStatus, PpuMode};
 use tile::{TILE_HEIGHT, TILE_WIDTH};
 use window::Window;
@@ -12,24 +12,43 @@ use crate::{
     cpu::CPU_CLOCK_SPEED,
     ppu::{
         background::BgMapAttributes,
+        frame::{FrameBuffer, Pixel},
         oam::{OAM_SIZE, Oam},
+        render_options::BOUNDING_BOX_COLOR,
     },
     system_bus::SystemMemoryAccess,
 };
 
 mod background;
+mod color_correction;
+mod compatibility;
+mod frame;
 mod oam;
 mod palette;
 pub mod registers;
+mod render_options;
 mod tile;
+mod viewer;
 mod window;
 
+pub use color_correction::ColorCorrection;
+pub use compatibility::CompatibilityPalette;
+pub use frame::PixelFormat;
+pub use oam::OamCorruption;
+pub use palette::{DmgPalette, PalettePreset};
+pub use render_options::RenderOptions;
+pub use viewer::{
+    ObjectView, PaletteView, TILE_MAP_SIZE, TILES_PER_BANK, TileMapLayer, TileMapView, VRAM_BANK_SIZE, ViewerPalette, tile_color_indices,
+};
+
 pub const VIEWPORT_WIDTH: usize = 160;
 pub const VIEWPORT_HEIGHT: usize = 144;
 const FULL_WIDTH: usize = 256;
 const TOTAL_LINE_CYCLES: u16 = 456;
 const NUMBER_OF_LINES: u8 = 154;
-pub const FPS: f32 = CPU_CLOCK_SPEED as f32 / (NUMBER_OF_LINES as f32 * TOTAL_LINE_CYCLES as f32);
+const LAST_LINE: u8 = NUMBER_OF_LINES - 1;
+pub const FRAME_T_CYCLES: u32 = NUMBER_OF_LINES as u32 * TOTAL_LINE_CYCLES as u32;
+pub const FPS: f32 = CPU_CLOCK_SPEED as f32 / FRAME_T_CYCLES as f32;
 
 const VRAM_SIZE: usize = 0x4000;
 
@@ -38,7 +57,7 @@ const DRAWING_PIXELS_CYCLES: u16 = 172;
 const HBLANK_CYCLES: u16 = 204;
 const VBLANK_CYCLES: u16 = TOTAL_LINE_CYCLES;
 
-#[derive(Getters, CopyGetters, Setters)]
+#[derive(Getters, CopyGetters, MutGetters, Setters)]
 pub struct Ppu {
     ly: u8,
     lyc: u8,
@@ -51,17 +70,33 @@ pub struct Ppu {
     obj1_palette: Palette,
     cgb_bg_palette: CgbPalette,
     cgb_obj_palette: CgbPalette,
+    #[getset(get_copy = "pub", set = "pub")]
+    dmg_palette: DmgPalette,
+    #[getset(get_copy = "pub")]
+    color_correction: ColorCorrection,
+    color_lut: Vec<Rgb>,
+    #[getset(get_copy = "pub", set = "pub")]
+    render_options: RenderOptions,
     vram: [u8; VRAM_SIZE],
     oam: Oam,
     oam_buffer: Vec<(usize, u8)>,
     object_height: u8,
+    /// One bit per OAM entry left out by the 10 objects per line limit, latched every VBlank
+    dropped_objects: u64,
+    last_dropped_objects: u64,
     line_priority: [(u8, bool); VIEWPORT_WIDTH],
-    #[getset(get = "pub")]
-    frame_buffer: Vec<(u8, u8, u8)>,
+    #[getset(get = "pub", get_mut = "pub")]
+    frame_buffer: FrameBuffer,
     vram_bank: usize,
     gb_mode: GbMode,
+    coordinate_object_priority: bool,
     interrupt_flag: Rc<RefCell<u8>>,
     mode_cycles: u16,
+    line_cycles: u16,
+    stat_line: bool,
+    lcd_off_cycles: u32,
+    first_line_after_enable: bool,
+    first_frame_after_enable: bool,
     #[getset(get_copy = "pub", set = "pub")]
     frame_ready: bool,
 }
@@ -72,23 +107,25 @@ impl SystemMemoryAccess for Ppu {
             0x8000..=0x9FFF => self.vram[(self.vram_bank * 0x2000) | (address as usize & 0x1FFF)],
             0xFE00..=0xFE9F => self.oam.read_8(address),
             0xFF40 => self.lcd_control.into(),
-            0xFF41 => self.lcd_status.into(),
+            0xFF41 => u8::from(self.lcd_status) | 0x80,
             0xFF42 | 0xFF43 => self.background.read_8(address),
-            0xFF44 => self.ly,
+            0xFF44 => self.ly(),
             0xFF45 => self.lyc,
             0xFF46 => 0,
             0xFF47 => self.bg_palette.read(),
             0xFF48 => self.obj0_palette.read(),
             0xFF49 => self.obj1_palette.read(),
             0xFF4A | 0xFF4B => self.window.read_8(address),
+            // KEY0 is locked once the boot ROM hands over, the mode it picked is gb_mode
             0xFF4C => 0xFF,
             0xFF4E => 0xFF,
-            0xFF4F..=0xFF6B if self.gb_mode != GbMode::Color => 0xFF,
+            0xFF4F..=0xFF6C if self.gb_mode != GbMode::Color => 0xFF,
             0xFF4F => self.vram_bank as u8 | 0xFE,
             0xFF68 => self.cgb_bg_palette.read_spec_and_index(),
             0xFF69 => self.cgb_bg_palette.read_palette(),
             0xFF6A => self.cgb_obj_palette.read_spec_and_index(),
             0xFF6B => self.cgb_obj_palette.read_palette(),
+            0xFF6C => self.coordinate_object_priority as u8 | 0xFE,
             _ => 0xFF,
         }
     }
@@ -98,23 +135,29 @@ impl SystemMemoryAccess for Ppu {
             0x8000..=0x9FFF => self.vram[(self.vram_bank * 0x2000) | (address as usize & 0x1FFF)] = value,
             0xFE00..=0xFE9F => self.oam.write_8(address, value),
             0xFF40 => self.set_lcd_control(value),
-            0xFF41 => self.lcd_status = value.into(),
+            0xFF41 => self.set_lcd_status(value),
             0xFF42 | 0xFF43 => self.background.write_8(address, value),
             0xFF44 => {}
             0xFF45 => self.set_lyc(value),
             0xFF47 => self.bg_palette.write(value),
             0xFF48 => self.obj0_palette.write(value),
             0xFF49 => self.obj1_palette.write(value),
+            // Pixels start leaving the FIFO about 12 dots into mode 3
+            0xFF4B if self.lcd_control.lcd_enabled() && self.lcd_status.mode() == PpuMode::DrawingPixels => {
+                let lx = self.mode_cycles.saturating_sub(12).min(VIEWPORT_WIDTH as u16) as u8;
+                self.window.write_wx_while_drawing(lx, value);
+            }
             0xFF4A | 0xFF4B => self.window.write_8(address, value),
             0xFF4C => {}
             0xFF4E => {}
-            0xFF4F..=0xFF6B if self.gb_mode != GbMode::Color => {}
+            0xFF4F..=0xFF6C if self.gb_mode != GbMode::Color => {}
             0xFF4F => self.vram_bank = (value & 0x01) as usize,
             0xFF68 => self.cgb_bg_palette.write_spec_and_index(value),
             0xFF69 => self.cgb_bg_palette.write_palette(value),
             0xFF6A => self.cgb_obj_palette.write_spec_and_index(value),
             0xFF6B => self.cgb_obj_palette.write_palette(value),
-            _ => panic!("PPU does not handle write {:#04X}", address),
+            0xFF6C => self.coordinate_object_priority = value & 0x01 != 0,
+            _ => {}
         }
     }
 }
@@ -124,7 +167,8 @@ impl Ppu {
         Ppu {
             ly: 0,
             lyc: 0,
-            lcd_control: LcdControl::new(),
+            // The boot ROM leaves the LCD running
+            lcd_control: LcdControl::from(0x80),
             lcd_status: LcdStatus::new(),
             background: Background::new(),
             window: Window::new(),
@@ -133,30 +177,50 @@ impl Ppu {
             obj1_palette: Palette::new(1),
             cgb_bg_palette: CgbPalette::new(),
             cgb_obj_palette: CgbPalette::new(),
+            dmg_palette: DmgPalette::default(),
+            color_correction: ColorCorrection::default(),
+            color_lut: ColorCorrection::default().lookup_table(),
+            render_options: RenderOptions::default(),
             vram: [0; VRAM_SIZE],
             oam: Oam::new(),
             oam_buffer: Vec::new(),
             object_height: TILE_HEIGHT,
+            dropped_objects: 0,
+            last_dropped_objects: 0,
             line_priority: [(0, false); VIEWPORT_WIDTH],
-            frame_buffer: vec![(0, 0, 0); VIEWPORT_WIDTH * VIEWPORT_HEIGHT],
+            frame_buffer: FrameBuffer::new(PixelFormat::default()),
             vram_bank: 0,
             gb_mode: mode,
+            coordinate_object_priority: mode != GbMode::Color,
             interrupt_flag,
             mode_cycles: 0,
+            line_cycles: 0,
+            stat_line: false,
+            lcd_off_cycles: 0,
+            first_line_after_enable: false,
+            first_frame_after_enable: false,
             frame_ready: false,
         }
     }
 
     pub fn cycle(&mut self) {
         if !self.lcd_control.lcd_enabled() {
+            // Frames keep their usual pace while the LCD is off so the screen still gets refreshed
+            self.lcd_off_cycles += T_CYCLES_PER_STEP as u32;
+            if self.lcd_off_cycles >= FRAME_T_CYCLES {
+                self.lcd_off_cycles -= FRAME_T_CYCLES;
+                self.clear_screen();
+            }
             return;
         }
 
         self.mode_cycles += T_CYCLES_PER_STEP as u16;
+        self.line_cycles += T_CYCLES_PER_STEP as u16;
         match self.lcd_status.mode() {
             PpuMode::OamScan => {
                 if self.mode_cycles >= OAM_SCAN_CYCLES {
                     self.mode_cycles = 0;
+                    self.window.start_drawing();
                     self.lcd_status.set_mode(PpuMode::DrawingPixels);
                 }
             }
@@ -164,27 +228,38 @@ impl Ppu {
                 if self.mode_cycles >= DRAWING_PIXELS_CYCLES {
                     self.mode_cycles = 0;
                     self.render_scanline();
-                    if self.set_mode(PpuMode::HBlank) {
-                        *self.interrupt_flag.borrow_mut() |= 0x02;
-                    }
+                    self.lcd_status.set_mode(PpuMode::HBlank);
+                }
+            }
+            // The first line after the LCD is enabled skips OAM scan and reports HBlank instead
+            PpuMode::HBlank if self.first_line_after_enable => {
+                if self.mode_cycles >= OAM_SCAN_CYCLES {
+                    self.mode_cycles = 0;
+                    self.first_line_after_enable = false;
+                    self.window.start_drawing();
+                    self.lcd_status.set_mode(PpuMode::DrawingPixels);
                 }
             }
             PpuMode::HBlank => {
                 if self.mode_cycles >= HBLANK_CYCLES {
                     self.mode_cycles = 0;
-                    self.window.increment_line_counter(self.lcd_control.window_enabled(), self.ly);
                     if self.ly == VIEWPORT_HEIGHT as u8 - 1 {
-                        self.frame_ready = true;
-                        *self.interrupt_flag.borrow_mut() |= 0x01;
-                        if self.set_mode(PpuMode::VBlank) {
-                            *self.interrupt_flag.borrow_mut() |= 0x02;
+                        self.last_dropped_objects = std::mem::take(&mut self.dropped_objects);
+                        if self.first_frame_after_enable {
+                            self.first_frame_after_enable = false;
+                            self.clear_screen();
+                        } else {
+                            self.frame_buffer.present();
+                            self.frame_ready = true;
                         }
+                        *self.interrupt_flag.borrow_mut() |= 0x01;
+                        self.lcd_status.set_mode(PpuMode::VBlank);
+                        self.set_ly(self.ly + 1);
                     } else {
-                        if self.set_mode(PpuMode::OamScan) {
-                            *self.interrupt_flag.borrow_mut() |= 0x02;
-                        }
+                        self.lcd_status.set_mode(PpuMode::OamScan);
+                        self.set_ly(self.ly + 1);
+                        self.window.start_line(self.ly);
                     }
-                    self.set_ly(self.ly + 1);
                 }
             }
             PpuMode::VBlank => {
@@ -192,141 +267,404 @@ impl Ppu {
                     self.mode_cycles = 0;
                     self.set_ly(self.ly + 1);
                     if self.ly == 0 {
-                        self.window.reset_line_counter();
-                        if self.set_mode(PpuMode::OamScan) {
-                            *self.interrupt_flag.borrow_mut() |= 0x02;
-                        }
+                        self.window.reset_frame();
+                        self.window.start_line(self.ly);
+                        self.lcd_status.set_mode(PpuMode::OamScan);
                     }
                 }
             }
         }
+
+        self.update_stat_line();
     }
 
     pub fn mode(&self) -> PpuMode {
         self.lcd_status.mode()
     }
 
-    fn set_mode(&mut self, mode: PpuMode) -> bool {
-        if self.lcd_status.mode() == mode {
-            return false;
+    pub fn set_color_correction(&mut self, color_correction: ColorCorrection) {
+        if color_correction != self.color_correction {
+            self.color_correction = color_correction;
+            self.color_lut = color_correction.lookup_table();
         }
+    }
 
-        self.lcd_status.set_mode(mode);
-        match self.lcd_status.mode() {
-            PpuMode::HBlank => self.lcd_status.mode0_interrupt(),
-            PpuMode::VBlank => self.lcd_status.mode1_interrupt(),
-            PpuMode::OamScan => self.lcd_status.mode1_interrupt(),
-            PpuMode::DrawingPixels => false,
+    pub fn load_compatibility_palette(&mut self, palette: CompatibilityPalette) {
+        self.cgb_bg_palette.set_colors(0, palette.bg);
+        self.cgb_obj_palette.set_colors(0, palette.obj0);
+        self.cgb_obj_palette.set_colors(1, palette.obj1);
+    }
+
+    /// Reads a VRAM bank regardless of VBK and mode 3, bank 1 is only used in color mode and there is no bank 2
+    pub fn vram_bank(&self, bank: usize) -> Option<&[u8]> {
+        self.vram.get(bank * VRAM_BANK_SIZE..(bank + 1) * VRAM_BANK_SIZE)
+    }
+
+    /// The colors a palette currently maps color indices 0-3 to, as they would be drawn. CGB palettes past the
+    /// 8th wrap around
+    pub fn viewer_palette_colors(&self, palette: ViewerPalette) -> [Rgb; 4] {
+        let grayscale = PalettePreset::Grayscale.palette().bg;
+        std::array::from_fn(|color| {
+            let color = color as u8;
+            match (palette, self.gb_mode) {
+                (ViewerPalette::Grayscale, _) => grayscale[color as usize],
+                (ViewerPalette::Background(palette), GbMode::Color) => self.cgb_bg_palette.pixel_color(palette & 0x07, color, &self.color_lut),
+                (ViewerPalette::Object(palette), GbMode::Color) => self.cgb_obj_palette.pixel_color(palette & 0x07, color, &self.color_lut),
+                (ViewerPalette::Background(_), GbMode::ColorAsMonochrome) => {
+                    self.cgb_bg_palette.pixel_color(0, self.bg_palette.shade(color), &self.color_lut)
+                }
+                (ViewerPalette::Object(palette), GbMode::ColorAsMonochrome) => {
+                    let object_palette = if palette & 0x01 != 0 { self.obj1_palette } else { self.obj0_palette };
+                    self.cgb_obj_palette
+                        .pixel_color(palette & 0x01, object_palette.shade(color), &self.color_lut)
+                }
+                (ViewerPalette::Background(_), GbMode::Monochrome) => self.bg_palette.pixel_color(color, &self.dmg_palette.bg),
+                (ViewerPalette::Object(palette), GbMode::Monochrome) => match palette & 0x01 != 0 {
+                    false => self.obj0_palette.pixel_color(color, &self.dmg_palette.obj0),
+                    true => self.obj1_palette.pixel_color(color, &self.dmg_palette.obj1),
+                },
+            }
+        })
+    }
+
+    /// Draws a whole 256x256 tile map with the current LCDC tile data area and palettes
+    pub fn tile_map_view(&self, layer: TileMapLayer) -> TileMapView {
+        let (tile_map, (x, y), enabled) = match layer {
+            TileMapLayer::Background => (self.lcd_control.bg_tile_map(), (self.background.scx(), self.background.scy()), true),
+            TileMapLayer::Window => (
+                self.lcd_control.window_tile_map(),
+                (self.window.wx(), self.window.wy()),
+                self.lcd_control.window_enabled(),
+            ),
+        };
+        // On DMG the window goes away with the background
+        let enabled = enabled && (self.gb_mode == GbMode::Color || self.lcd_control.bg_window_enabled());
+
+        let mut colors = Vec::with_capacity(FULL_WIDTH * FULL_WIDTH);
+        for map_y in 0..=u8::MAX {
+            for map_x in 0..=u8::MAX {
+                let tile_index_address = tile_map.tile_index_address(map_x, map_y);
+                let (x_offset, y_offset) = self.background.pixel_offsets(map_x, map_y);
+                let bg_map_attributes = self.bg_map_attributes(tile_index_address);
+                let color_index = self.bg_color_index(tile_index_address, bg_map_attributes, x_offset, y_offset);
+                colors.push(self.bg_color(bg_map_attributes, color_index));
+            }
+        }
+
+        TileMapView {
+            colors,
+            map_address: tile_map.tile_index_address(0, 0),
+            x,
+            y,
+            enabled,
+        }
+    }
+
+    pub fn object_views(&self) -> Vec<ObjectView> {
+        let object_height = if self.lcd_control.object_size() { 2 * TILE_HEIGHT } else { TILE_HEIGHT };
+        (0..OAM_SIZE)
+            .map(|oam_index| {
+                let oam_entry = self.oam.oam_entry(oam_index);
+                let attributes = oam_entry.attributes();
+                let mut tile_index = oam_entry.tile_index();
+                if object_height == 2 * TILE_HEIGHT {
+                    tile_index &= 0xFE;
+                }
+                let palette = match self.gb_mode {
+                    GbMode::Color => ViewerPalette::Object(attributes.cgb_palette()),
+                    _ => ViewerPalette::Object(attributes.dmg_palette() as u8),
+                };
+                let colors = self.viewer_palette_colors(palette);
+                let bank = attributes.bank() && self.gb_mode == GbMode::Color;
+
+                let mut pixels = Vec::with_capacity(TILE_WIDTH as usize * object_height as usize);
+                for row in 0..object_height {
+                    let line_offset = if attributes.y_flip() { object_height - 1 - row } else { row };
+                    let (byte1, byte2) = self.get_tile_bytes(0x8000 + tile_index as u16 * 16 + line_offset as u16 * 2, bank);
+                    for pixel_index in 0..TILE_WIDTH {
+                        let oam_pixel_index = if attributes.x_flip() { pixel_index } else { 7 - pixel_index };
+                        let color_index = color_index(byte1, byte2, oam_pixel_index);
+                        pixels.push((color_index != 0).then_some(colors[color_index as usize]));
+                    }
+                }
+
+                ObjectView {
+                    x: oam_entry.x_position(),
+                    y: oam_entry.y_position(),
+                    tile: oam_entry.tile_index(),
+                    x_flip: attributes.x_flip(),
+                    y_flip: attributes.y_flip(),
+                    priority: attributes.priority(),
+                    dmg_palette: attributes.dmg_palette() as u8,
+                    bank: attributes.bank() as u8,
+                    cgb_palette: attributes.cgb_palette(),
+                    dropped: self.last_dropped_objects & (1 << oam_index) != 0,
+                    pixels,
+                }
+            })
+            .collect()
+    }
+
+    pub fn palette_view(&self) -> PaletteView {
+        let cgb_values = |cgb_palette: &CgbPalette| std::array::from_fn(|palette| std::array::from_fn(|color| cgb_palette.rgb555(palette, color)));
+        let cgb_colors = |cgb_palette: &CgbPalette| {
+            std::array::from_fn(|palette| std::array::from_fn(|color| cgb_palette.pixel_color(palette as u8, color as u8, &self.color_lut)))
+        };
+        let dmg_colors = |palette: Palette, colors: &[Rgb; 4]| std::array::from_fn(|color| palette.pixel_color(color as u8, colors));
+
+        PaletteView {
+            dmg_registers: [self.bg_palette.read(), self.obj0_palette.read(), self.obj1_palette.read()],
+            dmg_colors: [
+                dmg_colors(self.bg_palette, &self.dmg_palette.bg),
+                dmg_colors(self.obj0_palette, &self.dmg_palette.obj0),
+                dmg_colors(self.obj1_palette, &self.dmg_palette.obj1),
+            ],
+            cgb_background: cgb_values(&self.cgb_bg_palette),
+            cgb_object: cgb_values(&self.cgb_obj_palette),
+            cgb_background_colors: cgb_colors(&self.cgb_bg_palette),
+            cgb_object_colors: cgb_colors(&self.cgb_obj_palette),
+        }
+    }
+
+    pub fn corrupt_oam(&mut self, corruption: OamCorruption) {
+        if self.lcd_control.lcd_enabled() && self.lcd_status.mode() == PpuMode::OamScan {
+            let row = (self.mode_cycles / T_CYCLES_PER_STEP as u16) as usize;
+            self.oam.corrupt(row, corruption);
         }
     }
 
     fn clear_screen(&mut self) {
         self.line_priority.fill((0, false));
-        self.frame_buffer.fill((255, 255, 255));
+        let blank = match self.gb_mode {
+            GbMode::Monochrome => self.dmg_palette.bg[0],
+            _ => (255, 255, 255),
+        };
+        self.frame_buffer.fill(blank);
+        self.frame_buffer.present();
         self.frame_ready = true;
     }
 
+    /// LY as the CPU sees it, line 153 only lasts a single M-cycle before reading as 0
     pub fn ly(&self) -> u8 {
-        self.ly
+        match (self.ly, self.line_cycles) {
+            (LAST_LINE, 4..) => 0,
+            (ly, _) => ly,
+        }
     }
 
     fn set_ly(&mut self, value: u8) {
         self.ly = value % NUMBER_OF_LINES;
-        self.compare_line();
+        self.line_cycles = 0;
     }
 
     pub fn set_lyc(&mut self, value: u8) {
         self.lyc = value;
-        self.compare_line();
+        self.update_stat_line();
     }
 
-    fn compare_line(&mut self) {
-        self.lcd_status.set_lyc_equals_ly(false);
-        if self.lyc != self.ly {
-            return;
+    /// The value LYC is compared against, nothing matches while LY is changing at the start of a line
+    fn ly_for_comparison(&self) -> Option<u8> {
+        match (self.ly, self.line_cycles) {
+            (0, _) => Some(0),
+            (LAST_LINE, 4..8) => Some(LAST_LINE),
+            (LAST_LINE, 12..) => Some(0),
+            (LAST_LINE, _) | (_, 0..4) => None,
+            (ly, _) => Some(ly),
         }
+    }
 
-        self.lcd_status.set_lyc_equals_ly(true);
-        if self.lcd_status.lyc_interrupt() {
+    /// The STAT interrupt is requested when any enabled source raises the shared line, so while one source holds it
+    /// high the others are blocked
+    fn update_stat_line(&mut self) {
+        let lyc_equals_ly = self.ly_for_comparison() == Some(self.lyc);
+        self.lcd_status.set_lyc_equals_ly(lyc_equals_ly);
+
+        let mode = self.lcd_status.mode();
+        // The OAM scan source also fires at the start of line 144, even though mode 2 is never entered
+        let oam_scan = mode == PpuMode::OamScan || (self.ly == VIEWPORT_HEIGHT as u8 && self.line_cycles == 0);
+        let stat_line = self.lcd_control.lcd_enabled()
+            && ((self.lcd_status.lyc_interrupt() && lyc_equals_ly)
+                || (self.lcd_status.mode0_interrupt() && mode == PpuMode::HBlank)
+                || (self.lcd_status.mode1_interrupt() && mode == PpuMode::VBlank)
+                || (self.lcd_status.mode2_interrupt() && oam_scan));
+
+        if stat_line && !self.stat_line {
             *self.interrupt_flag.borrow_mut() |= 0x02;
         }
+        self.stat_line = stat_line;
+    }
+
+    fn set_lcd_status(&mut self, value: u8) {
+        // On DMG every source is enabled for a moment while STAT is written, which can request a spurious interrupt
+        if self.gb_mode == GbMode::Monochrome {
+            self.lcd_status = (u8::from(self.lcd_status) | 0x78).into();
+            self.update_stat_line();
+        }
+
+        // Mode and LYC=LY flags are read only
+        let read_only_bits = u8::from(self.lcd_status) & 0x07;
+        self.lcd_status = ((value & 0x78) | read_only_bits).into();
+        self.update_stat_line();
     }
 
     fn set_lcd_control(&mut self, value: u8) {
+        let was_enabled = self.lcd_control.lcd_enabled();
         self.lcd_control = value.into();
 
-        if !self.lcd_control.lcd_enabled() {
-            self.clear_screen();
-            self.window.reset_line_counter();
+        if was_enabled && !self.lcd_control.lcd_enabled() {
+            self.lcd_off_cycles = self.frame_cycles();
+            self.window.reset_frame();
             self.set_ly(0);
             self.lcd_status.set_mode(PpuMode::HBlank);
             self.mode_cycles = 0;
+        } else if !was_enabled && self.lcd_control.lcd_enabled() {
+            // The LCD needs a frame to sync up, whatever is drawn during it never reaches the screen
+            self.first_line_after_enable = true;
+            self.first_frame_after_enable = true;
+            self.mode_cycles = 0;
+            self.window.start_line(self.ly);
         }
+        self.update_stat_line();
+    }
+
+    /// How far into the current frame the PPU is
+    fn frame_cycles(&self) -> u32 {
+        self.ly as u32 * TOTAL_LINE_CYCLES as u32 + self.line_cycles as u32
     }
 
     fn render_scanline(&mut self) {
         if self.lcd_control.bg_window_enabled() || self.gb_mode == GbMode::Color {
             self.render_bg_window_line();
+        } else {
+            self.render_blank_line();
         }
 
         if self.lcd_control.object_enabled() {
-            self.render_object_line();
+            self.read_objects_from_oam();
+            if self.render_options.show_objects {
+                self.render_object_line();
+            }
+            if self.render_options.object_bounding_boxes {
+                self.render_object_bounding_boxes();
+            }
         }
     }
 
     fn render_bg_window_line(&mut self) {
+        let window_line = self.window.line_layout(
+            self.lcd_control.window_enabled(),
+            self.background.scx(),
+            self.gb_mode == GbMode::Monochrome,
+        );
+        let mut window_x = None;
+
         for lx in 0..VIEWPORT_WIDTH as u8 {
-            let (tile_index_address, x_offset, y_offset) = self.bg_window_tile_data(lx);
+            let mut glitch = false;
+            if let Some(window_line) = &window_line {
+                if lx == window_line.start {
+                    window_x = Some(window_line.x);
+                }
+                glitch = window_line.glitch_pixels.contains(&lx);
+            }
 
-            let tile_index = self.read_vram_bank_0(tile_index_address);
-            let bg_map_attributes = if self.gb_mode == GbMode::Color {
-                BgMapAttributes::from(self.read_vram_bank_1(tile_index_address))
-            } else {
-                BgMapAttributes::from(0)
+            let shown_window_x = window_x.filter(|_| self.render_options.show_window);
+            let hidden = shown_window_x.is_none() && !self.render_options.show_background;
+            let (tile_index_address, x_offset, y_offset) = match shown_window_x {
+                Some(x) => self.window_tile_data(x),
+                None => self.bg_tile_data(lx),
             };
+            // A glitch pixel holds the window back by one pixel
+            window_x = window_x.map(|x| if glitch { x } else { x.wrapping_add(1) });
 
-            let tile_address = self.lcd_control.tile_data_area().tile_address(tile_index);
-            let (byte1, byte2) = match bg_map_attributes.y_flip() {
-                false => self.get_tile_bytes(tile_address + y_offset as u16, bg_map_attributes.bank()),
-                true => self.get_tile_bytes(tile_address + (14 - y_offset) as u16, bg_map_attributes.bank()),
+            let bg_map_attributes = match hidden {
+                false => self.bg_map_attributes(tile_index_address),
+                true => BgMapAttributes::from(0),
             };
-
-            let x_offset = match bg_map_attributes.x_flip() {
-                false => x_offset,
-                true => 7 - x_offset,
+            let color_index = match glitch || hidden {
+                false => self.bg_color_index(tile_index_address, bg_map_attributes, x_offset, y_offset),
+                true => 0,
             };
-
-            let color_index = color_index(byte1, byte2, x_offset);
             self.line_priority[lx as usize] = (color_index, bg_map_attributes.priority());
 
-            let color = if self.gb_mode == GbMode::Color {
-                self.cgb_bg_palette.pixel_color(bg_map_attributes.color_palette(), color_index)
-            } else {
-                self.bg_palette.pixel_color(color_index)
+            let pixel = Pixel {
+                color: self.bg_color(bg_map_attributes, color_index),
+                color_index,
+                palette: bg_map_attributes.color_palette(),
+                object: false,
+                attributes: bg_map_attributes.into(),
             };
             let offset = lx as usize + self.ly as usize * VIEWPORT_WIDTH;
-            self.frame_buffer[offset] = color
+            self.frame_buffer.put(offset, pixel);
+        }
+
+        // The window only moves on to its next line when it drew on this one
+        if window_line.is_some() {
+            self.window.increment_line_counter();
         }
     }
 
-    fn bg_window_tile_data(&self, lx: u8) -> (u16, u8, u8) {
-        if self.window.inside_window(self.lcd_control.window_enabled(), lx, self.ly) {
-            let (x, y) = self.window.tile_map_coordinates(lx);
-            let tile_index_address = self.lcd_control.window_tile_map().tile_index_address(x, y);
+    fn bg_map_attributes(&self, tile_index_address: u16) -> BgMapAttributes {
+        match self.gb_mode {
+            GbMode::Color => BgMapAttributes::from(self.read_vram_bank_1(tile_index_address)),
+            _ => BgMapAttributes::from(0),
+        }
+    }
 
-            let (x_offset, y_offset) = self.window.pixel_offsets(lx, self.ly);
-            (tile_index_address, x_offset, y_offset)
-        } else {
-            let (x, y) = self.background.tile_map_coordinates(lx, self.ly);
-            let tile_index_address = self.lcd_control.bg_tile_map().tile_index_address(x, y);
+    fn bg_color_index(&self, tile_index_address: u16, bg_map_attributes: BgMapAttributes, x_offset: u8, y_offset: u8) -> u8 {
+        let tile_index = self.read_vram_bank_0(tile_index_address);
+        let tile_address = self.lcd_control.tile_data_area().tile_address(tile_index);
+        let (byte1, byte2) = match bg_map_attributes.y_flip() {
+            false => self.get_tile_bytes(tile_address + y_offset as u16, bg_map_attributes.bank()),
+            true => self.get_tile_bytes(tile_address + (14 - y_offset) as u16, bg_map_attributes.bank()),
+        };
+
+        let x_offset = match bg_map_attributes.x_flip() {
+            false => x_offset,
+            true => 7 - x_offset,
+        };
+        color_index(byte1, byte2, x_offset)
+    }
 
-            let (x_offset, y_offset) = self.background.pixel_offsets(x, y);
-            (tile_index_address, x_offset, y_offset)
+    fn bg_color(&self, bg_map_attributes: BgMapAttributes, color_index: u8) -> Rgb {
+        match self.gb_mode {
+            GbMode::Color => self
+                .cgb_bg_palette
+                .pixel_color(bg_map_attributes.color_palette(), color_index, &self.color_lut),
+            GbMode::ColorAsMonochrome => self.cgb_bg_palette.pixel_color(0, self.bg_palette.shade(color_index), &self.color_lut),
+            GbMode::Monochrome => self.bg_palette.pixel_color(color_index, &self.dmg_palette.bg),
         }
     }
 
+    fn render_blank_line(&mut self) {
+        let color = match self.gb_mode {
+            GbMode::Monochrome => self.dmg_palette.bg[0],
+            _ => self.cgb_bg_palette.pixel_color(0, self.bg_palette.shade(0), &self.color_lut),
+        };
+        let pixel = Pixel { color, ..Default::default() };
+        let start = self.ly as usize * VIEWPORT_WIDTH;
+        for offset in start..start + VIEWPORT_WIDTH {
+            self.frame_buffer.put(offset, pixel);
+        }
+        self.line_priority.fill((0, false));
+    }
+
+    fn window_tile_data(&self, x: u8) -> (u16, u8, u8) {
+        let (x, y) = self.window.tile_map_coordinates(x);
+        let tile_index_address = self.lcd_control.window_tile_map().tile_index_address(x, y);
+
+        let (x_offset, y_offset) = self.window.pixel_offsets(x);
+        (tile_index_address, x_offset, y_offset)
+    }
+
+    fn bg_tile_data(&self, lx: u8) -> (u16, u8, u8) {
+        let (x, y) = self.background.tile_map_coordinates(lx, self.ly);
+        let tile_index_address = self.lcd_control.bg_tile_map().tile_index_address(x, y);
+
+        let (x_offset, y_offset) = self.background.pixel_offsets(x, y);
+        (tile_index_address, x_offset, y_offset)
+    }
+
     fn render_object_line(&mut self) {
-        self.read_objects_from_oam();
         for (oam_index, x_offset) in self.oam_buffer.iter() {
             let oam_entry = self.oam.oam_entry(*oam_index);
             let y_offset = oam_entry.y_position().wrapping_sub(16);
@@ -344,7 +682,7 @@ impl Ppu {
             };
             let tile_address = tile_base_address + line_offset as u16 * 2;
 
-            let bank = oam_entry.attributes().bank();
+            let bank = oam_entry.attributes().bank() && self.gb_mode == GbMode::Color;
             let (byte1, byte2) = self.get_tile_bytes(tile_address, bank);
             let color_palette_index = oam_entry.attributes().cgb_palette();
 
@@ -369,20 +707,40 @@ impl Ppu {
                         continue;
                     }
 
-                    let color = self.cgb_obj_palette.pixel_color(color_palette_index, color_index);
-                    self.frame_buffer[offset] = color;
+                    let color = self.cgb_obj_palette.pixel_color(color_palette_index, color_index, &self.color_lut);
+                    let pixel = Pixel {
+                        color,
+                        color_index,
+                        palette: color_palette_index,
+                        object: true,
+                        attributes: oam_entry.attributes().into(),
+                    };
+                    self.frame_buffer.put(offset, pixel);
                 } else {
                     if oam_entry.attributes().priority() && self.line_priority[lx as usize].0 != 0 {
                         continue;
                     }
 
-                    let object_pallete = if oam_entry.attributes().dmg_palette() {
-                        self.obj1_palette
-                    } else {
-                        self.obj0_palette
+                    let dmg_palette = oam_entry.attributes().dmg_palette();
+                    let object_pallete = if dmg_palette { self.obj1_palette } else { self.obj0_palette };
+                    let color = match self.gb_mode {
+                        GbMode::ColorAsMonochrome => {
+                            self.cgb_obj_palette
+                                .pixel_color(dmg_palette as u8, object_pallete.shade(color_index), &self.color_lut)
+                        }
+                        _ => {
+                            let colors = if dmg_palette { &self.dmg_palette.obj1 } else { &self.dmg_palette.obj0 };
+                            object_pallete.pixel_color(color_index, colors)
+                        }
                     };
-                    let color = object_pallete.pixel_color(color_index);
-                    self.frame_buffer[offset] = color;
+                    let pixel = Pixel {
+                        color,
+                        color_index,
+                        palette: dmg_palette as u8,
+                        object: true,
+                        attributes: oam_entry.attributes().into(),
+                    };
+                    self.frame_buffer.put(offset, pixel);
                 }
             }
         }
@@ -401,15 +759,39 @@ impl Ppu {
             }
         }
 
-        if self.gb_mode == GbMode::Color {
-            self.oam_buffer.sort_by(|a, b| a.0.cmp(&b.0));
-        } else {
+        // Only the first 10 objects in OAM order are selected, OPRI then decides which one is drawn on top
+        if !self.render_options.unlimited_objects {
+            for (oam_index, _) in self.oam_buffer.drain(10.min(self.oam_buffer.len())..) {
+                self.dropped_objects |= 1 << oam_index;
+            }
+        }
+        if self.coordinate_object_priority {
             self.oam_buffer.sort_by(|a, b| a.1.cmp(&b.1).then(a.0.cmp(&b.0)));
         }
-        self.oam_buffer.truncate(10);
         self.oam_buffer.reverse();
     }
 
+    fn render_object_bounding_boxes(&mut self) {
+        let pixel = Pixel {
+            color: BOUNDING_BOX_COLOR,
+            object: true,
+            ..Default::default()
+        };
+
+        for (oam_index, object_x) in self.oam_buffer.iter() {
+            let object_y = self.oam.oam_entry(*oam_index).y_position().wrapping_sub(16);
+            let row = self.ly.wrapping_sub(object_y);
+            let edge_row = row == 0 || row == self.object_height - 1;
+
+            for pixel_index in 0..TILE_WIDTH {
+                let lx = object_x.wrapping_add(pixel_index) as usize;
+                if lx < VIEWPORT_WIDTH && (edge_row || pixel_index == 0 || pixel_index == TILE_WIDTH - 1) {
+                    self.frame_buffer.put(lx + self.ly as usize * VIEWPORT_WIDTH, pixel);
+                }
+            }
+        }
+    }
+
     fn get_tile_bytes(&self, address: u16, bank: bool) -> (u8, u8) {
         match bank {
             false => (self.read_vram_bank_0(address), self.read_vram_bank_0(address + 1)),
@@ -418,10 +800,10 @@ impl Ppu {
     }
 
     fn read_vram_bank_0(&self, address: u16) -> u8 {
-        self.vram[address as usize - 0x8000]
+        self.vram[address as usize & 0x1FFF]
     }
 
     fn read_vram_bank_1(&self, address: u16) -> u8 {
-        self.vram[0x2000 + address as usize - 0x8000]
+        self.vram[0x2000 | (address as usize & 0x1FFF)]
     }
 }
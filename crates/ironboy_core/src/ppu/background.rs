@@ -26,6 +26,14 @@ impl Background {
         Background { scx: 0, scy: 0 }
     }
 
+    pub fn scx(&self) -> u8 {
+        self.scx
+    }
+
+    pub fn scy(&self) -> u8 {
+        self.scy
+    }
+
     pub fn tile_map_coordinates(&self, lx: u8, ly: u8) -> (u8, u8) {
         let x = lx.wrapping_add(self.scx);
         let y = ly.wrapping_add(self.scy);
@@ -44,7 +52,7 @@ impl SystemMemoryAccess for Background {
         match address {
             0xFF42 => self.scy,
             0xFF43 => self.scx,
-            _ => panic!("Background does not handle read {:#04X}", address),
+            _ => 0xFF,
         }
     }
 
@@ -52,7 +60,7 @@ impl SystemMemoryAccess for Background {
         match address {
             0xFF42 => self.scy = value,
             0xFF43 => self.scx = value,
-            _ => panic!("Background does not handle write {:#04X}", address),
+            _ => {}
         }
     }
 }
@@ -0,0 +1,94 @@
+use super::palette::Rgb;
+
+pub const RGB555_COLORS: usize = 0x8000;
+
+// Converting RGB 555 to RGB 888 [round(255 * i / 31) for i in range(32)]
+const GBC_COLOR_LUT: &[u8; 32] = &[
+    0, 8, 16, 25, 33, 41, 49, 58, 66, 74, 82, 90, 99, 107, 115, 123, 132, 140, 148, 156, 165, 173, 181, 189, 197, 206, 214, 222, 230, 239, 247, 255,
+];
+
+/// How CGB colors are adjusted to look like they would on a real screen
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub enum ColorCorrection {
+    /// Scale each channel linearly, the most saturated
+    #[default]
+    None,
+    /// Approximates the reflective CGB LCD
+    Cgb,
+    /// Approximates the darker, gamma heavy AGB LCD
+    Agb,
+    /// A gentle desaturation that keeps white white, closer to hardware without being as dull
+    ModernBalanced,
+}
+
+impl ColorCorrection {
+    pub const ALL: [ColorCorrection; 4] = [
+        ColorCorrection::None,
+        ColorCorrection::Cgb,
+        ColorCorrection::Agb,
+        ColorCorrection::ModernBalanced,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorCorrection::None => "none",
+            ColorCorrection::Cgb => "cgb",
+            ColorCorrection::Agb => "agb",
+            ColorCorrection::ModernBalanced => "modern-balanced",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let index = ColorCorrection::ALL.iter().position(|mode| *mode == self).unwrap_or(0);
+        ColorCorrection::ALL[(index + 1) % ColorCorrection::ALL.len()]
+    }
+
+    /// Every RGB555 color, indexed by its 15 bit value, so correcting a pixel is a single lookup
+    pub fn lookup_table(self) -> Vec<Rgb> {
+        (0..RGB555_COLORS as u16).map(|color| self.correct(color)).collect()
+    }
+
+    fn correct(self, color: u16) -> Rgb {
+        let r = (color & 0x1F) as u32;
+        let g = ((color >> 5) & 0x1F) as u32;
+        let b = ((color >> 10) & 0x1F) as u32;
+
+        match self {
+            ColorCorrection::None => (GBC_COLOR_LUT[r as usize], GBC_COLOR_LUT[g as usize], GBC_COLOR_LUT[b as usize]),
+            ColorCorrection::Cgb => {
+                let red = (r * 26 + g * 4 + b * 2).min(960) >> 2;
+                let green = (g * 24 + b * 8).min(960) >> 2;
+                let blue = (r * 6 + g * 4 + b * 22).min(960) >> 2;
+                (red as u8, green as u8, blue as u8)
+            }
+            ColorCorrection::Agb => {
+                let [r, g, b] = [r, g, b].map(|channel| (channel as f64 / 31.0).powf(4.0));
+                mix(
+                    [
+                        (255.0 * r + 50.0 * g) / 255.0,
+                        (10.0 * r + 230.0 * g + 30.0 * b) / 255.0,
+                        (50.0 * r + 10.0 * g + 220.0 * b) / 255.0,
+                    ],
+                    255.0 / 280.0,
+                )
+            }
+            ColorCorrection::ModernBalanced => {
+                let [r, g, b] = [r, g, b].map(|channel| (channel as f64 / 31.0).powf(2.2));
+                mix(
+                    [
+                        0.84 * r + 0.12 * g + 0.04 * b,
+                        0.06 * r + 0.86 * g + 0.08 * b,
+                        0.04 * r + 0.12 * g + 0.84 * b,
+                    ],
+                    1.0,
+                )
+            }
+        }
+    }
+}
+
+/// Converts linear light back to an 8 bit display value
+fn mix(linear: [f64; 3], brightness: f64) -> Rgb {
+    let [red, green, blue] = linear.map(|channel| (channel.powf(1.0 / 2.2) * brightness * 255.0).round().clamp(0.0, 255.0) as u8);
+    (red, green, blue)
+}
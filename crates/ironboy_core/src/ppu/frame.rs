@@ -0,0 +1,123 @@
+use super::{VIEWPORT_HEIGHT, VIEWPORT_WIDTH, palette::Rgb};
+
+/// How pixels are packed into frames. Packed formats are stored in native endianness,
+/// so they match the pixel formats of the same name in SDL and most graphics APIs
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub enum PixelFormat {
+    /// u32, red in the highest byte and alpha in the lowest
+    #[default]
+    Rgba8888,
+    /// u32, alpha in the highest byte and blue in the lowest
+    Argb8888,
+    /// u16, 5 bits red, 6 bits green, 5 bits blue
+    Rgb565,
+    /// u16, the pixel before any palette is applied. Bits 0-1 are the color index, bits 2-4 the palette
+    /// (OBP0/OBP1 on DMG), bit 7 is set for objects and the high byte holds the BG map or OAM attributes
+    Indexed,
+}
+
+impl PixelFormat {
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::Rgba8888 | PixelFormat::Argb8888 => 4,
+            PixelFormat::Rgb565 | PixelFormat::Indexed => 2,
+        }
+    }
+
+    pub fn frame_size(self) -> usize {
+        VIEWPORT_WIDTH * VIEWPORT_HEIGHT * self.bytes_per_pixel()
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Pixel {
+    pub color: Rgb,
+    pub color_index: u8,
+    pub palette: u8,
+    pub object: bool,
+    pub attributes: u8,
+}
+
+/// Frames are drawn into the back buffer and only swapped to the front once complete.
+/// A front buffer handed out by `swap_front` is replaced by the caller's, which becomes the next back buffer
+pub struct FrameBuffer {
+    format: PixelFormat,
+    back: Vec<u8>,
+    front: Vec<u8>,
+    spare: Vec<u8>,
+}
+
+impl FrameBuffer {
+    pub fn new(format: PixelFormat) -> Self {
+        FrameBuffer {
+            format,
+            back: vec![0; format.frame_size()],
+            front: vec![0; format.frame_size()],
+            spare: Vec::new(),
+        }
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    pub fn set_format(&mut self, format: PixelFormat) {
+        if format != self.format {
+            *self = FrameBuffer::new(format);
+        }
+    }
+
+    pub fn front(&self) -> &[u8] {
+        &self.front
+    }
+
+    /// Hands the finished frame to the caller in place of their buffer without copying it.
+    /// The front stays empty until the next frame is presented, returns false if it already was
+    pub fn swap_front(&mut self, buffer: &mut Vec<u8>) -> bool {
+        if self.front.is_empty() {
+            return false;
+        }
+        std::mem::swap(&mut self.front, buffer);
+        self.spare = std::mem::take(&mut self.front);
+        true
+    }
+
+    pub fn present(&mut self) {
+        std::mem::swap(&mut self.front, &mut self.back);
+        if self.back.is_empty() {
+            std::mem::swap(&mut self.back, &mut self.spare);
+            self.back.resize(self.format.frame_size(), 0);
+        }
+    }
+
+    pub fn put(&mut self, offset: usize, pixel: Pixel) {
+        let (r, g, b) = pixel.color;
+        let start = offset * self.format.bytes_per_pixel();
+        match self.format {
+            PixelFormat::Rgba8888 => {
+                let value = u32::from_be_bytes([r, g, b, 0xFF]);
+                self.back[start..start + 4].copy_from_slice(&value.to_ne_bytes());
+            }
+            PixelFormat::Argb8888 => {
+                let value = u32::from_be_bytes([0xFF, r, g, b]);
+                self.back[start..start + 4].copy_from_slice(&value.to_ne_bytes());
+            }
+            PixelFormat::Rgb565 => {
+                let value = (r as u16 >> 3) << 11 | (g as u16 >> 2) << 5 | b as u16 >> 3;
+                self.back[start..start + 2].copy_from_slice(&value.to_ne_bytes());
+            }
+            PixelFormat::Indexed => {
+                let index = pixel.color_index & 0x03 | (pixel.palette & 0x07) << 2 | (pixel.object as u8) << 7;
+                let value = u16::from_le_bytes([index, pixel.attributes]);
+                self.back[start..start + 2].copy_from_slice(&value.to_ne_bytes());
+            }
+        }
+    }
+
+    pub fn fill(&mut self, color: Rgb) {
+        let pixel = Pixel { color, ..Default::default() };
+        for offset in 0..VIEWPORT_WIDTH * VIEWPORT_HEIGHT {
+            self.put(offset, pixel);
+        }
+    }
+}
@@ -3,6 +3,15 @@ use bitfields::bitfield;
 use crate::system_bus::SystemMemoryAccess;
 
 pub const OAM_SIZE: usize = 40;
+const ROW_WORDS: usize = 4;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum OamCorruption {
+    Read,
+    Write,
+    /// A read in the same M-cycle as an increment or decrement of its address register
+    ReadIncrement,
+}
 
 #[bitfield(u8, order = msb)]
 #[derive(Copy, Clone)]
@@ -32,9 +41,9 @@ pub struct Oam {
 
 impl SystemMemoryAccess for Oam {
     fn read_8(&self, address: u16) -> u8 {
-        let address = address - 0xFE00;
         match address {
-            0x00..=0x9F => {
+            0xFE00..=0xFE9F => {
+                let address = address - 0xFE00;
                 let index = (address / 4) as usize;
                 let oam_address = (address % 4) as usize;
                 match oam_address {
@@ -45,26 +54,25 @@ impl SystemMemoryAccess for Oam {
                     _ => unreachable!(),
                 }
             }
-            _ => panic!("OAM does not handle read {:#04X}", address),
+            _ => 0xFF,
         }
     }
 
     fn write_8(&mut self, address: u16, value: u8) {
+        if !(0xFE00..=0xFE9F).contains(&address) {
+            return;
+        }
+
         let address = address - 0xFE00;
-        match address {
-            0x00..=0x9F => {
-                let index = (address / 4) as usize;
-                let oam_address = (address % 4) as usize;
+        let index = (address / 4) as usize;
+        let oam_address = (address % 4) as usize;
 
-                match oam_address {
-                    0 => self.data[index].set_y_position(value),
-                    1 => self.data[index].set_x_position(value),
-                    2 => self.data[index].set_tile_index(value),
-                    3 => self.data[index].set_attributes(value.into()),
-                    _ => unreachable!(),
-                }
-            }
-            _ => panic!("OAM does not handle write {:#04X}", address),
+        match oam_address {
+            0 => self.data[index].set_y_position(value),
+            1 => self.data[index].set_x_position(value),
+            2 => self.data[index].set_tile_index(value),
+            3 => self.data[index].set_attributes(value.into()),
+            _ => unreachable!(),
         }
     }
 }
@@ -79,4 +87,63 @@ impl Oam {
     pub fn oam_entry(&self, index: usize) -> OamEntry {
         self.data[index]
     }
+
+    /// DMG OAM bug, corrupts the row being scanned using the row before it
+    pub fn corrupt(&mut self, row: usize, corruption: OamCorruption) {
+        if row == 0 || row >= OAM_SIZE / 2 {
+            return;
+        }
+
+        if corruption == OamCorruption::ReadIncrement {
+            self.corrupt_preceding_rows(row);
+        }
+
+        let current = row * ROW_WORDS;
+        let previous = current - ROW_WORDS;
+        let a = self.word(current);
+        let b = self.word(previous);
+        let c = self.word(previous + 2);
+        let first_word = match corruption {
+            OamCorruption::Read | OamCorruption::ReadIncrement => b | (a & c),
+            OamCorruption::Write => ((a ^ c) & (b ^ c)) ^ c,
+        };
+
+        self.set_word(current, first_word);
+        for offset in 1..ROW_WORDS {
+            self.set_word(current + offset, self.word(previous + offset));
+        }
+    }
+
+    /// Extra step of the read during increment pattern, skipped for the first four rows and the last
+    fn corrupt_preceding_rows(&mut self, row: usize) {
+        if row < 4 || row == OAM_SIZE / 2 - 1 {
+            return;
+        }
+
+        let current = row * ROW_WORDS;
+        let preceding = current - ROW_WORDS;
+        let two_before = preceding - ROW_WORDS;
+        let a = self.word(two_before);
+        let b = self.word(preceding);
+        let c = self.word(current);
+        let d = self.word(preceding + 2);
+        self.set_word(preceding, (b & (a | c | d)) | (a & c & d));
+        for offset in 0..ROW_WORDS {
+            let word = self.word(preceding + offset);
+            self.set_word(current + offset, word);
+            self.set_word(two_before + offset, word);
+        }
+    }
+
+    fn word(&self, index: usize) -> u16 {
+        let address = 0xFE00 + index as u16 * 2;
+        u16::from_le_bytes([self.read_8(address), self.read_8(address + 1)])
+    }
+
+    fn set_word(&mut self, index: usize, value: u16) {
+        let address = 0xFE00 + index as u16 * 2;
+        let [lo, hi] = value.to_le_bytes();
+        self.write_8(address, lo);
+        self.write_8(address + 1, hi);
+    }
 }
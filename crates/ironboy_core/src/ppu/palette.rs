@@ -1,7 +1,78 @@
-// Converting RGB 555 to RGB 888 [round(255 * i / 31) for i in range(32)]
-const GBC_COLOR_LUT: &[u8; 32] = &[
-    0, 8, 16, 25, 33, 41, 49, 58, 66, 74, 82, 90, 99, 107, 115, 123, 132, 140, 148, 156, 165, 173, 181, 189, 197, 206, 214, 222, 230, 239, 247, 255,
-];
+pub type Rgb = (u8, u8, u8);
+
+/// The colors each DMG shade is shown as, from lightest to darkest
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct DmgPalette {
+    pub bg: [Rgb; 4],
+    pub obj0: [Rgb; 4],
+    pub obj1: [Rgb; 4],
+}
+
+impl DmgPalette {
+    pub const fn uniform(colors: [Rgb; 4]) -> Self {
+        DmgPalette {
+            bg: colors,
+            obj0: colors,
+            obj1: colors,
+        }
+    }
+}
+
+impl Default for DmgPalette {
+    fn default() -> Self {
+        PalettePreset::Grayscale.palette()
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub enum PalettePreset {
+    #[default]
+    Grayscale,
+    DmgGreen,
+    Pocket,
+    Light,
+    HighContrast,
+}
+
+impl PalettePreset {
+    pub const ALL: [PalettePreset; 5] = [
+        PalettePreset::Grayscale,
+        PalettePreset::DmgGreen,
+        PalettePreset::Pocket,
+        PalettePreset::Light,
+        PalettePreset::HighContrast,
+    ];
+
+    pub fn palette(self) -> DmgPalette {
+        match self {
+            PalettePreset::Grayscale => DmgPalette::uniform([(255, 255, 255), (170, 170, 170), (85, 85, 85), (0, 0, 0)]),
+            PalettePreset::DmgGreen => DmgPalette::uniform([(155, 188, 15), (139, 172, 15), (48, 98, 48), (15, 56, 15)]),
+            PalettePreset::Pocket => DmgPalette::uniform([(196, 207, 161), (139, 149, 109), (77, 83, 60), (31, 31, 31)]),
+            PalettePreset::Light => DmgPalette::uniform([(0, 181, 129), (0, 154, 113), (0, 105, 74), (0, 79, 59)]),
+            // Lightness and hue both change between shades so they stay apart with most color vision deficiencies
+            PalettePreset::HighContrast => DmgPalette::uniform([(255, 255, 255), (255, 215, 0), (0, 80, 180), (0, 0, 0)]),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PalettePreset::Grayscale => "grayscale",
+            PalettePreset::DmgGreen => "dmg-green",
+            PalettePreset::Pocket => "pocket",
+            PalettePreset::Light => "light",
+            PalettePreset::HighContrast => "high-contrast",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        PalettePreset::ALL.into_iter().find(|preset| preset.name() == name)
+    }
+
+    pub fn next(self) -> Self {
+        let index = PalettePreset::ALL.iter().position(|preset| *preset == self).unwrap_or(0);
+        PalettePreset::ALL[(index + 1) % PalettePreset::ALL.len()]
+    }
+}
 
 #[derive(Copy, Clone)]
 pub struct Palette {
@@ -17,13 +88,12 @@ impl Palette {
         pallete
     }
 
-    pub fn pixel_color(&self, color: u8) -> (u8, u8, u8) {
-        match self.data[color as usize] {
-            0 => (255, 255, 255), // white
-            1 => (170, 170, 170), // light gray
-            2 => (85, 85, 85),    // dark gray
-            _ => (0, 0, 0),       // black
-        }
+    pub fn shade(&self, color: u8) -> u8 {
+        self.data[color as usize]
+    }
+
+    pub fn pixel_color(&self, color: u8, colors: &[Rgb; 4]) -> Rgb {
+        colors[self.data[color as usize] as usize]
     }
 
     pub fn write(&mut self, value: u8) {
@@ -63,16 +133,21 @@ impl CgbPalette {
         }
     }
 
-    pub fn pixel_color(&self, palette: u8, color: u8) -> (u8, u8, u8) {
-        let r = self.data[palette as usize][color as usize][0] as u32;
-        let g = self.data[palette as usize][color as usize][1] as u32;
-        let b = self.data[palette as usize][color as usize][2] as u32;
+    /// Looks the RGB555 color up in a color corrected table
+    pub fn pixel_color(&self, palette: u8, color: u8, color_lut: &[Rgb]) -> Rgb {
+        let [r, g, b] = self.data[palette as usize][color as usize];
+        color_lut[r as usize | (g as usize) << 5 | (b as usize) << 10]
+    }
 
-        let red = GBC_COLOR_LUT[r as usize];
-        let green = GBC_COLOR_LUT[g as usize];
-        let blue = GBC_COLOR_LUT[b as usize];
+    pub fn rgb555(&self, palette: usize, color: usize) -> u16 {
+        let [r, g, b] = self.data[palette][color];
+        r as u16 | (g as u16) << 5 | (b as u16) << 10
+    }
 
-        (red, green, blue)
+    pub fn set_colors(&mut self, palette: usize, colors: [u16; 4]) {
+        for (color, rgb) in colors.into_iter().enumerate() {
+            self.data[palette][color] = [(rgb & 0x1F) as u8, ((rgb >> 5) & 0x1F) as u8, ((rgb >> 10) & 0x1F) as u8];
+        }
     }
 
     pub fn write_spec_and_index(&mut self, value: u8) {
@@ -0,0 +1,27 @@
+use super::palette::Rgb;
+
+pub const BOUNDING_BOX_COLOR: Rgb = (255, 0, 255);
+
+/// Rendering tweaks for debugging and screenshots, the default matches hardware
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct RenderOptions {
+    pub show_background: bool,
+    pub show_window: bool,
+    pub show_objects: bool,
+    /// Outlines every object selected for a line
+    pub object_bounding_boxes: bool,
+    /// Draws every object on a line instead of the first 10, which hides flicker from sprite multiplexing
+    pub unlimited_objects: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            show_background: true,
+            show_window: true,
+            show_objects: true,
+            object_bounding_boxes: false,
+            unlimited_objects: false,
+        }
+    }
+}
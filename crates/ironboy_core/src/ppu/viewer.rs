@@ -0,0 +1,100 @@
+use super::{
+    FULL_WIDTH,
+    palette::{Rgb, color_index},
+    tile::{TILE_HEIGHT, TILE_WIDTH},
+};
+
+pub const VRAM_BANK_SIZE: usize = 0x2000;
+pub const TILES_PER_BANK: usize = 384;
+pub const TILE_MAP_SIZE: usize = FULL_WIDTH;
+const TILE_SIZE: usize = 16;
+
+/// Which palette debug views color tiles with
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub enum ViewerPalette {
+    /// Plain shades, ignoring every palette register
+    #[default]
+    Grayscale,
+    /// BGP on DMG, one of the 8 CGB background palettes otherwise
+    Background(u8),
+    /// OBP0 or OBP1 on DMG, one of the 8 CGB object palettes otherwise
+    Object(u8),
+}
+
+impl ViewerPalette {
+    pub fn name(self) -> String {
+        match self {
+            ViewerPalette::Grayscale => "grayscale".to_string(),
+            ViewerPalette::Background(palette) => format!("BG{}", palette),
+            ViewerPalette::Object(palette) => format!("OBJ{}", palette),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub enum TileMapLayer {
+    #[default]
+    Background,
+    Window,
+}
+
+/// A tile map drawn as it would show on screen, without scrolling
+#[derive(Debug, Clone)]
+pub struct TileMapView {
+    /// `TILE_MAP_SIZE` rows of `TILE_MAP_SIZE` colors
+    pub colors: Vec<Rgb>,
+    /// 0x9800 or 0x9C00, picked by LCDC
+    pub map_address: u16,
+    /// SCX for the background, WX for the window
+    pub x: u8,
+    /// SCY for the background, WY for the window
+    pub y: u8,
+    /// Whether LCDC currently lets the layer show
+    pub enabled: bool,
+}
+
+/// An OAM entry decoded for debug views
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectView {
+    pub x: u8,
+    pub y: u8,
+    pub tile: u8,
+    pub x_flip: bool,
+    pub y_flip: bool,
+    /// BG and window colors 1-3 are drawn over the object
+    pub priority: bool,
+    pub dmg_palette: u8,
+    pub bank: u8,
+    pub cgb_palette: u8,
+    /// Some line of the last frame already had 10 objects, so it was not drawn there
+    pub dropped: bool,
+    /// The object as it is drawn, 8 pixels wide and 8 or 16 rows high, `None` where it is transparent
+    pub pixels: Vec<Option<Rgb>>,
+}
+
+/// Every palette register decoded for debug views
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaletteView {
+    /// BGP, OBP0 and OBP1
+    pub dmg_registers: [u8; 3],
+    /// The colors BGP, OBP0 and OBP1 map color indices to with the current DMG palette
+    pub dmg_colors: [[Rgb; 4]; 3],
+    /// The 8 CGB background palettes as RGB555
+    pub cgb_background: [[u16; 4]; 8],
+    /// The 8 CGB object palettes as RGB555
+    pub cgb_object: [[u16; 4]; 8],
+    pub cgb_background_colors: [[Rgb; 4]; 8],
+    pub cgb_object_colors: [[Rgb; 4]; 8],
+}
+
+/// Decodes a tile from a VRAM bank into color indices, row by row
+pub fn tile_color_indices(vram_bank: &[u8], tile: usize) -> [u8; TILE_WIDTH as usize * TILE_HEIGHT as usize] {
+    let mut color_indices = [0; TILE_WIDTH as usize * TILE_HEIGHT as usize];
+    let start = tile * TILE_SIZE;
+    for (row, bytes) in vram_bank[start..start + TILE_SIZE].chunks_exact(2).enumerate() {
+        for column in 0..TILE_WIDTH {
+            color_indices[row * TILE_WIDTH as usize + column as usize] = color_index(bytes[0], bytes[1], 7 - column);
+        }
+    }
+    color_indices
+}
@@ -1,7 +1,7 @@
 use crate::system_bus::SystemMemoryAccess;
 
 use super::{
-    VIEWPORT_HEIGHT, VIEWPORT_WIDTH,
+    VIEWPORT_WIDTH,
     tile::{TILE_HEIGHT, TILE_WIDTH},
 };
 
@@ -9,6 +9,9 @@ pub struct Window {
     wx: u8,
     wy: u8,
     line_counter: u8,
+    wy_triggered: bool,
+    full_line_pending: bool,
+    line_wx: Vec<(u8, u8)>,
 }
 
 impl Window {
@@ -17,55 +20,136 @@ impl Window {
             wx: 0,
             wy: 0,
             line_counter: 0,
+            wy_triggered: false,
+            full_line_pending: false,
+            line_wx: Vec::new(),
         }
     }
 
-    pub fn inside_window(&self, window_enabled: bool, lx: u8, ly: u8) -> bool {
-        (window_enabled && lx >= self.wx.wrapping_sub(7)) && (window_enabled && ly >= self.wy)
+    pub fn wx(&self) -> u8 {
+        self.wx
     }
 
-    pub fn reset_line_counter(&mut self) {
+    pub fn wy(&self) -> u8 {
+        self.wy
+    }
+
+    pub fn reset_frame(&mut self) {
         self.line_counter = 0;
+        self.wy_triggered = false;
+        self.full_line_pending = false;
     }
 
-    pub fn increment_line_counter(&mut self, window_enabled: bool, ly: u8) {
-        if window_enabled && self.wx - 7 < VIEWPORT_WIDTH as u8 && self.wy < VIEWPORT_HEIGHT as u8 && ly >= self.wy {
-            self.line_counter = self.line_counter.saturating_add(1);
+    /// WY is only compared at the start of a line, once it has matched the window can show for the rest of the frame
+    pub fn start_line(&mut self, ly: u8) {
+        if ly == self.wy {
+            self.wy_triggered = true;
         }
     }
 
-    pub fn tile_map_coordinates(&self, lx: u8) -> (u8, u8) {
-        let x = lx.wrapping_sub(self.wx.wrapping_sub(7));
-        let y = self.line_counter;
-        (x, y)
+    pub fn start_drawing(&mut self) {
+        self.line_wx.clear();
+        self.line_wx.push((0, self.wx));
+    }
+
+    /// Remembers where in the line WX changed so the rendered line matches what mode 3 would have drawn
+    pub fn write_wx_while_drawing(&mut self, lx: u8, value: u8) {
+        self.wx = value;
+        self.line_wx.push((lx, value));
+    }
+
+    /// Decides where the window shows on the current line, returning the first pixel it covers, the window X coordinate
+    /// for it and any pixels that glitched on DMG
+    pub fn line_layout(&mut self, window_enabled: bool, scx: u8, dmg: bool) -> Option<WindowLine> {
+        let full_line = std::mem::take(&mut self.full_line_pending);
+        if !window_enabled || !self.wy_triggered {
+            return None;
+        }
+
+        // A window at WX=166 starts past the end of this line and spans all of the next one
+        self.full_line_pending = self.wx == 166;
+        if full_line {
+            return Some(WindowLine::new(0, 0));
+        }
+
+        let mut line: Option<WindowLine> = None;
+        for lx in 0..VIEWPORT_WIDTH as u8 {
+            let (changed_at, wx) = self.wx_at(lx);
+            match line.as_mut() {
+                None => {
+                    line = match wx {
+                        // WX=0 also loses the pixels SCX scrolls away
+                        0 if lx == 0 => Some(WindowLine::new(0, 7 + (scx % TILE_WIDTH))),
+                        1..7 if lx == 0 => Some(WindowLine::new(0, 7 - wx)),
+                        7..166 if lx + 7 == wx => Some(WindowLine::new(lx, 0)),
+                        _ => None,
+                    }
+                }
+                // Rewriting WX to the current position while the window draws makes the DMG output a color 0 pixel
+                Some(window_line) if dmg && changed_at > 0 && changed_at <= lx && lx + 7 == wx => {
+                    window_line.glitch_pixels.push(lx);
+                }
+                Some(_) => {}
+            }
+        }
+
+        line
     }
 
-    pub fn pixel_offsets(&self, lx: u8, ly: u8) -> (u8, u8) {
-        let x_offset = self.wx.wrapping_sub(lx) % TILE_WIDTH;
-        let y_offset = 2 * ((ly - self.wy) % TILE_HEIGHT);
+    fn wx_at(&self, lx: u8) -> (u8, u8) {
+        self.line_wx
+            .iter()
+            .rev()
+            .find(|(changed_at, _)| *changed_at <= lx)
+            .copied()
+            .unwrap_or((0, self.wx))
+    }
+
+    pub fn increment_line_counter(&mut self) {
+        self.line_counter = self.line_counter.wrapping_add(1);
+    }
+
+    pub fn tile_map_coordinates(&self, x: u8) -> (u8, u8) {
+        (x, self.line_counter)
+    }
+
+    pub fn pixel_offsets(&self, x: u8) -> (u8, u8) {
+        let x_offset = 7 - (x % TILE_WIDTH);
+        let y_offset = 2 * (self.line_counter % TILE_HEIGHT);
         (x_offset, y_offset)
     }
 }
 
+pub struct WindowLine {
+    pub start: u8,
+    pub x: u8,
+    pub glitch_pixels: Vec<u8>,
+}
+
+impl WindowLine {
+    fn new(start: u8, x: u8) -> Self {
+        WindowLine {
+            start,
+            x,
+            glitch_pixels: Vec::new(),
+        }
+    }
+}
+
 impl SystemMemoryAccess for Window {
     fn read_8(&self, address: u16) -> u8 {
         match address {
             0xFF4A => self.wy,
             0xFF4B => self.wx,
-            _ => panic!("Window does not handle read {:#04X}", address),
+            _ => 0xFF,
         }
     }
 
     fn write_8(&mut self, address: u16, value: u8) {
         match address {
             0xFF4A => self.wy = value,
-            0xFF4B => {
-                if value < 7 {
-                    return;
-                }
-                self.wx = value;
-            }
-            _ => panic!("Window does not handle write {:#04X}", address),
+            0xFF4B => self.wx = value,
+            _ => {}
         }
     }
 }
@@ -2,9 +2,12 @@ use std::{cell::RefCell, rc::Rc};
 
 use crate::system_bus::SystemMemoryAccess;
 
+/// Bytes kept of what was sent, a ROM can send forever
+const OUTPUT_LIMIT: usize = 0x1000;
+
 pub struct SerialTransfer {
     data: u8,
-    message: String,
+    output: Vec<u8>,
     control: u8,
     interrupt_flag: Rc<RefCell<u8>>,
 }
@@ -13,11 +16,16 @@ impl SerialTransfer {
     pub fn new(interrupt_flag: Rc<RefCell<u8>>) -> Self {
         SerialTransfer {
             data: 0,
-            message: String::new(),
+            output: Vec::new(),
             control: 0,
             interrupt_flag,
         }
     }
+
+    /// The most recent bytes sent, at most `OUTPUT_LIMIT` of them
+    pub fn output(&self) -> &[u8] {
+        &self.output
+    }
 }
 
 impl SystemMemoryAccess for SerialTransfer {
@@ -25,24 +33,24 @@ impl SystemMemoryAccess for SerialTransfer {
         match address {
             0xFF01 => self.data,
             0xFF02 => self.control,
-            _ => panic!("Serial Transfer does not handle read from address {:#4X}", address),
+            _ => 0xFF,
         }
     }
 
     fn write_8(&mut self, address: u16, value: u8) {
         match address {
-            0xFF01 => {
-                self.data = value;
-                self.message.push(value as char);
-            }
+            0xFF01 => self.data = value,
             0xFF02 => {
                 self.control = value;
                 if self.control == 0x81 {
                     *self.interrupt_flag.borrow_mut() |= 0b1000;
-                    println!("{}", self.message);
+                    if self.output.len() == OUTPUT_LIMIT {
+                        self.output.drain(..OUTPUT_LIMIT / 2);
+                    }
+                    self.output.push(self.data);
                 }
             }
-            _ => panic!("Serial Transfer does not handle write to address {:#4X}", address),
+            _ => {}
         }
     }
 }
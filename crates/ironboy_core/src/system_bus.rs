@@ -10,11 +10,11 @@ use crate::dma::{Dma, DmaContext};
 use crate::interrupts::Interrupts;
 use crate::joypad::JoyPad;
 use crate::memory::Memory;
-use crate::ppu::Ppu;
+use crate::ppu::{OamCorruption, Ppu};
 use crate::serial_transfer::SerialTransfer;
 use crate::speed_switch::SpeedSwitch;
 use crate::timer::Timer;
-use crate::{GbMode, GbSpeed, t_cycles};
+use crate::{GbMode, GbSpeed, HardwareModel, t_cycles};
 
 pub const IF_ADDRESS: u16 = 0xFF0F;
 pub const IE_ADDRESS: u16 = 0xFFFF;
@@ -27,15 +27,17 @@ pub trait SystemMemoryAccess {
 
 #[derive(Getters, MutGetters)]
 pub struct SystemBus {
+    model: HardwareModel,
     gb_mode: GbMode,
     speed_switch: SpeedSwitch,
-    undocumented_cgb_registers: [u8; 3],
+    undocumented_cgb_registers: [u8; 4],
     interrupts: Interrupts,
     dma: Dma,
     memory: Memory,
     cartridge: Cartridge,
     #[getset(get = "pub", get_mut = "pub")]
     joy_pad: JoyPad,
+    #[getset(get = "pub(crate)")]
     serial_transfer: SerialTransfer,
     timer: Timer,
     #[getset(get = "pub", get_mut = "pub")]
@@ -66,16 +68,17 @@ impl SystemMemoryAccess for SystemBus {
             0xFF0F => *self.interrupts.interrupt_flag().borrow() & 0b0001_1111,
             0xFF10..=0xFF3F => self.apu.read_8(address),
             0xFF40..=0xFF4B => self.ppu.read_8(address),
-            0xFF4D | 0xFF4F | 0xFF51..=0xFF56 | 0xFF70 | 0xFF72..=0xFF77 if self.gb_mode != GbMode::Color => 0xFF,
+            0xFF72..=0xFF77 if !self.model.is_color() => 0xFF,
+            0xFF4D | 0xFF4F | 0xFF51..=0xFF56 | 0xFF70 | 0xFF74 if self.gb_mode != GbMode::Color => 0xFF,
             0xFF4D => self.speed_switch.read_8(address),
             0xFF4F => self.ppu.read_8(address),
-            0xFF50 => todo!("Set to non-zero to disable boot ROM"),
+            0xFF50 => 0xFF,
             0xFF51..=0xFF55 => self.dma.read_8(address),
             0xFF56 => 0xFF, //todo!("Infrared Comms"),
             0xFF68..=0xFF6C => self.ppu.read_8(address),
             0xFF70 => self.memory.read_8(address),
-            0xFF72..=0xFF73 => self.undocumented_cgb_registers[address as usize - 0xFF72],
-            0xFF75 => self.undocumented_cgb_registers[2] | 0x8F,
+            0xFF72..=0xFF74 => self.undocumented_cgb_registers[address as usize - 0xFF72],
+            0xFF75 => self.undocumented_cgb_registers[3] | 0x8F,
             0xFF76..=0xFF77 => self.apu.read_8(address),
             0xFF80..=0xFFFE => self.memory.read_8(address),
             0xFFFF => *self.interrupts.interrupt_enable(),
@@ -103,7 +106,8 @@ impl SystemMemoryAccess for SystemBus {
             0xFF40..=0xFF45 => self.ppu.write_8(address, value),
             0xFF46 => self.dma.write_8(address, value),
             0xFF47..=0xFF4B => self.ppu.write_8(address, value),
-            0xFF4D | 0xFF4F | 0xFF51..=0xFF56 | 0xFF70 | 0xFF72..=0xFF77 if self.gb_mode != GbMode::Color => {}
+            0xFF72..=0xFF77 if !self.model.is_color() => {}
+            0xFF4D | 0xFF4F | 0xFF51..=0xFF56 | 0xFF70 | 0xFF74 if self.gb_mode != GbMode::Color => {}
             0xFF4D => self.speed_switch.write_8(address, value),
             0xFF4F => self.ppu.write_8(address, value),
             0xFF50 => {}
@@ -112,8 +116,8 @@ impl SystemMemoryAccess for SystemBus {
             0xFF56 => {} //todo!("Infrared Comms"),
             0xFF68..=0xFF6C => self.ppu.write_8(address, value),
             0xFF70 => self.memory.write_8(address, value),
-            0xFF72..=0xFF73 => self.undocumented_cgb_registers[address as usize - 0xFF72] = value,
-            0xFF75 => self.undocumented_cgb_registers[2] = value,
+            0xFF72..=0xFF74 => self.undocumented_cgb_registers[address as usize - 0xFF72] = value,
+            0xFF75 => self.undocumented_cgb_registers[3] = value,
             0xFF76..=0xFF77 => self.apu.write_8(address, value),
             0xFF80..=0xFFFE => self.memory.write_8(address, value),
             0xFFFF => {
@@ -126,6 +130,16 @@ impl SystemMemoryAccess for SystemBus {
 
 impl MemoryInterface for SystemBus {
     fn load_8(&mut self, address: u16, with_cycles: bool) -> u8 {
+        self.trigger_oam_bug(address, OamCorruption::Read);
+        let value = self.read_8(address);
+        if with_cycles {
+            self.m_cycle();
+        }
+        value
+    }
+
+    fn load_8_with_increment(&mut self, address: u16, with_cycles: bool) -> u8 {
+        self.trigger_oam_bug(address, OamCorruption::ReadIncrement);
         let value = self.read_8(address);
         if with_cycles {
             self.m_cycle();
@@ -134,6 +148,7 @@ impl MemoryInterface for SystemBus {
     }
 
     fn store_8(&mut self, address: u16, value: u8, with_cycles: bool) {
+        self.trigger_oam_bug(address, OamCorruption::Write);
         self.write_8(address, value);
         if with_cycles {
             self.m_cycle();
@@ -163,6 +178,10 @@ impl MemoryInterface for SystemBus {
         }
     }
 
+    fn idu_access(&mut self, address: u16) {
+        self.trigger_oam_bug(address, OamCorruption::Write);
+    }
+
     fn total_m_cycles(&self) -> u64 {
         self.total_m_cycles as u64
     }
@@ -191,13 +210,13 @@ impl MemoryInterface for SystemBus {
 }
 
 impl SystemBus {
-    pub fn new(cartridge: Cartridge, cpu_halted: Rc<RefCell<bool>>) -> Self {
-        let gb_mode = cartridge.mode();
+    pub fn new(cartridge: Cartridge, model: HardwareModel, gb_mode: GbMode, cpu_halted: Rc<RefCell<bool>>) -> Self {
         let interrupt_flag = Rc::new(RefCell::new(0));
         let mut bus = SystemBus {
+            model,
             gb_mode,
             memory: Memory::new(),
-            undocumented_cgb_registers: [0; 3],
+            undocumented_cgb_registers: [0; 4],
             speed_switch: SpeedSwitch::new(),
             dma: Dma::new(),
             cartridge,
@@ -206,16 +225,26 @@ impl SystemBus {
             serial_transfer: SerialTransfer::new(interrupt_flag.clone()),
             timer: Timer::new(interrupt_flag.clone()),
             ppu: Ppu::new(gb_mode, interrupt_flag),
-            apu: Apu::new(gb_mode),
+            apu: Apu::new(model),
             cpu_halted,
             total_m_cycles: 0,
             total_t_cycles: 0,
         };
 
         bus.set_hardware_registers();
+        if gb_mode == GbMode::ColorAsMonochrome {
+            let palette = bus.cartridge.compatibility_palette();
+            bus.ppu.load_compatibility_palette(palette);
+        }
         bus
     }
 
+    fn trigger_oam_bug(&mut self, address: u16, corruption: OamCorruption) {
+        if self.model.has_oam_bug() && (0xFE00..=0xFEFF).contains(&address) {
+            self.ppu.corrupt_oam(corruption);
+        }
+    }
+
     fn set_hardware_registers(&mut self) {
         self.write_8(0xFF04, 0);
         self.write_8(0xFF05, 0);
@@ -27,7 +27,7 @@ impl SystemMemoryAccess for Timer {
             0xFF05 => self.tima,
             0xFF06 => self.tma,
             0xFF07 => self.tac(),
-            _ => panic!("Timer does not handle read from address {:#4X}", address),
+            _ => 0xFF,
         }
     }
 
@@ -37,7 +37,7 @@ impl SystemMemoryAccess for Timer {
             0xFF05 => self.tima = value,
             0xFF06 => self.tma = value,
             0xFF07 => self.set_tac(value),
-            _ => panic!("Timer does not handle write to address {:4X}", address),
+            _ => {}
         }
     }
 }
@@ -0,0 +1,219 @@
+use crate::cpu::CPU_CLOCK_SPEED;
+
+/// VGM files count time in samples at this rate, whatever rate they are played back at
+const VGM_SAMPLE_RATE: u64 = 44100;
+const VERSION: u32 = 0x161;
+const HEADER_SIZE: usize = 0x100;
+const EOF_OFFSET: usize = 0x04;
+const VERSION_OFFSET: usize = 0x08;
+const TOTAL_SAMPLES_OFFSET: usize = 0x18;
+const LOOP_OFFSET: usize = 0x1C;
+const LOOP_SAMPLES_OFFSET: usize = 0x20;
+const DATA_OFFSET: usize = 0x34;
+const DMG_CLOCK_OFFSET: usize = 0x80;
+const DMG_WRITE: u8 = 0xB3;
+const WAIT: u8 = 0x61;
+const WAIT_NTSC_FRAME: u8 = 0x62;
+const WAIT_PAL_FRAME: u8 = 0x63;
+const WAIT_SHORT: u8 = 0x70;
+const END_OF_DATA: u8 = 0x66;
+/// Register 0 of the VGM DMG chip is NR10
+const FIRST_REGISTER: u16 = 0xFF10;
+/// A loop has to repeat at least this many writes, so a register that is rewritten every frame is not taken for one
+const MIN_LOOP_WRITES: usize = 8;
+/// Writes of two loop passes can be this many T-cycles apart, about one VGM sample
+const LOOP_TIMING_TOLERANCE: u64 = CPU_CLOCK_SPEED as u64 / VGM_SAMPLE_RATE;
+
+/// A write that reached the APU, `t_cycle` is counted on the 4 MiHz clock
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RegisterWrite {
+    pub t_cycle: u64,
+    pub address: u16,
+    pub value: u8,
+}
+
+/// The APU register writes between a start and a stop, with writes that bring a fresh APU to the state it had at the start
+#[derive(Debug, Clone, PartialEq)]
+pub struct VgmLog {
+    pub start: u64,
+    pub end: u64,
+    pub initial_state: Vec<(u16, u8)>,
+    pub writes: Vec<RegisterWrite>,
+}
+
+/// Where the song starts repeating, as indices into `VgmLog::writes`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VgmLoop {
+    pub start: usize,
+    pub writes: usize,
+}
+
+impl VgmLog {
+    /// The earliest point from which the writes repeat with the same timing until the log ends,
+    /// at least one full pass has to be logged after the first one
+    pub fn find_loop(&self) -> Option<VgmLoop> {
+        let count = self.writes.len();
+        let (matching, timed) = self.matching_suffixes();
+        // The timing can only repeat as far back as the registers and values do, which bounds the start of each
+        // period. Trying them from the lowest bound lets the search stop once no period can start earlier
+        let mut candidates: Vec<(usize, usize)> = (MIN_LOOP_WRITES..=count / 2)
+            .filter(|&period| matching[period] >= period)
+            .map(|period| (count - matching[period] - period, period))
+            .collect();
+        candidates.sort_unstable();
+
+        let mut best: Option<VgmLoop> = None;
+        for (earliest, period) in candidates {
+            if best.is_some_and(|best| (earliest, period) > (best.start, best.writes)) {
+                break;
+            }
+            // Writes with waits in the same bucket are known to repeat, only the ones before them are compared
+            let known = (timed[period] + 1).min(matching[period]);
+            let repeated = known
+                + (period..count - known)
+                    .rev()
+                    .take(matching[period] - known)
+                    .take_while(|&index| self.same_write(index, index - period))
+                    .count();
+            if repeated < period {
+                continue;
+            }
+            let start = count - repeated - period;
+            if best.is_none_or(|best| (start, period) < (best.start, best.writes)) {
+                best = Some(VgmLoop { start, writes: period });
+            }
+        }
+        best
+    }
+
+    /// A VGM 1.61 file for the DMG chip, ending after the first pass of the loop when `detect_loop` finds one
+    pub fn to_vgm(&self, detect_loop: bool) -> Vec<u8> {
+        let song_loop = if detect_loop { self.find_loop() } else { None };
+        let (writes, end) = match song_loop {
+            Some(song_loop) => {
+                let end = song_loop.start + song_loop.writes;
+                (&self.writes[..end], self.writes[end].t_cycle)
+            }
+            None => (&self.writes[..], self.end),
+        };
+
+        let mut file = vec![0; HEADER_SIZE];
+        for &(address, value) in &self.initial_state {
+            file.extend([DMG_WRITE, (address - FIRST_REGISTER) as u8, value]);
+        }
+
+        let mut position = 0;
+        let mut loop_position = None;
+        for (index, write) in writes.iter().enumerate() {
+            let sample = self.sample(write.t_cycle);
+            push_wait(&mut file, sample - position);
+            position = sample;
+            if song_loop.is_some_and(|song_loop| song_loop.start == index) {
+                loop_position = Some((file.len(), sample));
+            }
+            file.extend([DMG_WRITE, (write.address - FIRST_REGISTER) as u8, write.value]);
+        }
+        let total_samples = self.sample(end);
+        push_wait(&mut file, total_samples - position);
+        file.push(END_OF_DATA);
+
+        let file_size = file.len();
+        let mut put = |offset: usize, value: u32| file[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        put(0x00, u32::from_le_bytes(*b"Vgm "));
+        put(EOF_OFFSET, (file_size - EOF_OFFSET) as u32);
+        put(VERSION_OFFSET, VERSION);
+        put(TOTAL_SAMPLES_OFFSET, total_samples);
+        if let Some((offset, sample)) = loop_position {
+            put(LOOP_OFFSET, (offset - LOOP_OFFSET) as u32);
+            put(LOOP_SAMPLES_OFFSET, total_samples - sample);
+        }
+        put(DATA_OFFSET, (HEADER_SIZE - DATA_OFFSET) as u32);
+        put(DMG_CLOCK_OFFSET, CPU_CLOCK_SPEED);
+        file
+    }
+
+    /// The VGM sample a T-cycle falls on, rounded from the start so waits do not drift
+    fn sample(&self, t_cycle: u64) -> u32 {
+        let elapsed = t_cycle.saturating_sub(self.start);
+        ((elapsed * VGM_SAMPLE_RATE + CPU_CLOCK_SPEED as u64 / 2) / CPU_CLOCK_SPEED as u64) as u32
+    }
+
+    /// Same register and value, followed by the same wait. The last write has no wait after it yet
+    fn same_write(&self, index: usize, other: usize) -> bool {
+        let (write, other_write) = (self.writes[index], self.writes[other]);
+        let same_wait = match (self.wait_after(index), self.wait_after(other)) {
+            (Some(wait), Some(other_wait)) => wait.abs_diff(other_wait) <= LOOP_TIMING_TOLERANCE,
+            _ => true,
+        };
+        write.address == other_write.address && write.value == other_write.value && same_wait
+    }
+
+    /// For every period, how many writes back from the end have the same register and value as the write a period
+    /// before them, and how many before the last write also have a wait in the same bucket of tolerance. Those
+    /// are known to repeat, the rest have to be compared one by one
+    fn matching_suffixes(&self) -> (Vec<usize>, Vec<usize>) {
+        let values: Vec<(u16, u8)> = self.writes.iter().rev().map(|write| (write.address, write.value)).collect();
+        let timed: Vec<(u16, u8, u64)> = (0..self.writes.len())
+            .rev()
+            .filter_map(|index| {
+                let wait = self.wait_after(index)?;
+                Some((self.writes[index].address, self.writes[index].value, wait / (LOOP_TIMING_TOLERANCE + 1)))
+            })
+            .collect();
+        (common_prefixes(&values), common_prefixes(&timed))
+    }
+
+    /// T-cycles until the next write
+    fn wait_after(&self, index: usize) -> Option<u64> {
+        let next = self.writes.get(index + 1)?;
+        Some(next.t_cycle - self.writes[index].t_cycle)
+    }
+}
+
+/// The Z-algorithm, how far the values from each index agree with the values from the start, in linear time
+fn common_prefixes<T: PartialEq>(values: &[T]) -> Vec<usize> {
+    let count = values.len();
+    let mut lengths = vec![0; count];
+    if let Some(first) = lengths.first_mut() {
+        *first = count;
+    }
+    let (mut left, mut right) = (0, 0);
+    for index in 1..count {
+        if index < right {
+            lengths[index] = (right - index).min(lengths[index - left]);
+        }
+        while index + lengths[index] < count && values[lengths[index]] == values[index + lengths[index]] {
+            lengths[index] += 1;
+        }
+        if index + lengths[index] > right {
+            (left, right) = (index, index + lengths[index]);
+        }
+    }
+    lengths
+}
+
+fn push_wait(file: &mut Vec<u8>, mut samples: u32) {
+    while samples > 0 {
+        let waited = match samples {
+            735 => {
+                file.push(WAIT_NTSC_FRAME);
+                735
+            }
+            882 => {
+                file.push(WAIT_PAL_FRAME);
+                882
+            }
+            1..=16 => {
+                file.push(WAIT_SHORT + samples as u8 - 1);
+                samples
+            }
+            _ => {
+                let waited = samples.min(u16::MAX as u32);
+                file.push(WAIT);
+                file.extend((waited as u16).to_le_bytes());
+                waited
+            }
+        };
+        samples -= waited;
+    }
+}
@@ -0,0 +1,209 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::SampleRate;
+
+const PCM_FORMAT: u16 = 1;
+const FLOAT_FORMAT: u16 = 3;
+/// RIFF size, the format chunk and the data chunk header
+const PCM_HEADER_SIZE: u32 = 44;
+/// Float files also have a fact chunk and the extension size in the format chunk
+const FLOAT_HEADER_SIZE: u32 = 58;
+/// The fact chunk holds the number of frames
+const FACT_FRAMES_OFFSET: u64 = 46;
+const STEM_NAMES: [&str; 4] = ["pulse1", "pulse2", "wave", "noise"];
+
+#[derive(Error, Debug)]
+pub enum WavError {
+    #[error("Failed to write WAV file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("WAV file is over 4 GiB")]
+    TooLong,
+}
+
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub enum WavFormat {
+    #[default]
+    Pcm16,
+    Float32,
+}
+
+impl WavFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "pcm16" => Some(WavFormat::Pcm16),
+            "float" => Some(WavFormat::Float32),
+            _ => None,
+        }
+    }
+
+    fn bytes_per_sample(self) -> u16 {
+        match self {
+            WavFormat::Pcm16 => 2,
+            WavFormat::Float32 => 4,
+        }
+    }
+}
+
+/// Streams samples to a WAV file, the sizes in the header are filled in by `finish`
+pub struct WavWriter<W: Write + Seek> {
+    writer: W,
+    format: WavFormat,
+    channels: u16,
+    frames: u32,
+}
+
+impl WavWriter<BufWriter<File>> {
+    pub fn create(path: &Path, format: WavFormat, channels: u16, sample_rate: SampleRate) -> Result<Self, WavError> {
+        WavWriter::new(BufWriter::new(File::create(path)?), format, channels, sample_rate)
+    }
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    pub fn new(writer: W, format: WavFormat, channels: u16, sample_rate: SampleRate) -> Result<Self, WavError> {
+        let mut wav = WavWriter {
+            writer,
+            format,
+            channels,
+            frames: 0,
+        };
+        wav.write_header(sample_rate.hz())?;
+        Ok(wav)
+    }
+
+    /// Interleaves one slice per channel, all of the same length
+    pub fn write(&mut self, channels: &[&[f32]]) -> Result<(), WavError> {
+        let frames = channels.iter().map(|samples| samples.len()).min().unwrap_or(0);
+        let total_frames = self.frames as u64 + frames as u64;
+        if FLOAT_HEADER_SIZE as u64 + total_frames * self.block_align() as u64 > u32::MAX as u64 {
+            return Err(WavError::TooLong);
+        }
+
+        for frame in 0..frames {
+            for samples in channels {
+                match self.format {
+                    WavFormat::Pcm16 => {
+                        let sample = (samples[frame].clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16;
+                        self.writer.write_all(&sample.to_le_bytes())?
+                    }
+                    WavFormat::Float32 => self.writer.write_all(&samples[frame].to_le_bytes())?,
+                }
+            }
+        }
+        self.frames = total_frames as u32;
+        Ok(())
+    }
+
+    /// Writes the final sizes and hands the writer back
+    pub fn finish(mut self) -> Result<W, WavError> {
+        let data_size = self.frames * self.block_align() as u32;
+        let (header_size, data_size_offset) = match self.format {
+            WavFormat::Pcm16 => (PCM_HEADER_SIZE, PCM_HEADER_SIZE - 4),
+            WavFormat::Float32 => (FLOAT_HEADER_SIZE, FLOAT_HEADER_SIZE - 4),
+        };
+
+        self.writer.seek(SeekFrom::Start(4))?;
+        self.writer.write_all(&(header_size - 8 + data_size).to_le_bytes())?;
+        if self.format == WavFormat::Float32 {
+            self.writer.seek(SeekFrom::Start(FACT_FRAMES_OFFSET))?;
+            self.writer.write_all(&self.frames.to_le_bytes())?;
+        }
+        self.writer.seek(SeekFrom::Start(data_size_offset as u64))?;
+        self.writer.write_all(&data_size.to_le_bytes())?;
+        self.writer.seek(SeekFrom::End(0))?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn block_align(&self) -> u16 {
+        self.channels * self.format.bytes_per_sample()
+    }
+
+    fn write_header(&mut self, sample_rate: u32) -> Result<(), WavError> {
+        let (format_tag, format_size) = match self.format {
+            WavFormat::Pcm16 => (PCM_FORMAT, 16u32),
+            WavFormat::Float32 => (FLOAT_FORMAT, 18u32),
+        };
+        let block_align = self.block_align();
+
+        let mut header = Vec::with_capacity(FLOAT_HEADER_SIZE as usize);
+        header.extend(b"RIFF");
+        header.extend(0u32.to_le_bytes());
+        header.extend(b"WAVE");
+        header.extend(b"fmt ");
+        header.extend(format_size.to_le_bytes());
+        header.extend(format_tag.to_le_bytes());
+        header.extend(self.channels.to_le_bytes());
+        header.extend(sample_rate.to_le_bytes());
+        header.extend((sample_rate * block_align as u32).to_le_bytes());
+        header.extend(block_align.to_le_bytes());
+        header.extend((self.format.bytes_per_sample() * 8).to_le_bytes());
+        if self.format == WavFormat::Float32 {
+            header.extend(0u16.to_le_bytes());
+            header.extend(b"fact");
+            header.extend(4u32.to_le_bytes());
+            header.extend(0u32.to_le_bytes());
+        }
+        header.extend(b"data");
+        header.extend(0u32.to_le_bytes());
+
+        self.writer.write_all(&header)?;
+        Ok(())
+    }
+}
+
+/// Records the mixed stereo output, and optionally every channel on its own into `<name>.<channel>.wav` stems
+pub struct AudioRecorder {
+    mix: WavWriter<BufWriter<File>>,
+    stems: Option<[WavWriter<BufWriter<File>>; 4]>,
+}
+
+impl AudioRecorder {
+    pub fn start(path: &Path, format: WavFormat, sample_rate: SampleRate, stems: bool) -> Result<Self, WavError> {
+        let mix = WavWriter::create(path, format, 2, sample_rate)?;
+        let stems = match stems {
+            true => {
+                let create = |stem: &PathBuf| WavWriter::create(stem, format, 1, sample_rate);
+                let [pulse1, pulse2, wave, noise] = stem_paths(path);
+                Some([create(&pulse1)?, create(&pulse2)?, create(&wave)?, create(&noise)?])
+            }
+            false => None,
+        };
+        Ok(AudioRecorder { mix, stems })
+    }
+
+    /// Stems need the channel streams, so channel capture has to be on while recording them
+    pub fn records_stems(&self) -> bool {
+        self.stems.is_some()
+    }
+
+    /// `channels` are the separate streams in pulse 1, pulse 2, wave, noise order, only used for stems
+    pub fn write(&mut self, left: &[f32], right: &[f32], channels: [&[f32]; 4]) -> Result<(), WavError> {
+        self.mix.write(&[left, right])?;
+        if let Some(stems) = &mut self.stems {
+            for (stem, samples) in stems.iter_mut().zip(channels) {
+                stem.write(&[samples])?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<(), WavError> {
+        self.mix.finish()?;
+        for stem in self.stems.into_iter().flatten() {
+            stem.finish()?;
+        }
+        Ok(())
+    }
+}
+
+/// Where the stems of a recording to `path` go
+pub fn stem_paths(path: &Path) -> [PathBuf; 4] {
+    let name = path.file_stem().unwrap_or_default().to_string_lossy();
+    STEM_NAMES.map(|channel| path.with_file_name(format!("{}.{}.wav", name, channel)))
+}
@@ -0,0 +1,629 @@
+use ironboy_core::{GameBoy, GameBoyConfig, HardwareModel, LoadMode, SampleRate, cpu::CPU_CLOCK_SPEED};
+
+use crate::support::rom_with_code;
+
+pub const RECORDED_FRAMES: usize = 10;
+
+/// Pulse 1 at full volume with a 50% duty on both sides, `period` sets the frequency to 131072 / (2048 - period) Hz
+pub fn pulse_code(period: u16) -> Vec<u8> {
+    pulse_code_with_duty(period, 0x80)
+}
+
+/// Like `pulse_code` with the duty written to NR11
+pub fn pulse_code_with_duty(period: u16, nr11: u8) -> Vec<u8> {
+    let [low, high] = period.to_le_bytes();
+    // NR52 on, NR50 full volume, NR51 pulse 1 on both sides, NR11 duty, NR12 volume 15, NR13/NR14 period and trigger; JR -2
+    vec![
+        0x3E,
+        0x80,
+        0xE0,
+        0x26,
+        0x3E,
+        0x77,
+        0xE0,
+        0x24,
+        0x3E,
+        0x11,
+        0xE0,
+        0x25,
+        0x3E,
+        nr11,
+        0xE0,
+        0x11,
+        0x3E,
+        0xF0,
+        0xE0,
+        0x12,
+        0x3E,
+        low,
+        0xE0,
+        0x13,
+        0x3E,
+        0x80 | high,
+        0xE0,
+        0x14,
+        0x18,
+        0xFE,
+    ]
+}
+
+/// Turns the pulse 1 DAC off, waits about 3 frames and turns it back on without triggering the channel
+pub const DAC_POP: [u8; 27] = [
+    0x3E, 0x80, 0xE0, 0x26, 0x3E, 0x77, 0xE0, 0x24, 0x3E, 0x11, 0xE0, 0x25, 0xAF, 0xE0, 0x12, 0x01, 0x00, 0x20, 0x0B, 0x78, 0xB1, 0x20, 0xFB, 0x3E,
+    0xF0, 0xE0, 0x12,
+];
+
+/// KEY1 armed and STOP, the code after it runs in double speed
+pub const DOUBLE_SPEED: [u8; 6] = [0x3E, 0x01, 0xE0, 0x4D, 0x10, 0x00];
+
+/// Runs the code placed after the header, as a CGB cartridge on color models
+pub fn run_audio(code: &[u8], sample_rate: SampleRate, model: HardwareModel) -> GameBoy {
+    let cgb_flag = if model == HardwareModel::Cgb { 0x80 } else { 0x00 };
+    let config = GameBoyConfig {
+        load_mode: LoadMode::Lenient,
+        model: Some(model),
+        sample_rate,
+        ..Default::default()
+    };
+    GameBoy::with_config("audio.gb", rom_with_code(code, cgb_flag), config).expect("Unable to load test rom")
+}
+
+/// The left samples of `RECORDED_FRAMES` frames, skipping the first ones while the channel starts
+pub fn record(game_boy: &mut GameBoy) -> Vec<f32> {
+    for _ in 0..2 {
+        game_boy.run_until_frame_complete();
+    }
+    let start = game_boy.audio_samples().0.len();
+    for _ in 0..RECORDED_FRAMES {
+        game_boy.run_until_frame_complete();
+    }
+    game_boy.audio_samples().0[start..].to_vec()
+}
+
+/// The left samples of a quarter of an emulated second, after the first frames
+pub fn record_quarter_second(game_boy: &mut GameBoy) -> Vec<f32> {
+    for _ in 0..2 {
+        game_boy.run_until_frame_complete();
+    }
+    let start = game_boy.audio_samples().0.len();
+    let end = game_boy.t_cycles() + CPU_CLOCK_SPEED as u64 / 4;
+    while game_boy.t_cycles() < end {
+        game_boy.step();
+    }
+    game_boy.audio_samples().0[start..].to_vec()
+}
+
+pub fn mean(samples: &[f32]) -> f32 {
+    samples.iter().sum::<f32>() / samples.len() as f32
+}
+
+pub fn rising_crossings(samples: &[f32]) -> usize {
+    let mean = mean(samples);
+    samples.windows(2).filter(|pair| pair[0] < mean && pair[1] >= mean).count()
+}
+
+pub fn rms(samples: &[f32]) -> f32 {
+    let mean = mean(samples);
+    (samples.iter().map(|sample| (sample - mean).powi(2)).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fuzz::Rng;
+    use ironboy_core::{
+        AudioChannel, AudioOutput, FRAME_T_CYCLES,
+        vgm::{RegisterWrite, VgmLog, VgmLoop},
+        wav::{AudioRecorder, WavFormat, WavWriter, stem_paths},
+    };
+    use std::{fs, io::Cursor};
+
+    #[test]
+    fn tone_at_every_rate() {
+        // 131072 / (2048 - 1917) is about 1000.5 Hz, so about 167.5 periods over 10 frames
+        for sample_rate in SampleRate::ALL {
+            let samples = record(&mut run_audio(&pulse_code(1917), sample_rate, HardwareModel::Dmg));
+            let expected = (RECORDED_FRAMES as u32 * FRAME_T_CYCLES) as f64 * sample_rate.hz() as f64 / CPU_CLOCK_SPEED as f64;
+            assert!(
+                (samples.len() as f64 - expected).abs() <= 1.0,
+                "{} samples at {} Hz",
+                samples.len(),
+                sample_rate.hz()
+            );
+
+            let crossings = rising_crossings(&samples);
+            assert!((167..=168).contains(&crossings), "{} periods at {} Hz", crossings, sample_rate.hz());
+            assert!(samples.iter().all(|sample| sample.abs() <= 1.0));
+
+            let again = record(&mut run_audio(&pulse_code(1917), sample_rate, HardwareModel::Dmg));
+            assert_eq!(samples, again);
+        }
+    }
+
+    #[test]
+    fn tones_above_nyquist_are_filtered() {
+        // 131072 / (2048 - 2040) = 16384 Hz, above half of 22050 and 32000
+        for sample_rate in [SampleRate::Hz22050, SampleRate::Hz32000] {
+            let audible = rms(&record(&mut run_audio(&pulse_code(1917), sample_rate, HardwareModel::Dmg)));
+            let filtered = rms(&record(&mut run_audio(&pulse_code(2040), sample_rate, HardwareModel::Dmg)));
+            assert!(filtered < audible / 10.0, "{} against {} at {} Hz", filtered, audible, sample_rate.hz());
+        }
+    }
+
+    #[test]
+    fn double_speed_keeps_the_pace() {
+        for sample_rate in [SampleRate::Hz22050, SampleRate::Hz48000] {
+            let normal = record_quarter_second(&mut run_audio(&pulse_code(1917), sample_rate, HardwareModel::Cgb));
+            let mut code = DOUBLE_SPEED.to_vec();
+            code.extend(pulse_code(1917));
+            let double = record_quarter_second(&mut run_audio(&code, sample_rate, HardwareModel::Cgb));
+
+            let expected = sample_rate.hz() as usize / 4;
+            assert!(
+                normal.len().abs_diff(expected) <= 2,
+                "{} samples at {} Hz",
+                normal.len(),
+                sample_rate.hz()
+            );
+            assert!(
+                double.len().abs_diff(expected) <= 2,
+                "{} samples at {} Hz",
+                double.len(),
+                sample_rate.hz()
+            );
+            // About 250 periods of the 1000.5 Hz tone either way
+            assert!(rising_crossings(&normal).abs_diff(250) <= 1);
+            assert!(rising_crossings(&double).abs_diff(250) <= 1);
+        }
+    }
+
+    #[test]
+    fn audio_rate_adjustment() {
+        let mut game_boy = run_audio(&pulse_code(1917), SampleRate::Hz44100, HardwareModel::Dmg);
+        game_boy.run_frame_with_audio();
+        let record_frames = |game_boy: &mut GameBoy| -> Vec<f32> { (0..60).flat_map(|_| game_boy.run_frame_with_audio().0.to_vec()).collect() };
+
+        // 60 frames of 70224 T-cycles at 44100 Hz
+        let nominal = record_frames(&mut game_boy);
+        assert!(nominal.len().abs_diff(44302) <= 1, "{}", nominal.len());
+
+        game_boy.set_audio_rate_adjustment(1.005);
+        let faster = record_frames(&mut game_boy);
+        let ratio = faster.len() as f64 / nominal.len() as f64;
+        assert!((ratio - 1.005).abs() < 0.0001, "{}", ratio);
+        // The same tone is spread over more samples, it is not sped up
+        assert!(rising_crossings(&faster).abs_diff(rising_crossings(&nominal)) <= 1);
+
+        game_boy.set_audio_rate_adjustment(1.0);
+        assert!(record_frames(&mut game_boy).len().abs_diff(44302) <= 1);
+
+        // Fast forward at 8x and slow motion at 0.25x
+        game_boy.set_audio_rate_adjustment(0.125);
+        assert!(record_frames(&mut game_boy).len().abs_diff(44302 / 8) <= 1);
+        game_boy.set_audio_rate_adjustment(4.0);
+        assert!(record_frames(&mut game_boy).len().abs_diff(44302 * 4) <= 4);
+    }
+
+    #[test]
+    fn rate_adjustment_is_clamped() {
+        let mut game_boy = run_audio(&pulse_code(1917), SampleRate::Hz44100, HardwareModel::Dmg);
+        for (adjustment, samples) in [
+            (0.0, 738 / 16),
+            (-1.0, 738 / 16),
+            (f64::MIN_POSITIVE, 738 / 16),
+            (f64::NAN, 738),
+            (1e300, 738 * 16),
+            (f64::INFINITY, 738 * 16),
+        ] {
+            game_boy.set_audio_rate_adjustment(adjustment);
+            game_boy.run_frame_with_audio();
+            let count = game_boy.run_frame_with_audio().0.len();
+            assert!(count.abs_diff(samples) <= 16, "{}: {}", adjustment, count);
+        }
+    }
+
+    #[test]
+    fn recording_ignores_rate_adjustment() {
+        let mut game_boy = run_audio(&pulse_code(1917), SampleRate::Hz44100, HardwareModel::Dmg);
+        assert!(!game_boy.recording());
+        game_boy.run_frame_with_audio();
+        assert!(game_boy.recording_samples().0.is_empty());
+
+        game_boy.set_recording(true);
+        game_boy.set_audio_rate_adjustment(1.005);
+        let (mut played, mut recorded) = (0, Vec::new());
+        for _ in 0..60 {
+            played += game_boy.run_frame_with_audio().0.len();
+            let (left, right) = game_boy.recording_samples();
+            assert_eq!(left.len(), right.len());
+            recorded.extend_from_slice(left);
+        }
+        assert!(played.abs_diff(44302 * 1005 / 1000) <= 1, "{}", played);
+        assert!(recorded.len().abs_diff(44302) <= 1, "{}", recorded.len());
+        assert!(rms(&recorded) > 0.1);
+    }
+
+    #[test]
+    fn recording_keeps_the_rate_at_every_speed() {
+        // The desktop client divides the rate adjustment by the speed multiplier, a quarter second is 11025 samples
+        for multiplier in [0.25, 0.5, 1.0, 2.0, 4.0, 8.0] {
+            let mut game_boy = run_audio(&pulse_code(1917), SampleRate::Hz44100, HardwareModel::Dmg);
+            game_boy.set_recording(true);
+            game_boy.set_audio_rate_adjustment(1.0 / multiplier);
+            game_boy.run_frame_with_audio();
+
+            let (played, recorded) = (game_boy.audio_samples().0.len(), game_boy.recording_samples().0.len());
+            let end = game_boy.t_cycles() + CPU_CLOCK_SPEED as u64 / 4;
+            while game_boy.t_cycles() < end {
+                game_boy.step();
+            }
+            let played = game_boy.audio_samples().0.len() - played;
+            let recorded = game_boy.recording_samples().0.len() - recorded;
+            assert!(recorded.abs_diff(11025) <= 1, "{}x: {}", multiplier, recorded);
+            assert!(played.abs_diff((11025.0 / multiplier) as usize) <= 1, "{}x: {}", multiplier, played);
+        }
+    }
+
+    #[test]
+    fn analog_output_removes_the_dc_offset() {
+        // A 12.5% duty spends most of the time at digital 0
+        let code = pulse_code_with_duty(1917, 0x00);
+        let ideal = mean(&record(&mut run_audio(&code, SampleRate::Hz44100, HardwareModel::Dmg)));
+        assert!(ideal < -0.1, "{}", ideal);
+
+        for model in [HardwareModel::Dmg, HardwareModel::Cgb, HardwareModel::Agb] {
+            let mut game_boy = run_audio(&code, SampleRate::Hz44100, model);
+            game_boy.set_audio_output(AudioOutput::Analog);
+            let analog = mean(&record(&mut game_boy));
+            assert!(analog.abs() < 0.01, "{} on {:?}", analog, model);
+        }
+    }
+
+    #[test]
+    fn dac_pops() {
+        let pop = |model, audio_output| {
+            let mut game_boy = run_audio(&DAC_POP, SampleRate::Hz44100, model);
+            game_boy.set_audio_output(audio_output);
+            for _ in 0..6 {
+                game_boy.run_until_frame_complete();
+            }
+            let samples = game_boy.audio_samples().0;
+            // The boot state already turned the DAC on, that step has faded after 2 frames
+            let settled = &samples[2 * samples.len() / 6..];
+            assert!(settled.last().unwrap().abs() < 0.01);
+            settled.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()))
+        };
+
+        assert!(pop(HardwareModel::Dmg, AudioOutput::Analog) > 0.2);
+        assert!(pop(HardwareModel::Cgb, AudioOutput::Analog) > 0.1);
+        assert!(pop(HardwareModel::Agb, AudioOutput::Analog) < 0.01);
+        assert_eq!(pop(HardwareModel::Dmg, AudioOutput::Ideal), 0.0);
+    }
+
+    #[test]
+    fn vin_is_mixed_by_nr50() {
+        let vin = |nr50| {
+            // NR50; JR -2
+            let mut game_boy = run_audio(&[0x3E, nr50, 0xE0, 0x24, 0x18, 0xFE], SampleRate::Hz44100, HardwareModel::Dmg);
+            game_boy.set_audio_output(AudioOutput::Ideal);
+            game_boy.set_vin_input(0.5);
+            game_boy.run_until_frame_complete();
+            game_boy.run_until_frame_complete();
+            let (left, right) = game_boy.audio_samples();
+            (*left.last().unwrap(), *right.last().unwrap())
+        };
+
+        assert_eq!(vin(0x77), (0.0, 0.0));
+        // VIN left only at volume 7, so 0.5 * 8 / 32
+        let (left, right) = vin(0xF7);
+        assert!((left - 0.125).abs() < 0.001, "{}", left);
+        assert_eq!(right, 0.0);
+    }
+
+    #[test]
+    fn muted_and_soloed_channels() {
+        let tone = |mute: Option<AudioChannel>, solo: Option<AudioChannel>| {
+            let mut game_boy = run_audio(&pulse_code(1917), SampleRate::Hz44100, HardwareModel::Dmg);
+            game_boy.set_channel_capture(true);
+            if let Some(channel) = mute {
+                game_boy.set_channel_muted(channel, true);
+            }
+            if let Some(channel) = solo {
+                game_boy.set_channel_soloed(channel, true);
+            }
+            let mixed = rms(&record(&mut game_boy));
+            let start = game_boy.channel_samples(AudioChannel::Pulse1).len() / 2;
+            let pulse = rms(&game_boy.channel_samples(AudioChannel::Pulse1)[start..]);
+            let noise = rms(game_boy.channel_samples(AudioChannel::Noise));
+            (mixed, pulse, noise)
+        };
+
+        let (mixed, pulse, noise) = tone(None, None);
+        assert!(mixed > 0.1);
+        // Channel streams are full scale, before NR50 and panning
+        assert!(pulse > 0.9, "{}", pulse);
+        assert_eq!(noise, 0.0);
+
+        assert!(tone(None, Some(AudioChannel::Pulse1)).0 > 0.1);
+        for (mute, solo) in [
+            (Some(AudioChannel::Pulse1), None),
+            (None, Some(AudioChannel::Wave)),
+            (Some(AudioChannel::Pulse1), Some(AudioChannel::Pulse1)),
+        ] {
+            let (mixed, pulse, _) = tone(mute, solo);
+            assert_eq!(mixed, 0.0, "{:?} muted, {:?} soloed", mute, solo);
+            assert!(pulse > 0.9);
+        }
+    }
+
+    #[test]
+    fn channel_views() {
+        let mut game_boy = run_audio(&pulse_code(1917), SampleRate::Hz44100, HardwareModel::Dmg);
+        game_boy.run_until_frame_complete();
+        let [pulse, _, wave, noise] = game_boy.channel_views();
+
+        assert_eq!(pulse.channel, AudioChannel::Pulse1);
+        assert!(pulse.enabled && pulse.dac_enabled);
+        assert_eq!(pulse.duty_percent(), Some(50.0));
+        assert_eq!(pulse.volume, 15);
+        assert!((pulse.frequency - 1000.5).abs() < 0.1, "{}", pulse.frequency);
+        // 1000.5 Hz is 22 cents above B5 at 987.8 Hz
+        assert_eq!(pulse.note().as_deref(), Some("B5 +22"));
+        assert_eq!(pulse.sweep.map(|sweep| sweep.step), Some(0));
+
+        assert_eq!(wave.wave_ram.map(|samples| samples.len()), Some(32));
+        assert_eq!(wave.duty, None);
+        assert_eq!(noise.lfsr_width, Some(15));
+        assert_eq!(noise.note(), None);
+        assert!(!noise.enabled);
+    }
+
+    #[test]
+    fn wav_files() {
+        let mut wav = WavWriter::new(Cursor::new(Vec::new()), WavFormat::Pcm16, 2, SampleRate::Hz48000).unwrap();
+        wav.write(&[&[1.0, -2.0], &[0.0, -0.5]]).unwrap();
+        let bytes = wav.finish().unwrap().into_inner();
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(bytes[4..8].try_into().unwrap()), 36 + 8);
+        // PCM, stereo, 48000 Hz, 4 bytes per frame, 16 bits
+        assert_eq!(u16::from_le_bytes([bytes[20], bytes[21]]), 1);
+        assert_eq!(u16::from_le_bytes([bytes[22], bytes[23]]), 2);
+        assert_eq!(u32::from_le_bytes(bytes[24..28].try_into().unwrap()), 48000);
+        assert_eq!(u16::from_le_bytes([bytes[32], bytes[33]]), 4);
+        assert_eq!(u16::from_le_bytes([bytes[34], bytes[35]]), 16);
+        assert_eq!(u32::from_le_bytes(bytes[40..44].try_into().unwrap()), 8);
+        let samples: Vec<i16> = bytes[44..].chunks(2).map(|pair| i16::from_le_bytes([pair[0], pair[1]])).collect();
+        assert_eq!(samples, [32767, 0, -32767, -16384]);
+
+        let mut wav = WavWriter::new(Cursor::new(Vec::new()), WavFormat::Float32, 1, SampleRate::Hz44100).unwrap();
+        wav.write(&[&[0.25, -2.0, 0.5]]).unwrap();
+        let bytes = wav.finish().unwrap().into_inner();
+        assert_eq!(u16::from_le_bytes([bytes[20], bytes[21]]), 3);
+        assert_eq!(&bytes[38..42], b"fact");
+        assert_eq!(u32::from_le_bytes(bytes[46..50].try_into().unwrap()), 3);
+        assert_eq!(u32::from_le_bytes(bytes[54..58].try_into().unwrap()), 12);
+        // Float samples are not clipped
+        assert_eq!(f32::from_le_bytes(bytes[62..66].try_into().unwrap()), -2.0);
+    }
+
+    #[test]
+    fn channel_capture_is_opt_in() {
+        let mut game_boy = run_audio(&pulse_code(1917), SampleRate::Hz44100, HardwareModel::Dmg);
+        assert!(!game_boy.channel_capture());
+        assert!(!record(&mut game_boy).is_empty());
+        assert!(game_boy.channel_samples(AudioChannel::Pulse1).is_empty());
+
+        game_boy.set_channel_capture(true);
+        record(&mut game_boy);
+        assert!(!game_boy.channel_samples(AudioChannel::Pulse1).is_empty());
+
+        game_boy.set_channel_capture(false);
+        assert!(game_boy.channel_samples(AudioChannel::Pulse1).is_empty());
+    }
+
+    #[test]
+    fn recorder_writes_stems() {
+        let directory = std::env::temp_dir().join(format!("ironboy_recorder_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("song.wav");
+
+        let mut game_boy = run_audio(&pulse_code(1917), SampleRate::Hz22050, HardwareModel::Dmg);
+        let mut recorder = AudioRecorder::start(&path, WavFormat::Pcm16, SampleRate::Hz22050, true).unwrap();
+        assert!(recorder.records_stems());
+        game_boy.set_channel_capture(true);
+        let mut frames = 0;
+        for _ in 0..4 {
+            game_boy.run_until_audio_buffer_full();
+            let (left, right) = game_boy.audio_samples();
+            frames += left.len();
+            recorder
+                .write(left, right, AudioChannel::ALL.map(|channel| game_boy.channel_samples(channel)))
+                .unwrap();
+        }
+        recorder.finish().unwrap();
+
+        assert_eq!(fs::metadata(&path).unwrap().len() as usize, 44 + 4 * frames);
+        for stem in stem_paths(&path) {
+            assert_eq!(fs::metadata(&stem).unwrap().len() as usize, 44 + 2 * frames, "{}", stem.display());
+        }
+        assert!(stem_paths(&path)[0].ends_with("song.pulse1.wav"));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    /// Samples waited by the commands after the header, checking that only DMG writes and waits are used
+    fn vgm_waits(vgm: &[u8]) -> u32 {
+        let (mut position, mut samples) = (0x100, 0);
+        loop {
+            match vgm[position] {
+                0xB3 => position += 3,
+                0x61 => {
+                    samples += u16::from_le_bytes([vgm[position + 1], vgm[position + 2]]) as u32;
+                    position += 3;
+                }
+                0x62 => (samples, position) = (samples + 735, position + 1),
+                0x63 => (samples, position) = (samples + 882, position + 1),
+                command @ 0x70..=0x7F => (samples, position) = (samples + (command & 0x0F) as u32 + 1, position + 1),
+                0x66 => return samples,
+                command => panic!("Unexpected command {:#04X}", command),
+            }
+        }
+    }
+
+    #[test]
+    fn vgm_log_of_a_tone() {
+        let mut game_boy = run_audio(&pulse_code(1917), SampleRate::Hz44100, HardwareModel::Dmg);
+        game_boy.start_vgm_log();
+        for _ in 0..4 {
+            game_boy.run_until_frame_complete();
+        }
+        let log = game_boy.stop_vgm_log().unwrap();
+        assert!(!game_boy.vgm_logging());
+
+        let addresses: Vec<u16> = log.writes.iter().map(|write| write.address).collect();
+        assert_eq!(addresses, [0xFF26, 0xFF24, 0xFF25, 0xFF11, 0xFF12, 0xFF13, 0xFF14]);
+        assert!(log.writes.windows(2).all(|pair| pair[0].t_cycle < pair[1].t_cycle));
+
+        // Started again while the tone plays, the initial state powers the APU and retriggers pulse 1
+        game_boy.start_vgm_log();
+        game_boy.run_until_frame_complete();
+        let log = game_boy.stop_vgm_log().unwrap();
+        assert!(log.writes.is_empty());
+        assert_eq!(log.initial_state.first(), Some(&(0xFF26, 0x80)));
+        assert!(log.initial_state.contains(&(0xFF12, 0xF0)));
+        assert_eq!(log.initial_state.last(), Some(&(0xFF14, 0x87)));
+
+        let vgm = log.to_vgm(true);
+        assert_eq!(&vgm[0..4], b"Vgm ");
+        assert_eq!(read_u32(&vgm, 0x04) as usize, vgm.len() - 4);
+        assert_eq!(read_u32(&vgm, 0x08), 0x161);
+        assert_eq!(read_u32(&vgm, 0x1C), 0);
+        assert_eq!(read_u32(&vgm, 0x34), 0xCC);
+        assert_eq!(read_u32(&vgm, 0x80), CPU_CLOCK_SPEED);
+        // NR52 on is register 0x16 of the DMG chip
+        assert_eq!(&vgm[0x100..0x103], &[0xB3, 0x16, 0x80]);
+        // A frame is 738.3 VGM samples
+        assert_eq!(read_u32(&vgm, 0x18), 738);
+        assert_eq!(vgm_waits(&vgm), 738);
+    }
+
+    #[test]
+    fn vgm_loop_detection() {
+        let frame = FRAME_T_CYCLES as u64;
+        let mut writes: Vec<RegisterWrite> = (0..3)
+            .map(|i| RegisterWrite {
+                t_cycle: i * 100,
+                address: 0xFF24,
+                value: i as u8,
+            })
+            .collect();
+        // An intro, then a 10 note song played two and a half times
+        for pass in 0..25u64 {
+            writes.push(RegisterWrite {
+                t_cycle: frame * (pass + 1),
+                address: 0xFF13,
+                value: (pass % 10) as u8 * 16,
+            });
+        }
+        let log = VgmLog {
+            start: 0,
+            end: frame * 26,
+            initial_state: vec![(0xFF26, 0x80)],
+            writes,
+        };
+        assert_eq!(log.find_loop(), Some(VgmLoop { start: 3, writes: 10 }));
+
+        let vgm = log.to_vgm(true);
+        let loop_offset = read_u32(&vgm, 0x1C) as usize + 0x1C;
+        assert_eq!(&vgm[loop_offset..loop_offset + 3], &[0xB3, 0x03, 0x00]);
+        // The intro and one pass of 10 frames, cut where the second pass starts
+        assert_eq!(read_u32(&vgm, 0x18), 8122);
+        assert_eq!(read_u32(&vgm, 0x20), 8122 - 738);
+        assert_eq!(vgm_waits(&vgm), 8122);
+
+        assert_eq!(read_u32(&log.to_vgm(false), 0x1C), 0);
+    }
+
+    /// Every period checked against every write, how `find_loop` used to search
+    fn find_loop_naively(log: &VgmLog) -> Option<VgmLoop> {
+        let tolerance = CPU_CLOCK_SPEED as u64 / 44100;
+        let wait_after = |index: usize| log.writes.get(index + 1).map(|next| next.t_cycle - log.writes[index].t_cycle);
+        let same_write = |index: usize, other: usize| {
+            let (write, other_write) = (log.writes[index], log.writes[other]);
+            let same_wait = match (wait_after(index), wait_after(other)) {
+                (Some(wait), Some(other_wait)) => wait.abs_diff(other_wait) <= tolerance,
+                _ => true,
+            };
+            write.address == other_write.address && write.value == other_write.value && same_wait
+        };
+
+        let count = log.writes.len();
+        let mut best: Option<VgmLoop> = None;
+        for period in 8..=count / 2 {
+            let repeated = (period..count).rev().take_while(|&index| same_write(index, index - period)).count();
+            let start = count - repeated - period;
+            if repeated >= period && best.is_none_or(|best| start < best.start) {
+                best = Some(VgmLoop { start, writes: period });
+            }
+        }
+        best
+    }
+
+    /// An intro, then a pattern repeated a few times and cut anywhere, with waits that are sometimes within tolerance
+    fn random_log(rng: &mut Rng) -> VgmLog {
+        let write = |rng: &mut Rng| (rng.pick(&[0xFF12u16, 0xFF13]), rng.below(3) as u8, rng.pick(&[100u64, 150, 1000]));
+        let intro: Vec<_> = (0..rng.below(20)).map(|_| write(rng)).collect();
+        let pattern: Vec<_> = (0..1 + rng.below(30)).map(|_| write(rng)).collect();
+        let length = intro.len() + pattern.len() * (1 + rng.below(4)) + rng.below(pattern.len());
+
+        let mut t_cycle = 0;
+        let writes = intro
+            .iter()
+            .chain(pattern.iter().cycle())
+            .take(length)
+            .map(|&(address, value, wait)| {
+                t_cycle += wait;
+                RegisterWrite { t_cycle, address, value }
+            })
+            .collect();
+        VgmLog {
+            start: 0,
+            end: t_cycle + 1000,
+            initial_state: Vec::new(),
+            writes,
+        }
+    }
+
+    #[test]
+    fn vgm_loop_detection_matches_a_naive_search() {
+        let mut rng = Rng::new(0x5EED);
+        for _ in 0..2000 {
+            let log = random_log(&mut rng);
+            assert_eq!(log.find_loop(), find_loop_naively(&log), "{:?}", log.writes);
+        }
+    }
+
+    #[test]
+    fn vgm_loop_detection_on_a_long_log() {
+        // An hour of the same register rewritten every frame with an uneven intro, too long to compare every period
+        let frame = FRAME_T_CYCLES as u64;
+        let writes: Vec<RegisterWrite> = (0..60 * 60 * 60)
+            .map(|i| RegisterWrite {
+                t_cycle: i * frame + if i < 100 { i * i } else { 0 },
+                address: 0xFF13,
+                value: (i % 2) as u8,
+            })
+            .collect();
+        let log = VgmLog {
+            start: 0,
+            end: frame * writes.len() as u64,
+            initial_state: Vec::new(),
+            writes,
+        };
+        // The waits settle to a frame once the intro is over
+        assert_eq!(log.find_loop(), Some(VgmLoop { start: 100, writes: 8 }));
+    }
+}
@@ -0,0 +1,218 @@
+#[cfg(test)]
+mod tests {
+    use ironboy_core::{CartridgeError, GameBoy, GameBoyConfig, HardwareModel, LoadMode, gb::GameBoyError};
+
+    use crate::support::rom_with_code;
+
+    const BANK_SIZE: usize = 0x4000;
+
+    /// A ROM only cartridge of the given length with a valid header checksum
+    fn rom_with_size(length: usize, rom_size_code: u8) -> Vec<u8> {
+        let mut rom = rom_with_code(&[], 0x00);
+        rom.resize(length, 0);
+        rom[0x0148] = rom_size_code;
+        fix_checksum(&mut rom);
+        rom
+    }
+
+    fn fix_checksum(rom: &mut [u8]) {
+        rom[0x014D] = rom[0x0134..=0x014C]
+            .iter()
+            .fold(0u8, |checksum, byte| checksum.wrapping_sub(*byte).wrapping_sub(1));
+    }
+
+    fn load(rom: Vec<u8>, load_mode: LoadMode) -> Result<GameBoy, CartridgeError> {
+        let config = GameBoyConfig {
+            load_mode,
+            model: Some(HardwareModel::Dmg),
+            ..Default::default()
+        };
+        GameBoy::with_config("cartridge.gb", rom, config).map_err(|error| match error {
+            GameBoyError::CartridgeError(error) => error,
+            error => panic!("Unexpected error: {}", error),
+        })
+    }
+
+    fn strict_error(rom: Vec<u8>) -> CartridgeError {
+        load(rom, LoadMode::Strict).err().expect("Strict load should fail")
+    }
+
+    fn lenient(rom: Vec<u8>) -> GameBoy {
+        load(rom, LoadMode::Lenient).expect("Lenient load should succeed")
+    }
+
+    /// An MBC5 cartridge where every bank starts with its own number. The code switches to the selected bank and
+    /// sets BGP to black when the expected bank number was read back, to white otherwise
+    fn banked_rom(banks: usize, rom_size_code: u8, selected: u8, expected: u8) -> Vec<u8> {
+        // LD A, selected; LD (0x2000), A; LD A, (0x4000); CP expected; LD A, 0xFF; JR Z, +1; XOR A; LDH (BGP), A; JR -2
+        let code = [
+            0x3E, selected, 0xEA, 0x00, 0x20, 0xFA, 0x00, 0x40, 0xFE, expected, 0x3E, 0xFF, 0x28, 0x01, 0xAF, 0xE0, 0x47, 0x18, 0xFE,
+        ];
+        let mut rom = rom_with_code(&code, 0x00);
+        rom.resize(banks * BANK_SIZE, 0);
+        for bank in 0..banks {
+            rom[bank * BANK_SIZE] = bank as u8;
+        }
+        rom[0x0147] = 0x19;
+        rom[0x0148] = rom_size_code;
+        fix_checksum(&mut rom);
+        rom
+    }
+
+    fn run(rom: Vec<u8>) -> GameBoy {
+        let mut game_boy = lenient(rom);
+        for _ in 0..4 {
+            game_boy.run_until_frame_complete();
+        }
+        game_boy
+    }
+
+    /// Compared with bank 1, which every size maps to itself
+    fn reads_bank(banks: usize, rom_size_code: u8, selected: u8, expected: u8) -> bool {
+        let game_boy = run(banked_rom(banks, rom_size_code, selected, expected));
+        let control = run(banked_rom(banks, rom_size_code, 1, 1));
+        game_boy.current_frame() == control.current_frame()
+    }
+
+    #[test]
+    fn valid_rom_has_no_warnings() {
+        assert!(load(rom_with_size(0x8000, 0x00), LoadMode::Strict).is_ok());
+        assert!(lenient(rom_with_size(0x8000, 0x00)).load_warnings().is_empty());
+    }
+
+    #[test]
+    fn truncated_header() {
+        for load_mode in [LoadMode::Strict, LoadMode::Lenient] {
+            let error = load(vec![0; 0x0100], load_mode).err().expect("A ROM without a header can't load");
+            assert!(matches!(
+                error,
+                CartridgeError::TruncatedRom {
+                    expected: 0x0150,
+                    actual: 0x0100
+                }
+            ));
+        }
+    }
+
+    #[test]
+    fn truncated_rom() {
+        let rom = rom_with_size(0x8000, 0x01);
+        assert!(matches!(
+            strict_error(rom.clone()),
+            CartridgeError::TruncatedRom {
+                expected: 0x10000,
+                actual: 0x8000
+            }
+        ));
+        assert!(matches!(
+            lenient(rom).load_warnings().as_slice(),
+            [CartridgeError::TruncatedRom {
+                expected: 0x10000,
+                actual: 0x8000
+            }]
+        ));
+    }
+
+    #[test]
+    fn oversized_rom() {
+        let rom = rom_with_size(0x10000, 0x00);
+        assert!(matches!(
+            strict_error(rom.clone()),
+            CartridgeError::OversizedRom {
+                expected: 0x8000,
+                actual: 0x10000
+            }
+        ));
+        assert!(matches!(
+            lenient(rom).load_warnings().as_slice(),
+            [CartridgeError::OversizedRom {
+                expected: 0x8000,
+                actual: 0x10000
+            }]
+        ));
+    }
+
+    #[test]
+    fn unpadded_rom() {
+        let rom = rom_with_size(0x8100, 0x00);
+        assert!(matches!(
+            strict_error(rom.clone()),
+            CartridgeError::OversizedRom {
+                expected: 0x8000,
+                actual: 0x8100
+            }
+        ));
+        assert!(matches!(
+            lenient(rom).load_warnings().as_slice(),
+            [
+                CartridgeError::OversizedRom {
+                    expected: 0x8000,
+                    actual: 0x8100
+                },
+                CartridgeError::UnpaddedRom(0x8100)
+            ]
+        ));
+    }
+
+    #[test]
+    fn invalid_rom_size() {
+        let rom = rom_with_size(0x8000, 0x20);
+        assert!(matches!(strict_error(rom.clone()), CartridgeError::InvalidRomSize(0x20)));
+        assert!(matches!(lenient(rom).load_warnings().as_slice(), [CartridgeError::InvalidRomSize(0x20)]));
+    }
+
+    #[test]
+    fn checksum_failure() {
+        let mut rom = rom_with_size(0x8000, 0x00);
+        let expected = rom[0x014D].wrapping_add(1);
+        rom[0x014D] = expected;
+        let actual = expected.wrapping_sub(1);
+        assert!(matches!(
+            strict_error(rom.clone()),
+            CartridgeError::CheckSumFailure { expected: e, actual: a } if e == expected && a == actual
+        ));
+        assert!(matches!(
+            lenient(rom).load_warnings().as_slice(),
+            [CartridgeError::CheckSumFailure { expected: e, actual: a }] if *e == expected && *a == actual
+        ));
+    }
+
+    #[test]
+    fn lenient_mode_collects_every_warning() {
+        let mut rom = rom_with_size(0x6100, 0x20);
+        rom[0x014D] ^= 0xFF;
+        assert!(matches!(
+            lenient(rom).load_warnings().as_slice(),
+            [
+                CartridgeError::CheckSumFailure { .. },
+                CartridgeError::InvalidRomSize(0x20),
+                CartridgeError::UnpaddedRom(0x6100)
+            ]
+        ));
+    }
+
+    #[test]
+    fn banks_mirror_on_power_of_two_sizes() {
+        // 4 banks, only the low 2 bits of the bank number are wired
+        assert!(reads_bank(4, 0x01, 3, 3));
+        assert!(reads_bank(4, 0x01, 6, 2));
+    }
+
+    #[test]
+    fn banks_mirror_on_three_banks() {
+        // An unknown size code keeps the 3 banks that are present, bank 3 wraps back to 0
+        assert!(reads_bank(3, 0xFF, 2, 2));
+        assert!(reads_bank(3, 0xFF, 3, 0));
+        assert!(reads_bank(3, 0xFF, 5, 1));
+        assert!(!reads_bank(3, 0xFF, 5, 2));
+    }
+
+    #[test]
+    fn banks_mirror_on_72_banks() {
+        assert!(reads_bank(72, 0x52, 71, 71));
+        // Masked to 7 bits first, then the missing top of the 128 bank range wraps around
+        assert!(reads_bank(72, 0x52, 100, 28));
+        assert!(reads_bank(72, 0x52, 200, 0));
+        assert!(reads_bank(72, 0x52, 210, 10));
+    }
+}
@@ -0,0 +1,149 @@
+use ironboy_core::{GameBoy, GameBoyConfig, HardwareModel, LoadMode, PalettePreset, PixelFormat};
+
+use crate::{models::rom_with_bg_shade, support::rom_with_code};
+
+/// Runs a DMG with every BG pixel in shade 2 of the grayscale preset
+pub fn render(pixel_format: PixelFormat) -> GameBoy {
+    let config = GameBoyConfig {
+        load_mode: LoadMode::Lenient,
+        model: Some(HardwareModel::Dmg),
+        pixel_format,
+        ..Default::default()
+    };
+    let mut game_boy = GameBoy::with_config("frames.gb", rom_with_bg_shade(0x00, 2), config).expect("Unable to load test rom");
+    game_boy.set_dmg_palette(PalettePreset::Grayscale.palette());
+    game_boy.run_until_frame_complete();
+    game_boy.run_until_frame_complete();
+    game_boy
+}
+
+/// Runs the code placed after the header, DMG models use the grayscale preset
+pub fn run_code(code: &[u8], model: HardwareModel) -> GameBoy {
+    let mut game_boy = GameBoy::with_config(
+        "frames.gb",
+        rom_with_code(code, 0x00),
+        GameBoyConfig {
+            load_mode: LoadMode::Lenient,
+            model: Some(model),
+            ..Default::default()
+        },
+    )
+    .expect("Unable to load test rom");
+    game_boy.set_dmg_palette(PalettePreset::Grayscale.palette());
+    game_boy
+}
+
+#[cfg(test)]
+mod tests {
+    use ironboy_core::{FRAME_T_CYCLES, VIEWPORT_HEIGHT, VIEWPORT_WIDTH};
+
+    use super::*;
+    use crate::models::first_pixel;
+
+    #[test]
+    fn frames_within_a_budget() {
+        let frame = FRAME_T_CYCLES as u64;
+        let mut game_boy = run_code(&[0x18, 0xFE], HardwareModel::Dmg);
+        game_boy.run_until_frame_complete();
+        assert_eq!(game_boy.run_until_frame_complete_within(frame), Some(frame));
+        let start = game_boy.t_cycles();
+        assert_eq!(game_boy.run_until_frame_complete_within(1000), None);
+        // The instruction running when the budget ran out finishes
+        let spent = game_boy.t_cycles() - start;
+        assert!((1000..1012).contains(&spent), "{}", spent);
+        assert_eq!(game_boy.run_until_frame_complete_within(frame), Some(frame - spent));
+    }
+
+    #[test]
+    fn frame_sizes() {
+        for (pixel_format, bytes_per_pixel) in [
+            (PixelFormat::Rgba8888, 4),
+            (PixelFormat::Argb8888, 4),
+            (PixelFormat::Rgb565, 2),
+            (PixelFormat::Indexed, 2),
+        ] {
+            let game_boy = render(pixel_format);
+            assert_eq!(game_boy.pixel_format(), pixel_format);
+            assert_eq!(game_boy.current_frame().len(), VIEWPORT_WIDTH * VIEWPORT_HEIGHT * bytes_per_pixel);
+        }
+    }
+
+    #[test]
+    fn packed_formats() {
+        let (r, g, b) = PalettePreset::Grayscale.palette().bg[2];
+
+        let game_boy = render(PixelFormat::Rgba8888);
+        let frame = game_boy.current_frame();
+        assert_eq!(u32::from_ne_bytes(frame[0..4].try_into().unwrap()), u32::from_be_bytes([r, g, b, 0xFF]));
+
+        let game_boy = render(PixelFormat::Argb8888);
+        let frame = game_boy.current_frame();
+        assert_eq!(u32::from_ne_bytes(frame[0..4].try_into().unwrap()), u32::from_be_bytes([0xFF, r, g, b]));
+
+        let game_boy = render(PixelFormat::Rgb565);
+        let frame = game_boy.current_frame();
+        let expected = (r as u16 >> 3) << 11 | (g as u16 >> 2) << 5 | b as u16 >> 3;
+        assert_eq!(u16::from_ne_bytes(frame[0..2].try_into().unwrap()), expected);
+    }
+
+    #[test]
+    fn indexed_format_ignores_palette() {
+        // The tiles are empty so every pixel is color 0 of BG palette 0, whatever BGP maps it to
+        let game_boy = render(PixelFormat::Indexed);
+        assert!(game_boy.current_frame().iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn swap_frame() {
+        let mut game_boy = render(PixelFormat::Rgba8888);
+        let expected = game_boy.current_frame().to_vec();
+
+        let mut buffer = Vec::new();
+        assert!(game_boy.swap_frame(&mut buffer));
+        assert_eq!(buffer, expected);
+        assert!(game_boy.current_frame().is_empty());
+
+        // Nothing new to hand over until the next frame finishes
+        assert!(!game_boy.swap_frame(&mut buffer));
+        assert_eq!(buffer, expected);
+
+        // The caller's buffers are rendered into, so the same three allocations keep rotating
+        let mut handed = vec![buffer.as_ptr()];
+        for _ in 0..3 {
+            game_boy.run_until_frame_complete();
+            assert_eq!(game_boy.current_frame(), expected);
+            assert!(game_boy.swap_frame(&mut buffer));
+            assert_eq!(buffer, expected);
+            handed.push(buffer.as_ptr());
+        }
+        assert_eq!(handed[3], handed[0]);
+
+        game_boy.set_pixel_format(PixelFormat::Rgb565);
+        game_boy.run_until_frame_complete();
+        assert!(game_boy.swap_frame(&mut buffer));
+        assert_eq!(buffer.len(), PixelFormat::Rgb565.frame_size());
+    }
+
+    #[test]
+    fn frames_continue_while_lcd_is_off() {
+        // LDH (LCDC), 0; JR -2
+        let mut game_boy = run_code(&[0xAF, 0xE0, 0x40, 0x18, 0xFE], HardwareModel::Dmg);
+        for _ in 0..3 {
+            game_boy.run_until_frame_complete();
+            assert_eq!(first_pixel(&game_boy), PalettePreset::Grayscale.palette().bg[0]);
+        }
+    }
+
+    #[test]
+    fn first_frame_after_enable_is_hidden() {
+        // LDH (LCDC), 0; LDH (LCDC), 0x91; LDH (BGP), 0xFF; JR -2
+        let mut game_boy = run_code(
+            &[0xAF, 0xE0, 0x40, 0x3E, 0x91, 0xE0, 0x40, 0x3E, 0xFF, 0xE0, 0x47, 0x18, 0xFE],
+            HardwareModel::Dmg,
+        );
+        game_boy.run_until_frame_complete();
+        assert_eq!(first_pixel(&game_boy), PalettePreset::Grayscale.palette().bg[0]);
+        game_boy.run_until_frame_complete();
+        assert_eq!(first_pixel(&game_boy), PalettePreset::Grayscale.palette().bg[3]);
+    }
+}
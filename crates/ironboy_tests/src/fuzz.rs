@@ -0,0 +1,176 @@
+use ironboy_core::{FRAME_T_CYCLES, GameBoy, GameBoyConfig, LoadMode};
+
+use crate::support::rom_with_code;
+
+/// Frames run per input, shared with the `run_frames` fuzz target
+pub const FRAME_BUDGET: u32 = 4;
+const CARTRIDGE_TYPES: [u8; 10] = [0x00, 0x01, 0x02, 0x05, 0x11, 0x12, 0x19, 0x1A, 0x1C, 0x1D];
+const ROM_SIZES: [u8; 13] = [0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x52, 0x53, 0x54, 0xFF];
+
+/// xorshift64*, good enough to spread inputs around and trivially reproducible from a seed
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed.max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    pub fn byte(&mut self) -> u8 {
+        self.next_u64() as u8
+    }
+
+    pub fn pick<T: Copy>(&mut self, values: &[T]) -> T {
+        values[self.below(values.len())]
+    }
+
+    pub fn fill(&mut self, buffer: &mut [u8]) {
+        buffer.iter_mut().for_each(|byte| *byte = self.byte());
+    }
+}
+
+/// The `cartridge_load` fuzz target
+pub fn load_cartridge(data: &[u8]) {
+    for load_mode in [LoadMode::Strict, LoadMode::Lenient] {
+        let _ = GameBoy::with_config(
+            "fuzz.gb",
+            data.to_vec(),
+            GameBoyConfig {
+                load_mode,
+                ..Default::default()
+            },
+        );
+    }
+}
+
+/// The `run_frames` fuzz target
+pub fn run_frames(data: &[u8]) {
+    let config = GameBoyConfig {
+        load_mode: LoadMode::Lenient,
+        ..Default::default()
+    };
+    let Ok(mut game_boy) = GameBoy::with_config("fuzz.gb", data.to_vec(), config) else {
+        return;
+    };
+
+    // Frames keep coming with the LCD off, halted or locked up, so each one has to arrive within two frames of cycles
+    for frame in 0..FRAME_BUDGET {
+        let elapsed = game_boy.run_until_frame_complete_within(2 * FRAME_T_CYCLES as u64);
+        assert!(elapsed.is_some_and(|elapsed| elapsed > 0), "Frame {} did not complete", frame);
+    }
+}
+
+/// Random code behind a header the loader accepts, so most inputs reach the CPU and MBCs
+pub fn random_rom(rng: &mut Rng) -> Vec<u8> {
+    let mut rom = rom_with_code(&[], 0x00);
+    rng.fill(&mut rom[..0x0100]);
+    rng.fill(&mut rom[0x0104..]);
+    rom[0x0143] = rng.pick(&[0x00, 0x80, 0xC0]);
+    rom[0x0147] = rng.pick(&CARTRIDGE_TYPES);
+    rom[0x0148] = rng.pick(&ROM_SIZES);
+    rom[0x0149] = rng.below(6) as u8;
+    rom
+}
+
+pub fn mutate(rng: &mut Rng, seed: &[u8]) -> Vec<u8> {
+    let mut input = seed.to_vec();
+    for _ in 0..=rng.below(16) {
+        match rng.below(4) {
+            0 if !input.is_empty() => {
+                let index = rng.below(input.len());
+                input[index] ^= 1 << rng.below(8);
+            }
+            1 if !input.is_empty() => {
+                let index = rng.below(input.len());
+                input[index] = rng.byte();
+            }
+            2 => input.truncate(rng.below(input.len() + 1)),
+            _ => {
+                let extra = rng.below(0x100);
+                input.extend((0..extra).map(|_| rng.byte()));
+            }
+        }
+    }
+    input
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, panic};
+
+    use super::*;
+
+    const CORPUS_DIRECTORIES: [&str; 2] = ["../../fuzz/corpus/cartridge_load", "../../fuzz/corpus/run_frames"];
+    const DEFAULT_SEED: u64 = 0x1B0A_2D5E_C0DE_F00D;
+    const DEFAULT_ITERATIONS: usize = 48;
+
+    fn read_corpus() -> Vec<(String, Vec<u8>)> {
+        let mut corpus = Vec::new();
+        for directory in CORPUS_DIRECTORIES {
+            let mut files: Vec<_> = fs::read_dir(directory)
+                .expect("Unable to read corpus directory")
+                .collect::<Result<Vec<_>, _>>()
+                .expect("Unable to collect corpus files");
+            files.sort_by_key(|file| file.path());
+
+            for file in files {
+                let data = fs::read(file.path()).expect("Unable to read corpus file");
+                corpus.push((file.path().display().to_string(), data));
+            }
+        }
+        corpus
+    }
+
+    fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+        env::var(name).ok().and_then(|value| value.parse().ok()).unwrap_or(default)
+    }
+
+    fn check(label: &str, data: &[u8]) {
+        let result = panic::catch_unwind(|| {
+            load_cartridge(data);
+            run_frames(data);
+        });
+        assert!(result.is_ok(), "Fuzz input {} panicked", label);
+    }
+
+    #[test]
+    fn fuzz_corpus() {
+        for (name, data) in read_corpus() {
+            check(&name, &data);
+        }
+    }
+
+    #[test]
+    fn fuzz_seeded() {
+        let seed = env_or("IRONBOY_FUZZ_SEED", DEFAULT_SEED);
+        let iterations = env_or("IRONBOY_FUZZ_ITERATIONS", DEFAULT_ITERATIONS);
+        let corpus = read_corpus();
+        let mut rng = Rng::new(seed);
+
+        for iteration in 0..iterations {
+            let input = match iteration % 3 {
+                0 => random_rom(&mut rng),
+                1 => {
+                    let seed_input = &corpus[rng.below(corpus.len())].1;
+                    mutate(&mut rng, seed_input)
+                }
+                _ => {
+                    let mut input = vec![0; rng.below(0x400)];
+                    rng.fill(&mut input);
+                    input
+                }
+            };
+            check(&format!("{} (seed {:#X})", iteration, seed), &input);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use ironboy_core::{GameBoyConfig, gbs::GbsPlayer};
+
+const LOAD_ADDRESS: u16 = 0x0400;
+const INIT_ADDRESS: u16 = 0x0400;
+const PLAY_ADDRESS: u16 = 0x0420;
+
+/// Three songs, init plays pulse 1 at 131072 / (256 - song) Hz and play counts its calls in the first wave RAM byte
+pub fn gbs_file(timer_modulo: u8, timer_control: u8) -> Vec<u8> {
+    let mut file = vec![0; 0x70];
+    file[0x00..0x04].copy_from_slice(b"GBS\x01");
+    // 3 songs, starting at the second one
+    file[0x04] = 3;
+    file[0x05] = 2;
+    file[0x06..0x08].copy_from_slice(&LOAD_ADDRESS.to_le_bytes());
+    file[0x08..0x0A].copy_from_slice(&INIT_ADDRESS.to_le_bytes());
+    file[0x0A..0x0C].copy_from_slice(&PLAY_ADDRESS.to_le_bytes());
+    file[0x0C..0x0E].copy_from_slice(&0xFFFEu16.to_le_bytes());
+    file[0x0E] = timer_modulo;
+    file[0x0F] = timer_control;
+    file[0x10..0x15].copy_from_slice(b"Tones");
+    file[0x30..0x36].copy_from_slice(b"Author");
+
+    let mut data = vec![0; (PLAY_ADDRESS - LOAD_ADDRESS) as usize];
+    // LD B,A; NR52 on, NR50 full volume, NR51 pulse 1 on both sides, NR12 volume 15; NR13 the song, NR14 trigger; RET
+    let init = [
+        0x47, 0x3E, 0x80, 0xE0, 0x26, 0x3E, 0x77, 0xE0, 0x24, 0x3E, 0x11, 0xE0, 0x25, 0x3E, 0xF0, 0xE0, 0x12, 0x78, 0xE0, 0x13, 0x3E, 0x87, 0xE0,
+        0x14, 0xC9,
+    ];
+    data[..init.len()].copy_from_slice(&init);
+    // LD HL,0xFF30; INC (HL); RET
+    data.extend([0x21, 0x30, 0xFF, 0x34, 0xC9]);
+    file.extend(data);
+    file
+}
+
+pub fn play_count(player: &GbsPlayer) -> u8 {
+    let wave_ram = player.game_boy().channel_views()[2].wave_ram.unwrap();
+    wave_ram[0] << 4 | wave_ram[1]
+}
+
+pub fn start(file: &[u8]) -> GbsPlayer {
+    GbsPlayer::new("tones.gbs", file, GameBoyConfig::default()).expect("Unable to load GBS file")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ironboy_core::{
+        AudioChannel,
+        gbs::{Gbs, GbsError},
+    };
+
+    fn pulse_frequency(player: &GbsPlayer) -> f64 {
+        player.game_boy().channel_views()[0].frequency
+    }
+
+    #[test]
+    fn gbs_header() {
+        let gbs = Gbs::parse(&gbs_file(0x00, 0x06)).unwrap();
+        assert_eq!(gbs.songs(), 3);
+        assert_eq!(gbs.first_song(), 1);
+        assert_eq!(gbs.load_address(), LOAD_ADDRESS);
+        assert_eq!(gbs.play_address(), PLAY_ADDRESS);
+        assert_eq!(gbs.stack_pointer(), 0xFFFE);
+        assert_eq!(gbs.title(), "Tones");
+        assert_eq!(gbs.author(), "Author");
+        assert_eq!(gbs.copyright(), "");
+        assert!(gbs.uses_timer());
+        assert!(!gbs.double_speed());
+
+        let mut file = gbs_file(0x00, 0x00);
+        file[0x06..0x08].copy_from_slice(&0x0200u16.to_le_bytes());
+        assert!(matches!(Gbs::parse(&file), Err(GbsError::InvalidLoadAddress(0x0200))));
+        file[0x04] = 0;
+        assert!(matches!(Gbs::parse(&file), Err(GbsError::NoSongs)));
+        assert!(matches!(Gbs::parse(&file[..0x40]), Err(GbsError::Truncated)));
+        assert!(matches!(Gbs::parse(b"NES\x1A"), Err(GbsError::InvalidSignature)));
+    }
+
+    #[test]
+    fn gbs_tracks_play_on_vblank() {
+        let mut player = start(&gbs_file(0x00, 0x00));
+        player.game_boy_mut().run_until_frame_complete();
+        assert_eq!(player.track(), 1);
+        assert!((pulse_frequency(&player) - 131072.0 / 255.0).abs() < 0.01);
+
+        let count = play_count(&player);
+        for _ in 0..20 {
+            player.game_boy_mut().run_until_frame_complete();
+        }
+        assert_eq!(play_count(&player).wrapping_sub(count), 20);
+
+        player.game_boy_mut().set_channel_muted(AudioChannel::Pulse1, true);
+        player.next_track().unwrap();
+        player.game_boy_mut().run_until_frame_complete();
+        assert_eq!(player.track(), 2);
+        assert!((pulse_frequency(&player) - 131072.0 / 254.0).abs() < 0.01);
+        assert!(player.game_boy().channel_muted(AudioChannel::Pulse1));
+
+        player.next_track().unwrap();
+        assert_eq!(player.track(), 0);
+        player.previous_track().unwrap();
+        assert_eq!(player.track(), 2);
+        assert!(player.elapsed_seconds() < 0.01);
+    }
+
+    #[test]
+    fn gbs_play_on_the_timer() {
+        // 65536 Hz counting from 0 overflows 256 times a second
+        let mut player = start(&gbs_file(0x00, 0x06));
+        player.game_boy_mut().run_until_frame_complete();
+        let count = play_count(&player);
+        for _ in 0..30 {
+            player.game_boy_mut().run_until_frame_complete();
+        }
+        let calls = play_count(&player).wrapping_sub(count) as f64;
+        let expected = 256.0 * player.elapsed_seconds() - 256.0 * (1.0 / 59.7275);
+        assert!((calls - expected).abs() <= 2.0, "{} calls, expected {}", calls, expected);
+    }
+
+    #[test]
+    fn gbs_fade_out() {
+        let peak = |samples: &[f32]| samples.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        let mut player = start(&gbs_file(0x00, 0x00));
+        player.run_until_audio_buffer_full();
+        let (left, _) = player.run_until_audio_buffer_full();
+        let full_volume = peak(left);
+        assert!(!player.fading());
+
+        player.fade_out(0.05);
+        let (left, _) = player.run_until_audio_buffer_full();
+        assert!(peak(&left[left.len() / 2..]) < 0.9 * full_volume);
+        while !player.fade_finished() {
+            player.run_until_audio_buffer_full();
+        }
+        let (left, right) = player.run_until_audio_buffer_full();
+        assert!(left.iter().chain(right).all(|sample| *sample == 0.0));
+
+        player.select_track(0).unwrap();
+        assert!(!player.fading());
+    }
+}
@@ -0,0 +1,112 @@
+use ironboy_core::{GameBoy, HardwareModel, RenderOptions};
+
+use crate::{
+    frames::run_code,
+    window::{frame_loop_code, run_frames, window_setup, write_register},
+};
+
+/// Puts 11 black objects on lines 50-57, object `i` starting at X = 10 * i
+pub fn eleven_objects() -> Vec<u8> {
+    // LD HL, 0xFE00; LD C, 8
+    let mut code = vec![0x21, 0x00, 0xFE, 0x0E, 0x08];
+    // LD A, 66; LD (HL+), A; LD A, C; LD (HL+), A; ADD 10; LD C, A; LD A, 1; LD (HL+), A; XOR A; LD (HL+), A;
+    // LD A, L; CP 44; JR NZ, -18
+    code.extend([
+        0x3E, 0x42, 0x22, 0x79, 0x22, 0xC6, 0x0A, 0x4F, 0x3E, 0x01, 0x22, 0xAF, 0x22, 0x7D, 0xFE, 0x2C, 0x20, 0xEE,
+    ]);
+    code
+}
+
+/// Runs a test program with the window at WX=47 and WY=16, the given LCDC and the objects from `eleven_objects`
+pub fn run_with_options(lcdc: u8, render_options: RenderOptions) -> GameBoy {
+    // OAM is written with the LCD off so the writes cannot run into OAM scan
+    let mut setup = vec![0xF3, 0xAF, 0xE0, 0x40];
+    setup.extend(eleven_objects());
+    setup.extend(window_setup(47, 16, false));
+    let code = frame_loop_code(setup, &write_register(0x40, lcdc));
+    let mut game_boy = run_code(&code, HardwareModel::Dmg);
+    game_boy.set_render_options(render_options);
+    run_frames(&mut game_boy);
+    game_boy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::pixel,
+        window::{BLACK, WHITE},
+    };
+
+    const MAGENTA: (u8, u8, u8) = (255, 0, 255);
+
+    #[test]
+    fn hide_background() {
+        // BG uses the black tile map
+        let game_boy = run_with_options(0x99, RenderOptions::default());
+        assert_eq!(pixel(&game_boy, 0, 0), BLACK);
+
+        let render_options = RenderOptions {
+            show_background: false,
+            ..Default::default()
+        };
+        let game_boy = run_with_options(0x99, render_options);
+        assert_eq!(pixel(&game_boy, 0, 0), WHITE);
+    }
+
+    #[test]
+    fn hide_window() {
+        let game_boy = run_with_options(0xF1, RenderOptions::default());
+        assert_eq!(pixel(&game_boy, 40, 16), BLACK);
+
+        let render_options = RenderOptions {
+            show_window: false,
+            ..Default::default()
+        };
+        let game_boy = run_with_options(0xF1, render_options);
+        assert_eq!(pixel(&game_boy, 40, 16), WHITE);
+    }
+
+    #[test]
+    fn hide_objects() {
+        let game_boy = run_with_options(0x93, RenderOptions::default());
+        assert_eq!(pixel(&game_boy, 50, 50), BLACK);
+
+        let render_options = RenderOptions {
+            show_objects: false,
+            ..Default::default()
+        };
+        let game_boy = run_with_options(0x93, render_options);
+        assert_eq!(pixel(&game_boy, 50, 50), WHITE);
+    }
+
+    #[test]
+    fn object_bounding_boxes() {
+        let render_options = RenderOptions {
+            show_objects: false,
+            object_bounding_boxes: true,
+            ..Default::default()
+        };
+        let game_boy = run_with_options(0x93, render_options);
+        assert_eq!(pixel(&game_boy, 50, 50), MAGENTA);
+        assert_eq!(pixel(&game_boy, 53, 53), WHITE);
+        assert_eq!(pixel(&game_boy, 57, 53), MAGENTA);
+        assert_eq!(pixel(&game_boy, 53, 57), MAGENTA);
+        // Boxes only cover the objects the PPU selected
+        assert_eq!(pixel(&game_boy, 100, 50), WHITE);
+    }
+
+    #[test]
+    fn unlimited_objects() {
+        let game_boy = run_with_options(0x93, RenderOptions::default());
+        assert_eq!(pixel(&game_boy, 90, 50), BLACK);
+        assert_eq!(pixel(&game_boy, 100, 50), WHITE);
+
+        let render_options = RenderOptions {
+            unlimited_objects: true,
+            ..Default::default()
+        };
+        let game_boy = run_with_options(0x93, render_options);
+        assert_eq!(pixel(&game_boy, 100, 50), BLACK);
+    }
+}
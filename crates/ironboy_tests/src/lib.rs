@@ -1,3 +1,17 @@
+pub mod audio;
+pub mod cartridge;
+pub mod frames;
+pub mod fuzz;
+pub mod gbs;
+pub mod layers;
+pub mod lockup;
+pub mod models;
+pub mod mooneye;
+pub mod stat;
+pub mod support;
+pub mod viewers;
+pub mod window;
+
 use ironboy_core::cpu::MemoryInterface;
 
 use serde::{Deserialize, Serialize};
@@ -75,7 +89,7 @@ struct Test {
 #[cfg(test)]
 mod tests {
     use ironboy_core::{
-        GbMode,
+        GbMode, HardwareModel,
         cpu::{Cpu, instructions::Instruction},
     };
     use std::{cell::RefCell, fs, rc::Rc};
@@ -97,7 +111,7 @@ mod tests {
                 let inital_state = test.initial;
                 let final_state = test.r#final;
 
-                let mut cpu = Cpu::new(SimpleBus::new(), GbMode::Monochrome, Rc::new(RefCell::new(false)));
+                let mut cpu = Cpu::new(SimpleBus::new(), HardwareModel::Dmg, GbMode::Monochrome, Rc::new(RefCell::new(false)));
 
                 cpu.registers_mut().set_pc(inital_state.pc);
                 cpu.registers_mut().set_sp(inital_state.sp);
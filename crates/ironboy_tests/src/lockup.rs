@@ -0,0 +1,77 @@
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use ironboy_core::{
+        GameBoy, GameBoyConfig, GbMode, HardwareModel, LoadMode,
+        cpu::{Cpu, Lockup, MemoryInterface},
+    };
+
+    use crate::{SimpleBus, support::rom_with_code};
+
+    const ILLEGAL_OPCODES: [u8; 11] = [0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD];
+
+    /// Enables the VBlank interrupt, whose handler sets BGP to black, then executes the opcode and spins
+    fn lockup_rom(opcode: u8) -> Vec<u8> {
+        // LD A, 1; LDH (IE), A; EI; NOP; opcode; JR -2
+        let mut rom = rom_with_code(&[0x3E, 0x01, 0xE0, 0xFF, 0xFB, 0x00, opcode, 0x18, 0xFE], 0x00);
+        rom[0x0040..0x0046].copy_from_slice(&[0x3E, 0xFF, 0xE0, 0x47, 0x18, 0xFE]);
+        rom
+    }
+
+    /// A CPU on a flat bus about to execute a NOP followed by the opcode
+    fn cpu_before(opcode: u8) -> Cpu<SimpleBus> {
+        let mut cpu = Cpu::new(SimpleBus::new(), HardwareModel::Dmg, GbMode::Monochrome, Rc::new(RefCell::new(false)));
+        cpu.bus_mut().store_8(0x0100, 0x00, false);
+        cpu.bus_mut().store_8(0x0101, opcode, false);
+        cpu.registers_mut().set_pc(0x0100);
+        cpu.fetch_instruction();
+        cpu
+    }
+
+    fn run(opcode: u8) -> GameBoy {
+        let config = GameBoyConfig {
+            load_mode: LoadMode::Lenient,
+            model: Some(HardwareModel::Dmg),
+            ..Default::default()
+        };
+        let mut game_boy = GameBoy::with_config("lockup.gb", lockup_rom(opcode), config).expect("Unable to load test rom");
+        assert_eq!(game_boy.lockup(), None);
+        // Frames keep coming while the CPU is locked
+        for _ in 0..4 {
+            assert!(game_boy.run_until_frame_complete());
+        }
+        game_boy
+    }
+
+    #[test]
+    fn illegal_opcodes_stop_the_program_counter() {
+        for opcode in ILLEGAL_OPCODES {
+            let mut cpu = cpu_before(opcode);
+            cpu.cycle();
+            cpu.cycle();
+            assert_eq!(cpu.lockup(), Some(Lockup { opcode, address: 0x0101 }), "{:#04X}", opcode);
+
+            let pc = cpu.registers().pc();
+            let m_cycles = cpu.bus().total_m_cycles();
+            for _ in 0..100 {
+                cpu.cycle();
+            }
+            assert_eq!(cpu.registers().pc(), pc, "{:#04X}", opcode);
+            // The clock keeps running while the CPU is locked
+            assert_eq!(cpu.bus().total_m_cycles(), m_cycles + 100, "{:#04X}", opcode);
+        }
+    }
+
+    #[test]
+    fn game_boy_reports_lockups() {
+        let unlocked = run(0x00);
+        assert_eq!(unlocked.lockup(), None);
+        for opcode in ILLEGAL_OPCODES {
+            let game_boy = run(opcode);
+            assert_eq!(game_boy.lockup(), Some(Lockup { opcode, address: 0x0156 }), "{:#04X}", opcode);
+            // VBlank was requested while locked, but the handler never ran
+            assert_ne!(game_boy.current_frame(), unlocked.current_frame(), "{:#04X}", opcode);
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use ironboy_core::{GameBoy, GameBoyConfig, HardwareModel, LoadMode, VIEWPORT_WIDTH};
+
+use crate::support::rom_with_code;
+
+/// A cartridge with the given CGB flag that only spins
+pub fn rom_with_cgb_flag(cgb_flag: u8) -> Vec<u8> {
+    rom_with_code(&[0x18, 0xFE], cgb_flag)
+}
+
+/// Sets every BG pixel to the given shade and spins
+pub fn rom_with_bg_shade(cgb_flag: u8, shade: u8) -> Vec<u8> {
+    let bgp = shade * 0x55;
+    rom_with_code(&[0x3E, bgp, 0xE0, 0x47, 0x18, 0xFE], cgb_flag)
+}
+
+pub fn boot(rom: Vec<u8>, model: Option<HardwareModel>) -> GameBoy {
+    let config = GameBoyConfig {
+        load_mode: LoadMode::Lenient,
+        model,
+        ..Default::default()
+    };
+    GameBoy::with_config("model.gb", rom, config).expect("Unable to load test rom")
+}
+
+/// The color of a pixel in an RGBA8888 frame
+pub fn pixel(game_boy: &GameBoy, x: usize, y: usize) -> (u8, u8, u8) {
+    let start = (y * VIEWPORT_WIDTH + x) * 4;
+    let frame = &game_boy.current_frame()[start..start + 4];
+    let [r, g, b, _] = u32::from_ne_bytes([frame[0], frame[1], frame[2], frame[3]]).to_be_bytes();
+    (r, g, b)
+}
+
+pub fn first_pixel(game_boy: &GameBoy) -> (u8, u8, u8) {
+    pixel(game_boy, 0, 0)
+}
+
+/// Compares A, F, B, C, D, E, H and L with the expected values before anything else runs. A mismatch jumps
+/// straight to the end with the zero flag reset.
+pub fn register_check(expected: [u8; 8]) -> Vec<u8> {
+    let loads: [&[u8]; 8] = [
+        // PUSH AF; LD A, (0xFFFD), the stack now holds A and F
+        &[0xF5, 0xFA, 0xFD, 0xFF],
+        &[0xFA, 0xFC, 0xFF],
+        &[0x78],
+        &[0x79],
+        &[0x7A],
+        &[0x7B],
+        &[0x7C],
+        &[0x7D],
+    ];
+    let mut checks: Vec<Vec<u8>> = loads
+        .iter()
+        .zip(expected)
+        .map(|(load, value)| [*load, &[0xFE, value, 0x20, 0x00]].concat())
+        .collect();
+
+    let mut remaining = 0;
+    for check in checks.iter_mut().rev() {
+        let last = check.len() - 1;
+        check[last] = remaining;
+        remaining += check.len() as u8;
+    }
+    checks.concat()
+}
+
+/// Fills OAM with its own addresses while the LCD is off, then repeatedly points an IDU access at OAM for a
+/// few lines with the LCD on
+/// Fills OAM, turns the LCD on and repeats `access` with HL in OAM 256 times
+pub fn oam_bug_code(access: &[u8]) -> Vec<u8> {
+    let mut code = vec![
+        // DI; XOR A; LDH (LCDC), A; LD HL, 0xFE00
+        0xF3, 0xAF, 0xE0, 0x40, 0x21, 0x00, 0xFE, //
+        // LD A, L; LD (HL+), A; LD A, L; CP 0xA0; JR NZ, -7
+        0x7D, 0x22, 0x7D, 0xFE, 0xA0, 0x20, 0xF9, //
+        // LD A, 0x91; LDH (LCDC), A; LD HL, 0xFE00; LD B, 0
+        0x3E, 0x91, 0xE0, 0x40, 0x21, 0x00, 0xFE, 0x06, 0x00,
+    ];
+    code.extend(access);
+    // DEC B; JR NZ, back to the access; JR -2
+    code.extend([0x05, 0x20, (-(access.len() as i8) - 3) as u8, 0x18, 0xFE]);
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use ironboy_core::{ColorCorrection, GbMode, JoypadButton, PalettePreset, cpu::Cpu};
+
+    use super::*;
+    use crate::{
+        SimpleBus,
+        frames::run_code,
+        stat::{passed, report},
+    };
+
+    const MODELS: [HardwareModel; 5] = [
+        HardwareModel::Dmg,
+        HardwareModel::Mgb,
+        HardwareModel::Sgb,
+        HardwareModel::Cgb,
+        HardwareModel::Agb,
+    ];
+
+    /// Runs `access` through [`oam_bug_code`] and returns the OAM entries as (y, x, tile)
+    fn oam_after(access: &[u8], model: HardwareModel) -> Vec<(u8, u8, u8)> {
+        let mut game_boy = run_code(&oam_bug_code(access), model);
+        for _ in 0..2 {
+            game_boy.run_until_frame_complete();
+        }
+        game_boy.object_views().iter().map(|object| (object.y, object.x, object.tile)).collect()
+    }
+
+    #[test]
+    fn model_defaults_to_cartridge() {
+        assert_eq!(boot(rom_with_cgb_flag(0x00), None).model(), HardwareModel::Dmg);
+        assert_eq!(boot(rom_with_cgb_flag(0x80), None).model(), HardwareModel::Cgb);
+        assert_eq!(boot(rom_with_cgb_flag(0xC0), None).model(), HardwareModel::Cgb);
+    }
+
+    #[test]
+    fn dual_mode_cartridge_on_every_model() {
+        for model in MODELS {
+            let mut game_boy = boot(rom_with_cgb_flag(0x80), Some(model));
+            let expected = match model.is_color() {
+                true => GbMode::Color,
+                false => GbMode::Monochrome,
+            };
+            assert_eq!(game_boy.mode(), expected, "{:?}", model);
+            game_boy.run_until_frame_complete();
+        }
+    }
+
+    #[test]
+    fn monochrome_cartridge_on_color_models() {
+        for model in [HardwareModel::Cgb, HardwareModel::Agb] {
+            assert_eq!(boot(rom_with_cgb_flag(0x00), Some(model)).mode(), GbMode::ColorAsMonochrome);
+        }
+    }
+
+    #[test]
+    fn compatibility_palette_from_title() {
+        let mut rom = rom_with_bg_shade(0x00, 1);
+        rom[0x0134..0x013F].copy_from_slice(b"POKEMON RED");
+        rom[0x014B] = 0x01;
+
+        let mut game_boy = boot(rom, Some(HardwareModel::Cgb));
+        game_boy.run_until_frame_complete();
+        game_boy.run_until_frame_complete();
+        assert_eq!(first_pixel(&game_boy), (255, 132, 132));
+    }
+
+    #[test]
+    fn compatibility_palette_from_buttons() {
+        let mut game_boy = boot(rom_with_bg_shade(0x00, 0), Some(HardwareModel::Cgb));
+        assert!(game_boy.select_compatibility_palette(JoypadButton::Right, Some(JoypadButton::B)));
+        assert!(!game_boy.select_compatibility_palette(JoypadButton::A, None));
+        game_boy.run_until_frame_complete();
+        game_boy.run_until_frame_complete();
+        assert_eq!(first_pixel(&game_boy), (0, 0, 0));
+
+        let mut game_boy = boot(rom_with_bg_shade(0x00, 0), Some(HardwareModel::Dmg));
+        assert!(!game_boy.select_compatibility_palette(JoypadButton::Right, Some(JoypadButton::B)));
+    }
+
+    #[test]
+    fn dmg_palette_presets() {
+        for preset in PalettePreset::ALL {
+            let mut game_boy = boot(rom_with_bg_shade(0x00, 2), Some(HardwareModel::Dmg));
+            game_boy.set_dmg_palette(preset.palette());
+            game_boy.run_until_frame_complete();
+            game_boy.run_until_frame_complete();
+            assert_eq!(first_pixel(&game_boy), preset.palette().bg[2], "{}", preset.name());
+            assert_eq!(PalettePreset::from_name(preset.name()), Some(preset));
+        }
+    }
+
+    #[test]
+    fn color_correction() {
+        let expected = [(ColorCorrection::None, (255, 0, 0)), (ColorCorrection::Cgb, (201, 0, 46))];
+        for (color_correction, red) in expected {
+            let mut game_boy = boot(rom_with_bg_shade(0x00, 2), Some(HardwareModel::Cgb));
+            game_boy.select_compatibility_palette(JoypadButton::Down, Some(JoypadButton::A));
+            game_boy.set_color_correction(color_correction);
+            game_boy.run_until_frame_complete();
+            game_boy.run_until_frame_complete();
+            assert_eq!(first_pixel(&game_boy), red, "{}", color_correction.name());
+        }
+
+        for color_correction in ColorCorrection::ALL {
+            let lookup_table = color_correction.lookup_table();
+            assert_eq!(lookup_table.len(), 0x8000);
+            assert_eq!(lookup_table[0], (0, 0, 0), "{}", color_correction.name());
+        }
+        assert_eq!(ColorCorrection::ModernBalanced.lookup_table()[0x7FFF], (255, 255, 255));
+    }
+
+    #[test]
+    fn post_boot_registers() {
+        let expected = [
+            (HardwareModel::Dmg, [0x01, 0xB0, 0x00, 0x13, 0x00, 0xD8, 0x01, 0x4D]),
+            (HardwareModel::Mgb, [0xFF, 0xB0, 0x00, 0x13, 0x00, 0xD8, 0x01, 0x4D]),
+            (HardwareModel::Sgb, [0x01, 0x00, 0x00, 0x14, 0x00, 0x00, 0xC0, 0x60]),
+            (HardwareModel::Cgb, [0x11, 0x80, 0x00, 0x00, 0x00, 0x08, 0x00, 0x7C]),
+            (HardwareModel::Agb, [0x11, 0x00, 0x01, 0x00, 0x00, 0x08, 0x00, 0x7C]),
+        ];
+        for (model, registers) in expected {
+            let mut code = register_check(registers);
+            code.extend(report(true));
+            assert!(passed(&code, model), "{:?}", model);
+
+            // A single wrong value fails the check
+            let mut wrong = registers;
+            wrong[2] ^= 0x01;
+            let mut code = register_check(wrong);
+            code.extend(report(true));
+            assert!(!passed(&code, model), "{:?}", model);
+        }
+    }
+
+    #[test]
+    fn post_boot_registers_in_color_mode() {
+        let expected = [
+            (HardwareModel::Cgb, 0x1180, 0x0000, 0xFF56, 0x000D),
+            (HardwareModel::Agb, 0x1100, 0x0100, 0xFF56, 0x000D),
+        ];
+        for (model, af, bc, de, hl) in expected {
+            let cpu = Cpu::new(SimpleBus::new(), model, GbMode::Color, Rc::new(RefCell::new(false)));
+            let registers = cpu.registers();
+            assert_eq!(
+                (registers.af(), registers.bc(), registers.de(), registers.hl()),
+                (af, bc, de, hl),
+                "{:?}",
+                model
+            );
+            assert_eq!((registers.sp(), registers.pc()), (0xFFFE, 0x0100));
+        }
+    }
+
+    #[test]
+    fn undocumented_registers_only_on_color_models() {
+        for model in MODELS {
+            // FF72 and FF73 are plain read/write, FF74 only in CGB mode and only bits 4-6 of FF75
+            for (address, value, expected) in [
+                (0x72, 0x5A, 0x5A),
+                (0x73, 0xA5, 0xA5),
+                (0x74, 0x3C, 0xFF),
+                (0x75, 0x70, 0xFF),
+                (0x75, 0x00, 0x8F),
+            ] {
+                let expected = if model.is_color() { expected } else { 0xFF };
+                // LD A, value; LDH (address), A; LDH A, (address); CP expected
+                let mut code = vec![0x3E, value, 0xE0, address, 0xF0, address, 0xFE, expected];
+                code.extend(report(true));
+                assert!(passed(&code, model), "{:?} {:#06X}", model, 0xFF00 | address as u16);
+            }
+        }
+    }
+
+    #[test]
+    fn oam_bug_only_on_monochrome_models() {
+        // INC HL; DEC HL
+        let increments = [0x23, 0x2B];
+        for model in MODELS {
+            let intact = oam_after(&increments, model).iter().enumerate().all(|(index, &object)| {
+                let address = index as u8 * 4;
+                object == (address, address + 1, address + 2)
+            });
+            assert_eq!(intact, model.is_color(), "{:?}", model);
+        }
+    }
+
+    #[test]
+    fn oam_bug_read_during_increment() {
+        // LD A, (HL+); LD L, 0 against LD A, (HL); LD L, 0, which take the same number of cycles
+        let read_increment = [0x2A, 0x2E, 0x00];
+        let read = [0x7E, 0x2E, 0x00];
+        let untouched = oam_after(&[0x00, 0x00, 0x00, 0x00], HardwareModel::Dmg);
+
+        let corrupted = oam_after(&read_increment, HardwareModel::Dmg);
+        assert_ne!(corrupted, untouched);
+        assert_ne!(corrupted, oam_after(&read, HardwareModel::Dmg));
+        assert_eq!(
+            oam_after(&read_increment, HardwareModel::Cgb),
+            oam_after(&[0x00, 0x00, 0x00, 0x00], HardwareModel::Cgb)
+        );
+    }
+
+    #[test]
+    fn wave_ram_while_playing() {
+        for model in MODELS {
+            // Clear wave RAM, then trigger channel 3 and read FF30 while it plays
+            let mut code = vec![0xAF, 0x21, 0x30, 0xFF];
+            // LD (HL+), A; BIT 6, L; JR Z, -5
+            code.extend([0x22, 0xCB, 0x75, 0x28, 0xFB]);
+            // LD A, 0x80; LDH (NR30), A; LDH (NR34), A with the slowest period; NOP x4; LDH A, (FF30)
+            code.extend([0x3E, 0x80, 0xE0, 0x1A, 0xE0, 0x1E]);
+            code.extend([0x00, 0x00, 0x00, 0x00, 0xF0, 0x30, 0xFE, 0xFF]);
+            // DMG only reads wave RAM on the cycle the channel fetches a sample, otherwise it reads 0xFF
+            code.extend(report(!model.is_color()));
+            assert!(passed(&code, model), "{:?}", model);
+        }
+    }
+}
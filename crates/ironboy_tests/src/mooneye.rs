@@ -0,0 +1,112 @@
+use std::{fs, path::Path};
+
+use ironboy_core::{GameBoy, GameBoyConfig, HardwareModel, LoadMode};
+
+/// A Mooneye Test Suite release, the ROMs are built with rgbds so they aren't a submodule
+pub const MOONEYE_DIRECTORY: &str = "../../external/mts";
+const PASS: [u8; 6] = [3, 5, 8, 13, 21, 34];
+const FRAME_LIMIT: usize = 60 * 20;
+
+/// Runs a Mooneye test until it reports over serial, it sends the Fibonacci numbers on success and 0x42 on failure
+pub fn run_mooneye(path: &Path, model: HardwareModel) -> Result<(), String> {
+    let rom = fs::read(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+    let config = GameBoyConfig {
+        load_mode: LoadMode::Lenient,
+        model: Some(model),
+        ..Default::default()
+    };
+    let mut game_boy = GameBoy::with_config(&path.to_string_lossy(), rom, config).map_err(|error| format!("{}: {}", path.display(), error))?;
+
+    for _ in 0..FRAME_LIMIT {
+        game_boy.run_until_frame_complete();
+        let output = game_boy.serial_output();
+        if output.len() >= PASS.len() {
+            return match output[..PASS.len()] == PASS {
+                true => Ok(()),
+                false => Err(format!("{}: failed with {:02X?}", path.display(), output)),
+            };
+        }
+    }
+    Err(format!("{}: timed out", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::support::rom_with_code;
+
+    /// Sends the bytes over serial the way Mooneye tests report, then spins
+    fn reporting_rom(bytes: [u8; 6]) -> Vec<u8> {
+        // LD A, byte; LDH (SB), A; LD A, 0x81; LDH (SC), A
+        let mut code: Vec<u8> = bytes.iter().flat_map(|byte| [0x3E, *byte, 0xE0, 0x01, 0x3E, 0x81, 0xE0, 0x02]).collect();
+        code.extend([0x18, 0xFE]);
+        rom_with_code(&code, 0x00)
+    }
+
+    fn run_all(tests: &[(&str, HardwareModel)]) {
+        let directory = Path::new(MOONEYE_DIRECTORY);
+        assert!(directory.is_dir(), "Extract a Mooneye Test Suite release into {}", MOONEYE_DIRECTORY);
+
+        let failures: Vec<String> = tests
+            .iter()
+            .filter_map(|(rom, model)| run_mooneye(&directory.join(rom), *model).err())
+            .collect();
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+
+    #[test]
+    #[ignore = "needs a Mooneye Test Suite release in external/mts"]
+    fn mooneye_stat() {
+        run_all(&[
+            ("acceptance/ppu/stat_irq_blocking.gb", HardwareModel::Dmg),
+            ("acceptance/ppu/stat_lyc_onoff.gb", HardwareModel::Dmg),
+            ("acceptance/ppu/stat_irq_blocking.gb", HardwareModel::Cgb),
+            ("acceptance/ppu/stat_lyc_onoff.gb", HardwareModel::Cgb),
+        ]);
+    }
+
+    #[test]
+    #[ignore = "needs a Mooneye Test Suite release in external/mts"]
+    fn mooneye_lcdon_timing() {
+        run_all(&[
+            ("acceptance/ppu/lcdon_timing-GS.gb", HardwareModel::Dmg),
+            ("acceptance/ppu/lcdon_write_timing-GS.gb", HardwareModel::Dmg),
+        ]);
+    }
+
+    #[test]
+    fn mooneye_serial_protocol() {
+        let path = env::temp_dir().join("ironboy_mooneye_protocol.gb");
+        fs::write(&path, reporting_rom(PASS)).expect("Unable to write test rom");
+        assert_eq!(run_mooneye(&path, HardwareModel::Dmg), Ok(()));
+
+        fs::write(&path, reporting_rom([0x42; 6])).expect("Unable to write test rom");
+        assert!(run_mooneye(&path, HardwareModel::Dmg).is_err());
+        fs::remove_file(&path).expect("Unable to remove test rom");
+    }
+
+    #[test]
+    fn serial_output_is_capped() {
+        // INC A; LDH (SB), A; PUSH AF; LD A, 0x81; LDH (SC), A; POP AF; JR -11
+        let rom = rom_with_code(&[0x3C, 0xE0, 0x01, 0xF5, 0x3E, 0x81, 0xE0, 0x02, 0xF1, 0x18, 0xF5], 0x00);
+        let mut game_boy = GameBoy::with_config(
+            "serial.gb",
+            rom,
+            GameBoyConfig {
+                load_mode: LoadMode::Lenient,
+                ..Default::default()
+            },
+        )
+        .expect("Unable to load test rom");
+        for _ in 0..20 {
+            game_boy.run_until_frame_complete();
+        }
+
+        let output = game_boy.serial_output();
+        assert!((0x0800..=0x1000).contains(&output.len()), "{}", output.len());
+        // The newest bytes are the ones kept
+        assert!(output.windows(2).all(|pair| pair[1] == pair[0].wrapping_add(1)));
+    }
+}
@@ -0,0 +1,90 @@
+use ironboy_core::{GameBoy, HardwareModel};
+
+use crate::{frames::run_code, models::first_pixel};
+
+/// LDH A, (LY); CP ly; JR NZ, -6
+pub fn wait_for_ly(ly: u8) -> [u8; 6] {
+    [0xF0, 0x44, 0xFE, ly, 0x20, 0xFA]
+}
+
+/// Sets BGP to black when the zero flag matches and to white otherwise, then spins
+pub fn report(zero: bool) -> [u8; 9] {
+    let jump = if zero { 0x28 } else { 0x20 };
+    [0x3E, 0xFF, jump, 0x01, 0xAF, 0xE0, 0x47, 0x18, 0xFE]
+}
+
+/// Runs a test program long enough for the BGP it reports to show up on screen
+pub fn passed(code: &[u8], model: HardwareModel) -> bool {
+    let mut game_boy: GameBoy = run_code(code, model);
+    for _ in 0..4 {
+        game_boy.run_until_frame_complete();
+    }
+    first_pixel(&game_boy) == (0, 0, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Enables the given STAT sources, clears IF in VBlank and checks whether the STAT interrupt was requested once
+    /// line 0 starts
+    fn stat_interrupt_after_vblank(stat: u8) -> Vec<u8> {
+        let mut code = vec![0xF3, 0x3E, stat, 0xE0, 0x41];
+        code.extend(wait_for_ly(150));
+        code.extend([0xAF, 0xE0, 0x0F]);
+        code.extend(wait_for_ly(0));
+        // LDH A, (STAT); AND 3; CP 1; JR Z, -8; LDH A, (IF); AND 2
+        code.extend([0xF0, 0x41, 0xE6, 0x03, 0xFE, 0x01, 0x28, 0xF8, 0xF0, 0x0F, 0xE6, 0x02]);
+        code
+    }
+
+    #[test]
+    fn mode2_interrupt_on_line_0() {
+        let mut code = stat_interrupt_after_vblank(0x20);
+        code.extend(report(false));
+        assert!(passed(&code, HardwareModel::Dmg));
+    }
+
+    #[test]
+    fn stat_interrupt_blocked_while_line_is_high() {
+        // Mode 1 holds the line high through VBlank, so entering mode 2 on line 0 is not a rising edge
+        let mut code = stat_interrupt_after_vblank(0x30);
+        code.extend(report(true));
+        assert!(passed(&code, HardwareModel::Dmg));
+    }
+
+    #[test]
+    fn ly_153_reads_0_early() {
+        let mut code = vec![0xF3];
+        code.extend(wait_for_ly(152));
+        code.extend(wait_for_ly(0));
+        // LDH A, (STAT); AND 3; CP 1
+        code.extend([0xF0, 0x41, 0xE6, 0x03, 0xFE, 0x01]);
+        code.extend(report(true));
+        assert!(passed(&code, HardwareModel::Dmg));
+    }
+
+    #[test]
+    fn lyc_0_matches_during_line_153() {
+        // LD A, 0; LDH (LYC), A
+        let mut code = vec![0xF3, 0xAF, 0xE0, 0x45];
+        code.extend(wait_for_ly(152));
+        code.extend(wait_for_ly(0));
+        // LDH A, (STAT); AND 7; CP 5, the coincidence flag is set while still in VBlank
+        code.extend([0xF0, 0x41, 0xE6, 0x07, 0xFE, 0x05]);
+        code.extend(report(true));
+        assert!(passed(&code, HardwareModel::Dmg));
+    }
+
+    #[test]
+    fn dmg_stat_write_spurious_interrupt() {
+        for (model, spurious) in [(HardwareModel::Dmg, true), (HardwareModel::Cgb, false)] {
+            let mut code = vec![0xF3];
+            code.extend(wait_for_ly(145));
+            // XOR A; LDH (IF), A; LDH (STAT), A; LDH A, (IF); AND 2
+            code.extend([0xAF, 0xE0, 0x0F, 0xE0, 0x41, 0xF0, 0x0F, 0xE6, 0x02]);
+            code.extend(report(!spurious));
+            assert!(passed(&code, model), "{:?}", model);
+        }
+    }
+}
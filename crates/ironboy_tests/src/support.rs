@@ -0,0 +1,9 @@
+/// A 32 KiB ROM only cartridge with the given CGB flag that jumps over the header to the code
+pub fn rom_with_code(code: &[u8], cgb_flag: u8) -> Vec<u8> {
+    let mut rom = vec![0; 0x8000];
+    // NOP; JP 0x0150
+    rom[0x0100..0x0104].copy_from_slice(&[0x00, 0xC3, 0x50, 0x01]);
+    rom[0x0143] = cgb_flag;
+    rom[0x0150..0x0150 + code.len()].copy_from_slice(code);
+    rom
+}
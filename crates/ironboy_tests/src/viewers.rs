@@ -0,0 +1,154 @@
+use ironboy_core::{GameBoy, HardwareModel};
+
+use crate::frames::run_code;
+
+/// Writes tile 1's first row and reversed BGP with the LCD off, runs `extra` and spins
+pub fn tile_setup(model: HardwareModel, extra: &[u8]) -> GameBoy {
+    // DI; XOR A; LDH (0x40), A; LD A, 0xF0; LD (0x8010), A; LD A, 0xCC; LD (0x8011), A;
+    // LD A, 0x1B; LDH (0x47), A; LD A, 0xE4; LDH (0x49), A
+    let mut code = vec![
+        0xF3, 0xAF, 0xE0, 0x40, 0x3E, 0xF0, 0xEA, 0x10, 0x80, 0x3E, 0xCC, 0xEA, 0x11, 0x80, 0x3E, 0x1B, 0xE0, 0x47, 0x3E, 0xE4, 0xE0, 0x49,
+    ];
+    code.extend(extra);
+    // JR -2
+    code.extend([0x18, 0xFE]);
+    let mut game_boy = run_code(&code, model);
+    game_boy.run_until_frame_complete();
+    game_boy.run_until_frame_complete();
+    game_boy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        layers::run_with_options,
+        models::{boot, pixel, rom_with_cgb_flag},
+        support::rom_with_code,
+    };
+    use ironboy_core::{PalettePreset, RenderOptions, TILE_MAP_SIZE, TILES_PER_BANK, TileMapLayer, ViewerPalette, tile_color_indices};
+
+    #[test]
+    fn decodes_tiles() {
+        let game_boy = tile_setup(HardwareModel::Dmg, &[]);
+        let vram_bank = game_boy.vram_bank(0).expect("Bank 0 always exists");
+        assert_eq!(vram_bank.len(), TILES_PER_BANK * 16 + 0x800);
+        assert_eq!(tile_color_indices(vram_bank, 1)[..8], [3, 3, 1, 1, 2, 2, 0, 0]);
+        assert_eq!(tile_color_indices(vram_bank, 1)[8..], [0; 56]);
+        assert!(game_boy.vram_bank(1).is_some_and(|bank| bank.iter().all(|&byte| byte == 0)));
+        assert_eq!(game_boy.vram_bank(2), None);
+    }
+
+    #[test]
+    fn palette_colors() {
+        let game_boy = tile_setup(HardwareModel::Dmg, &[]);
+        let grayscale = PalettePreset::Grayscale.palette().bg;
+        assert_eq!(game_boy.viewer_palette_colors(ViewerPalette::Grayscale), grayscale);
+        assert_eq!(
+            game_boy.viewer_palette_colors(ViewerPalette::Background(0)),
+            [grayscale[3], grayscale[2], grayscale[1], grayscale[0]]
+        );
+        assert_eq!(game_boy.viewer_palette_colors(ViewerPalette::Object(1)), grayscale);
+    }
+
+    #[test]
+    fn palette_indices_wrap() {
+        let game_boy = boot(rom_with_cgb_flag(0xC0), Some(HardwareModel::Cgb));
+        for (palette, wrapped) in [(8, 0), (15, 7), (0xFF, 7)] {
+            assert_eq!(
+                game_boy.viewer_palette_colors(ViewerPalette::Background(palette)),
+                game_boy.viewer_palette_colors(ViewerPalette::Background(wrapped))
+            );
+            assert_eq!(
+                game_boy.viewer_palette_colors(ViewerPalette::Object(palette)),
+                game_boy.viewer_palette_colors(ViewerPalette::Object(wrapped))
+            );
+        }
+    }
+
+    #[test]
+    fn tile_maps() {
+        // LD A, 1; LD (0x9801), A; LD A, 5; LDH (0x43), A; LD A, 9; LDH (0x42), A;
+        // LD A, 20; LDH (0x4B), A; LD A, 30; LDH (0x4A), A; LD A, 0x71; LDH (0x40), A
+        let extra = [
+            0x3E, 0x01, 0xEA, 0x01, 0x98, 0x3E, 0x05, 0xE0, 0x43, 0x3E, 0x09, 0xE0, 0x42, 0x3E, 0x14, 0xE0, 0x4B, 0x3E, 0x1E, 0xE0, 0x4A, 0x3E, 0x71,
+            0xE0, 0x40,
+        ];
+        let game_boy = tile_setup(HardwareModel::Dmg, &extra);
+        let grayscale = PalettePreset::Grayscale.palette().bg;
+
+        let background = game_boy.tile_map_view(TileMapLayer::Background);
+        assert_eq!(background.colors.len(), TILE_MAP_SIZE * TILE_MAP_SIZE);
+        assert_eq!(
+            (background.map_address, background.x, background.y, background.enabled),
+            (0x9800, 5, 9, true)
+        );
+        // Tile 0 is empty and BGP is reversed
+        assert_eq!(background.colors[0], grayscale[3]);
+        assert_eq!(background.colors[8], grayscale[0]);
+        assert_eq!(background.colors[10], grayscale[2]);
+        assert_eq!(background.colors[TILE_MAP_SIZE + 8], grayscale[3]);
+
+        let window = game_boy.tile_map_view(TileMapLayer::Window);
+        assert_eq!((window.map_address, window.x, window.y, window.enabled), (0x9C00, 20, 30, true));
+        assert_eq!(window.colors[8], grayscale[3]);
+    }
+
+    #[test]
+    fn window_hidden_with_the_background_outside_color_mode() {
+        // LD A, 0xF0; LDH (0x40), A
+        let extra = [0x3E, 0xF0, 0xE0, 0x40];
+        for model in [HardwareModel::Dmg, HardwareModel::Cgb] {
+            let game_boy = tile_setup(model, &extra);
+            assert!(!game_boy.tile_map_view(TileMapLayer::Window).enabled, "{:?}", model);
+        }
+    }
+
+    #[test]
+    fn objects() {
+        let game_boy = run_with_options(0x93, RenderOptions::default());
+        let objects = game_boy.object_views();
+        assert_eq!(objects.len(), 40);
+        assert_eq!((objects[1].x, objects[1].y, objects[1].tile), (18, 66, 1));
+        assert_eq!(objects[0].pixels.len(), 64);
+        assert!(objects[0].pixels.iter().all(|&color| color == Some(pixel(&game_boy, 50, 50))));
+        // The 11th object on lines 50-57 is the only one over the limit
+        let dropped: Vec<_> = objects.iter().map(|object| object.dropped).collect();
+        assert_eq!(dropped.iter().position(|&dropped| dropped), Some(10));
+        assert_eq!(dropped.iter().filter(|&&dropped| dropped).count(), 1);
+
+        let render_options = RenderOptions {
+            unlimited_objects: true,
+            ..Default::default()
+        };
+        let game_boy = run_with_options(0x93, render_options);
+        assert!(game_boy.object_views().iter().all(|object| !object.dropped));
+    }
+
+    #[test]
+    fn dmg_palettes() {
+        let game_boy = tile_setup(HardwareModel::Dmg, &[]);
+        let grayscale = PalettePreset::Grayscale.palette().bg;
+        let palettes = game_boy.palette_view();
+        assert_eq!(palettes.dmg_registers[0], 0x1B);
+        assert_eq!(palettes.dmg_registers[2], 0xE4);
+        assert_eq!(palettes.dmg_colors[0], [grayscale[3], grayscale[2], grayscale[1], grayscale[0]]);
+        assert_eq!(palettes.dmg_colors[2], grayscale);
+    }
+
+    #[test]
+    fn cgb_palettes() {
+        // LD A, 0x8A; LDH (0x68), A; LD A, 0x1F; LDH (0x69), A; LD A, 0x7C; LDH (0x69), A; JR -2
+        let rom = rom_with_code(
+            &[0x3E, 0x8A, 0xE0, 0x68, 0x3E, 0x1F, 0xE0, 0x69, 0x3E, 0x7C, 0xE0, 0x69, 0x18, 0xFE],
+            0x80,
+        );
+        let mut game_boy = boot(rom, Some(HardwareModel::Cgb));
+        game_boy.run_until_frame_complete();
+
+        let palettes = game_boy.palette_view();
+        assert_eq!(palettes.cgb_background[1][1], 0x7C1F);
+        assert_eq!(palettes.cgb_background_colors[1][1], (255, 0, 255));
+        assert_eq!(palettes.cgb_object[1][1], 0x0000);
+    }
+}
@@ -0,0 +1,127 @@
+use ironboy_core::{GameBoy, HardwareModel};
+
+use crate::{frames::run_code, stat::wait_for_ly};
+
+pub const WHITE: (u8, u8, u8) = (255, 255, 255);
+pub const BLACK: (u8, u8, u8) = (0, 0, 0);
+
+/// Turns the LCD off, fills the window map with tile 1 and turns the LCD back on with the window at WX and WY.
+/// Tile 1 is black on every row, or only on its first row when `first_row_only` is set
+pub fn window_setup(wx: u8, wy: u8, first_row_only: bool) -> Vec<u8> {
+    let mut code = vec![0xF3, 0xAF, 0xE0, 0x40];
+    // LD HL, 0x8010; LD A, 0xFF; LD B, rows * 2; LD (HL+), A; DEC B; JR NZ, -4
+    let tile_bytes = if first_row_only { 2 } else { 16 };
+    code.extend([0x21, 0x10, 0x80, 0x3E, 0xFF, 0x06, tile_bytes, 0x22, 0x05, 0x20, 0xFC]);
+    // LD HL, 0x9C00; LD A, 1; LD (HL+), A; BIT 5, H; JR Z, -5
+    code.extend([0x21, 0x00, 0x9C, 0x3E, 0x01, 0x22, 0xCB, 0x6C, 0x28, 0xFB]);
+    // BGP, WY and WX, then the LCD on with the window using the 0x9C00 map
+    code.extend([0x3E, 0xE4, 0xE0, 0x47, 0x3E, wy, 0xE0, 0x4A, 0x3E, wx, 0xE0, 0x4B, 0x3E, 0xF1, 0xE0, 0x40]);
+    code
+}
+
+/// The setup followed by a loop that runs once per frame, starting from line 153
+pub fn frame_loop_code(setup: Vec<u8>, frame_loop: &[u8]) -> Vec<u8> {
+    let mut code = setup;
+    code.extend(wait_for_ly(0));
+    code.extend(frame_loop);
+    // Back over the loop, the wait for line 0 and the JR itself
+    let jump = i8::try_from(-(frame_loop.len() as i16 + 8)).expect("Frame loop is too long for a relative jump");
+    code.extend([0x18, jump as u8]);
+    code
+}
+
+pub fn run_frames(game_boy: &mut GameBoy) {
+    for _ in 0..4 {
+        game_boy.run_until_frame_complete();
+    }
+}
+
+pub fn run_window_test(setup: Vec<u8>, frame_loop: &[u8], model: HardwareModel) -> GameBoy {
+    let mut game_boy = run_code(&frame_loop_code(setup, frame_loop), model);
+    run_frames(&mut game_boy);
+    game_boy
+}
+
+/// LD A, value; LDH (register), A
+pub fn write_register(register: u8, value: u8) -> [u8; 4] {
+    [0x3E, value, 0xE0, register]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::pixel;
+
+    #[test]
+    fn window_position() {
+        let game_boy = run_window_test(window_setup(47, 16, false), &[], HardwareModel::Dmg);
+        assert_eq!(pixel(&game_boy, 40, 15), WHITE);
+        assert_eq!(pixel(&game_boy, 39, 16), WHITE);
+        assert_eq!(pixel(&game_boy, 40, 16), BLACK);
+        assert_eq!(pixel(&game_boy, 159, 143), BLACK);
+
+        // WX below 7 hides the left of the window instead of being ignored
+        let game_boy = run_window_test(window_setup(3, 16, false), &[], HardwareModel::Dmg);
+        assert_eq!(pixel(&game_boy, 0, 16), BLACK);
+    }
+
+    #[test]
+    fn wx_166_spans_next_line() {
+        let game_boy = run_window_test(window_setup(166, 16, false), &[], HardwareModel::Dmg);
+        assert_eq!(pixel(&game_boy, 159, 16), WHITE);
+        assert_eq!(pixel(&game_boy, 0, 17), BLACK);
+        assert_eq!(pixel(&game_boy, 0, 18), BLACK);
+    }
+
+    #[test]
+    fn line_counter_only_advances_when_drawn() {
+        // The window draws lines 0-2, then resumes on line 20 with its fourth line, so its next first tile row is at 25
+        let mut frame_loop = Vec::from(write_register(0x40, 0xF1));
+        frame_loop.extend(wait_for_ly(3));
+        frame_loop.extend(write_register(0x40, 0xD1));
+        frame_loop.extend(wait_for_ly(20));
+        frame_loop.extend(write_register(0x40, 0xF1));
+        let game_boy = run_window_test(window_setup(7, 0, true), &frame_loop, HardwareModel::Dmg);
+        assert_eq!(pixel(&game_boy, 0, 0), BLACK);
+        assert_eq!(pixel(&game_boy, 0, 10), WHITE);
+        assert_eq!(pixel(&game_boy, 0, 24), WHITE);
+        assert_eq!(pixel(&game_boy, 0, 25), BLACK);
+    }
+
+    #[test]
+    fn wy_only_matches_at_line_start() {
+        // WY moves to a line that has already passed, so it never matches
+        let mut frame_loop = Vec::from(write_register(0x4A, 200));
+        frame_loop.extend(wait_for_ly(50));
+        frame_loop.extend(write_register(0x4A, 40));
+        let game_boy = run_window_test(window_setup(7, 200, false), &frame_loop, HardwareModel::Dmg);
+        assert_eq!(pixel(&game_boy, 0, 60), WHITE);
+
+        // Once WY has matched, moving it away does not hide the window
+        let mut frame_loop = Vec::from(write_register(0x4A, 10));
+        frame_loop.extend(wait_for_ly(20));
+        frame_loop.extend(write_register(0x4A, 200));
+        let game_boy = run_window_test(window_setup(7, 10, false), &frame_loop, HardwareModel::Dmg);
+        assert_eq!(pixel(&game_boy, 0, 9), WHITE);
+        assert_eq!(pixel(&game_boy, 0, 60), BLACK);
+    }
+
+    #[test]
+    fn dmg_wx_write_glitch_pixel() {
+        // Moves WX to 150 early in mode 3 of line 40 while the window is already drawing
+        let mut frame_loop = Vec::from(write_register(0x4B, 7));
+        frame_loop.extend(wait_for_ly(40));
+        // LD B, 150; LDH A, (STAT); AND 3; CP 3; JR NZ, -8; LD A, B; LDH (WX), A
+        frame_loop.extend([0x06, 150, 0xF0, 0x41, 0xE6, 0x03, 0xFE, 0x03, 0x20, 0xF8, 0x78, 0xE0, 0x4B]);
+
+        let game_boy = run_window_test(window_setup(7, 0, false), &frame_loop, HardwareModel::Dmg);
+        assert_eq!(pixel(&game_boy, 142, 40), BLACK);
+        assert_eq!(pixel(&game_boy, 143, 40), WHITE);
+        assert_eq!(pixel(&game_boy, 144, 40), BLACK);
+        assert_eq!(pixel(&game_boy, 0, 41), WHITE);
+        assert_eq!(pixel(&game_boy, 143, 41), BLACK);
+
+        let game_boy = run_window_test(window_setup(7, 0, false), &frame_loop, HardwareModel::Cgb);
+        assert_eq!(pixel(&game_boy, 143, 40), BLACK);
+    }
+}